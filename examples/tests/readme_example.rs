@@ -0,0 +1,72 @@
+use kuri::transport::ChildProcessTransport;
+use kuri_client::Client;
+use kuri_mcp_protocol::content::Content;
+use serde_json::json;
+use std::{collections::HashMap, path::PathBuf};
+use tokio::process::Command;
+
+// Launches the compiled `01_readme_example` binary, and talks to it with a client
+
+/// Build an example, returning the path to its binary.
+fn build_example(name: &str) -> PathBuf {
+    let status = std::process::Command::new(env!("CARGO"))
+        .args(["build", "--quiet", "--example", name, "--manifest-path"])
+        .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "Failed to build example {name}");
+
+    // Test binaries are in `target/<profile>/deps`, and examples in `target/<profile>/examples`
+    let exe = std::env::current_exe().unwrap();
+    exe.parent()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .join("examples")
+        .join(name)
+}
+
+#[tokio::test]
+async fn test_readme_example() {
+    let command = Command::new(build_example("01_readme_example"));
+    let transport = ChildProcessTransport::spawn(command).unwrap();
+    let process = transport.process();
+
+    let client = Client::connect(transport);
+    let result = client.initialize().await.unwrap();
+    assert!(result.capabilities.tools.is_some());
+
+    let tools = client.list_tools().await.unwrap().tools;
+    assert_eq!(tools[0].name, "calculator");
+
+    let result = client
+        .call_tool(
+            "calculator",
+            json!({ "x": 4, "y": 5, "operation": "multiply" }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.content, vec![Content::text("20")]);
+
+    let result = client
+        .call_tool(
+            "calculator",
+            json!({ "x": 1, "y": 0, "operation": "divide" }),
+        )
+        .await
+        .unwrap();
+    assert!(result.is_error);
+
+    let result = client
+        .get_prompt(
+            "summarise_text",
+            HashMap::from([("text".to_string(), "Hello".to_string())]),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result.messages.len(), 1);
+
+    // Closing the connection stops the server
+    drop(client);
+    assert!(process.wait().await.is_some());
+}
@@ -0,0 +1,97 @@
+//! Handler wrappers used when composing services with [`MCPServiceBuilder::nest`].
+//!
+//! [`MCPServiceBuilder::nest`]: crate::MCPServiceBuilder::nest
+
+use crate::{
+    context::{Context, RequestContext},
+    extract::ExtractError,
+    handler::{PromptHandler, ToolHandler},
+};
+use async_trait::async_trait;
+use kuri_mcp_protocol::{
+    messages::{CallToolResult, GetPromptResult},
+    prompt::{PromptArgument, PromptError},
+    tool::{ToolAnnotations, ToolError},
+};
+use serde_json::Value;
+use std::{collections::HashMap, sync::Arc};
+
+/// A tool or prompt served under a prefixed name. Everything else is passed through to the inner
+/// handler, which is unaware of the prefix.
+pub(crate) struct Prefixed<H: ?Sized> {
+    name: String,
+    inner: Arc<H>,
+}
+
+impl<H: ?Sized> Prefixed<H> {
+    pub(crate) fn new(prefix: &str, name: &str, inner: Arc<H>) -> Self {
+        Self {
+            name: format!("{prefix}{name}"),
+            inner,
+        }
+    }
+}
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl ToolHandler for Prefixed<dyn ToolHandler> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        self.inner.description()
+    }
+
+    fn schema(&self) -> Value {
+        self.inner.schema()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.inner.output_schema()
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        self.inner.annotations()
+    }
+
+    fn verify_state(&self, state: &Context) -> Result<(), ExtractError> {
+        self.inner.verify_state(state)
+    }
+
+    async fn call(
+        &self,
+        context: &RequestContext,
+        params: Value,
+    ) -> Result<CallToolResult, ToolError> {
+        self.inner.call(context, params).await
+    }
+}
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl PromptHandler for Prefixed<dyn PromptHandler> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.inner.description()
+    }
+
+    fn arguments(&self) -> Option<Vec<PromptArgument>> {
+        self.inner.arguments()
+    }
+
+    fn verify_state(&self, state: &Context) -> Result<(), ExtractError> {
+        self.inner.verify_state(state)
+    }
+
+    async fn call(
+        &self,
+        context: &RequestContext,
+        params: HashMap<String, Value>,
+    ) -> Result<GetPromptResult, PromptError> {
+        self.inner.call(context, params).await
+    }
+}
@@ -1,11 +1,30 @@
+use crate::{
+    extract::{ClientInfo, ExtractError},
+    maybe_send::MaybeSendSync,
+    peer::Peer,
+    registry::Registry,
+    session::SessionState,
+};
+use kuri_mcp_protocol::jsonrpc::RequestId;
 use serde::{de, Serialize};
+use serde_json::{Map, Value};
 use std::{
     any::{Any, TypeId},
-    collections::HashMap,
+    collections::{hash_map::Entry, HashMap},
     ops::Deref,
     sync::Arc,
 };
 
+#[cfg(feature = "send")]
+pub(crate) type AnyState = dyn Any + Send + Sync;
+#[cfg(not(feature = "send"))]
+pub(crate) type AnyState = dyn Any;
+
+#[cfg(feature = "send")]
+type SessionStateFactory = dyn Fn(&RequestContext) -> Box<AnyState> + Send + Sync;
+#[cfg(not(feature = "send"))]
+type SessionStateFactory = dyn Fn(&RequestContext) -> Box<AnyState>;
+
 /// Registry of types that may be injected in MCPService tool handlers. Any state in the Context is
 /// global: it is shared and persisted throughout requests, *not* transient for the lifetime of a
 /// single request.
@@ -14,13 +33,20 @@ use std::{
 #[derive(Default)]
 pub struct Context {
     /// A map from type to the injected tool.
-    map: HashMap<TypeId, Box<dyn Any>>,
+    map: HashMap<TypeId, Box<AnyState>>,
+    /// Factories for session-scoped state, keyed by the type of state they create. Each is run when
+    /// a session is initialised.
+    session_factories: HashMap<TypeId, Box<SessionStateFactory>>,
+    /// The name of each type of state (and session state) held, to report conflicts.
+    type_names: HashMap<TypeId, &'static str>,
 }
 
 impl Context {
     /// Register a type T in the server's context.
-    pub fn insert<T: 'static>(&mut self, state: Inject<T>) {
-        self.map.insert(TypeId::of::<Inject<T>>(), Box::new(state));
+    pub fn insert<T: MaybeSendSync + 'static>(&mut self, state: Inject<T>) {
+        let type_id = TypeId::of::<Inject<T>>();
+        self.map.insert(type_id, Box::new(state));
+        self.type_names.insert(type_id, std::any::type_name::<T>());
     }
 
     /// Get a reference to a type T from the context.
@@ -29,13 +55,154 @@ impl Context {
             .get(&TypeId::of::<T>())
             .and_then(|boxed| boxed.downcast_ref())
     }
+
+    /// Add the state of `other` to this context. Where both hold state (or session state) of the
+    /// same type, this context's is kept, and the type's name is returned.
+    pub(crate) fn merge(&mut self, other: Context) -> Vec<&'static str> {
+        let mut conflicts = Vec::new();
+        for (type_id, state) in other.map {
+            match self.map.entry(type_id) {
+                Entry::Occupied(_) => conflicts.push(other.type_names[&type_id]),
+                Entry::Vacant(entry) => {
+                    entry.insert(state);
+                }
+            }
+        }
+        for (type_id, factory) in other.session_factories {
+            match self.session_factories.entry(type_id) {
+                Entry::Occupied(_) => conflicts.push(other.type_names[&type_id]),
+                Entry::Vacant(entry) => {
+                    entry.insert(factory);
+                }
+            }
+        }
+        self.type_names.extend(other.type_names);
+        conflicts
+    }
+
+    /// Register a factory creating each session's state of type T.
+    pub(crate) fn insert_session_factory<T, F>(&mut self, factory: F)
+    where
+        T: MaybeSendSync + 'static,
+        F: Fn(&RequestContext) -> T + MaybeSendSync + 'static,
+    {
+        let type_id = TypeId::of::<T>();
+        self.session_factories.insert(
+            type_id,
+            Box::new(move |ctx| Box::new(Arc::new(factory(ctx)))),
+        );
+        self.type_names.insert(type_id, std::any::type_name::<T>());
+    }
+
+    /// Whether session state of type T is created for each session.
+    pub(crate) fn has_session_state<T: 'static>(&self) -> bool {
+        self.session_factories.contains_key(&TypeId::of::<T>())
+    }
+
+    /// Create the state for a new session, by running each factory. `ctx` is the context of the
+    /// request initialising the session. Each value is an `Arc<T>`, keyed by the TypeId of T.
+    pub(crate) fn create_session_state(
+        &self,
+        ctx: &RequestContext,
+    ) -> HashMap<TypeId, Box<AnyState>> {
+        self.session_factories
+            .iter()
+            .map(|(type_id, factory)| (*type_id, factory(ctx)))
+            .collect()
+    }
+}
+
+/// The context of a single request, passed to handlers.
+///
+/// This gives access to the service's global state (the [`Context`]), along with details of the
+/// request being handled and the session it belongs to. Handlers defined with `#[tool]` or
+/// `#[prompt]` usually don't use this directly, and instead take [extractors] as parameters.
+///
+/// [extractors]: crate::extract
+#[derive(Clone)]
+pub struct RequestContext {
+    state: Arc<Context>,
+    registry: Registry,
+    id: RequestId,
+    meta: Option<Map<String, Value>>,
+    session: Option<Arc<SessionState>>,
+}
+
+impl RequestContext {
+    /// Create the context for a request, within the current session (if any).
+    pub(crate) fn new(
+        state: Arc<Context>,
+        registry: Registry,
+        id: RequestId,
+        meta: Option<Map<String, Value>>,
+    ) -> Self {
+        Self {
+            state,
+            registry,
+            id,
+            meta,
+            session: SessionState::current(),
+        }
+    }
+
+    /// The service's global state.
+    pub fn state(&self) -> &Context {
+        &self.state
+    }
+
+    /// The registry of the service handling the request.
+    pub fn registry(&self) -> &Registry {
+        &self.registry
+    }
+
+    /// The JSON-RPC ID of the request.
+    pub fn request_id(&self) -> &RequestId {
+        &self.id
+    }
+
+    /// The request's metadata (the `_meta` field of its params), if any.
+    pub fn meta(&self) -> Option<&Map<String, Value>> {
+        self.meta.as_ref()
+    }
+
+    /// Details of the client, if it has initialised the session.
+    pub fn client_info(&self) -> Option<ClientInfo> {
+        self.session
+            .as_ref()
+            .and_then(|session| session.client_info())
+    }
+
+    /// The peer of the session, which may be used to message the client. Only available when the
+    /// request is handled within a session (eg when served by [`serve`]).
+    ///
+    /// [`serve`]: crate::serve
+    pub fn peer(&self) -> Option<&Peer> {
+        self.session.as_ref().map(|session| session.peer())
+    }
+
+    /// The session the request belongs to, if any.
+    pub(crate) fn session(&self) -> Option<&SessionState> {
+        self.session.as_deref()
+    }
 }
 
 /// A trait to go from a Context to a type T.
 ///
 /// Implementing this for a type allows it to be directly injected into tool handlers as a parameter.
-pub trait FromContext {
-    fn from_context(ctx: &Context) -> Self;
+/// If the type can't be obtained (for example, because it wasn't registered with the service), the
+/// request fails with an internal error.
+pub trait FromContext: Sized {
+    fn from_context(ctx: &Context) -> Result<Self, ExtractError>;
+
+    /// Check that the type can be obtained from the context, without needing a request. Used to
+    /// verify handlers' dependencies when the service is built, in [`MCPServiceBuilder::try_build`].
+    ///
+    /// By default, this attempts to obtain the type.
+    ///
+    /// [`MCPServiceBuilder::try_build`]: crate::MCPServiceBuilder::try_build
+    fn verify(ctx: &Context) -> Result<(), ExtractError> {
+        Self::from_context(ctx).map(|_| ())
+    }
 }
 
 /// Inject wraps types that can be injected into tool handler functions. These allow tool handlers
@@ -113,11 +280,9 @@ where
 
 /// Implement ability to get a `Inject<T>` from the server's context
 impl<T: 'static> FromContext for Inject<T> {
-    fn from_context(ctx: &Context) -> Self {
-        if let Some(obj) = ctx.get::<Inject<T>>() {
-            obj.clone()
-        } else {
-            panic!("Tried to inject an object not in the MCPService's state!")
-        }
+    fn from_context(ctx: &Context) -> Result<Self, ExtractError> {
+        ctx.get::<Inject<T>>()
+            .cloned()
+            .ok_or(ExtractError::MissingState(std::any::type_name::<T>()))
     }
 }
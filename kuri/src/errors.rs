@@ -1,5 +1,53 @@
+use crate::extract::ExtractError;
+use kuri_mcp_protocol::jsonrpc::{ErrorCode, ErrorData};
+use serde_json::json;
+use std::fmt;
 use thiserror::Error;
 
+/// The kind of a handler served by an [`MCPService`], for reporting errors about it.
+///
+/// [`MCPService`]: crate::MCPService
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HandlerKind {
+    Tool,
+    Prompt,
+    Resource,
+}
+
+impl fmt::Display for HandlerKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            HandlerKind::Tool => "Tool",
+            HandlerKind::Prompt => "Prompt",
+            HandlerKind::Resource => "Resource",
+        })
+    }
+}
+
+/// Errors raised when building an [`MCPService`], with [`MCPServiceBuilder::try_build`].
+///
+/// [`MCPService`]: crate::MCPService
+/// [`MCPServiceBuilder::try_build`]: crate::MCPServiceBuilder::try_build
+#[derive(Error, Debug)]
+pub enum BuildError {
+    /// A handler depends on state that wasn't registered with the service.
+    #[error("{kind} `{name}` can't be served: {source}")]
+    UnsatisfiedDependency {
+        kind: HandlerKind,
+        name: String,
+        source: ExtractError,
+    },
+
+    /// More than one handler was registered under the same name (or URI, for resources).
+    #[error("{kind} `{name}` is registered more than once")]
+    DuplicateName { kind: HandlerKind, name: String },
+
+    /// More than one of the builders combined with `merge` or `nest` registered state of the same
+    /// type, so handlers from one of them would be given the other's.
+    #[error("State of type `{type_name}` is registered by more than one of the combined builders")]
+    ConflictingState { type_name: &'static str },
+}
+
 /// Errors raised while *processing* a request.
 /// These errors assume that the request is valid and was successfully parsed. Errors for invalid
 /// requests are handled at the transport level, within [`MessageParseError`].
@@ -22,31 +70,47 @@ pub enum RequestError {
     #[error("Resource not found: {0}")]
     ResourceNotFound(String),
 
-    #[error("Not found: {0}")]
+    #[error("Prompt not found: {0}")]
     PromptNotFound(String),
 
     #[error("This implementation doesn't support message type: {0}")]
     Unsupported(String),
+
+    /// An error raised by a handler, sent to the client as is.
+    #[error("{0}")]
+    Rpc(ErrorData),
 }
 
 /// Request errors can be returned as a `JsonRpcResponse` with the error type.
 /// This trait implementation aids conversion of the `RequestError` to an `ErrorData` which can be
 /// provided in the `JsonRpcResponse`.
-impl From<RequestError> for kuri_mcp_protocol::jsonrpc::ErrorData {
+///
+/// Errors for a missing tool, resource or prompt include its name (or URI) in the error's `data`,
+/// so clients can tell them apart from other invalid parameters.
+impl From<RequestError> for ErrorData {
     fn from(err: RequestError) -> Self {
-        use kuri_mcp_protocol::jsonrpc::{ErrorCode, ErrorData};
-
-        let code = match err {
-            RequestError::MethodNotFound(_) => ErrorCode::MethodNotFound,
-            RequestError::InvalidParams(_) => ErrorCode::InvalidParams,
-            RequestError::Internal(_) => ErrorCode::InternalError,
-            RequestError::ToolNotFound(_) => ErrorCode::InvalidParams,
-            RequestError::ResourceNotFound(_) => ErrorCode::InvalidParams,
-            RequestError::PromptNotFound(_) => ErrorCode::InvalidParams,
-            RequestError::Unsupported(_) => ErrorCode::InvalidRequest,
+        let (code, data) = match &err {
+            RequestError::MethodNotFound(_) => (ErrorCode::MethodNotFound, None),
+            RequestError::InvalidParams(_) => (ErrorCode::InvalidParams, None),
+            RequestError::Internal(_) => (ErrorCode::InternalError, None),
+            RequestError::ToolNotFound(name) => {
+                (ErrorCode::InvalidParams, Some(json!({ "tool": name })))
+            }
+            RequestError::ResourceNotFound(uri) => {
+                (ErrorCode::InvalidParams, Some(json!({ "uri": uri })))
+            }
+            RequestError::PromptNotFound(name) => {
+                (ErrorCode::InvalidParams, Some(json!({ "prompt": name })))
+            }
+            RequestError::Unsupported(_) => (ErrorCode::InvalidRequest, None),
+            RequestError::Rpc(error) => return error.clone(),
         };
 
-        ErrorData::new(code, err.to_string())
+        ErrorData {
+            code,
+            message: err.to_string(),
+            data,
+        }
     }
 }
 
@@ -71,6 +135,8 @@ impl From<kuri_mcp_protocol::tool::ToolError> for RequestError {
             kuri_mcp_protocol::tool::ToolError::SchemaError(msg) => {
                 RequestError::InvalidParams(msg)
             }
+            kuri_mcp_protocol::tool::ToolError::InternalError(msg) => RequestError::Internal(msg),
+            kuri_mcp_protocol::tool::ToolError::Rpc(error) => RequestError::Rpc(error),
             kuri_mcp_protocol::tool::ToolError::ExecutionError(_) => {
                 // This case should've been mapped to a successful result.
                 unreachable!()
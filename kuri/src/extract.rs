@@ -0,0 +1,288 @@
+//! Extractors: types that handlers can take as parameters, which are obtained from the request
+//! being handled rather than from the client's arguments.
+//!
+//! Any type implementing [`FromRequestContext`] is an extractor. kuri provides:
+//!
+//! * [`Inject<T>`], for global state registered with the service (and any other [`FromContext`]
+//!   type)
+//! * [`Session<T>`], for state scoped to the client's session
+//! * [`RequestId`], the JSON-RPC ID of the request
+//! * [`Meta`], the request's `_meta` field
+//! * [`ClientInfo`], details of the client, given when the session was initialised
+//! * [`Peer`], to message the client
+//! * [`RequestContext`], for all of the above
+//!
+//! [`Registry`], the service's tools, prompts and resources (eg to link to a resource), is an
+//! extractor too. Its name is common enough that it isn't recognised by name (see below): mark it
+//! with `#[extract]`, or use [`RequestContext::registry`].
+//!
+//! ```rust
+//! use kuri::{extract::{ClientInfo, Meta}, tool};
+//!
+//! #[tool(description = "Say hello to the client")]
+//! async fn hello(client: ClientInfo, meta: Meta, name: String) -> String {
+//!     format!("Hello {name}, from {} (meta: {:?})", client.name, meta.get("tag"))
+//! }
+//! ```
+//!
+//! `#[tool]` and `#[prompt]` need to tell extractors apart from the tool's arguments when
+//! generating its schema. A macro can't see which traits a type implements, so kuri's extractors
+//! are recognised by name, and your own extractors must be marked with `#[extract]`. An unmarked
+//! parameter whose type implements [`FromRequestContext`] fails to compile, so an extractor is
+//! never silently taken from the client's arguments:
+//!
+//! ```rust
+//! use kuri::{context::RequestContext, extract::{ExtractError, FromRequestContext}, tool};
+//!
+//! struct TraceId(String);
+//!
+//! impl FromRequestContext for TraceId {
+//!     fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+//!         let trace_id = ctx
+//!             .meta()
+//!             .and_then(|meta| meta.get("traceId"))
+//!             .and_then(|id| id.as_str())
+//!             .ok_or_else(|| ExtractError::Other("Missing trace ID".to_string()))?;
+//!         Ok(TraceId(trace_id.to_string()))
+//!     }
+//! }
+//!
+//! #[tool]
+//! async fn traced(#[extract] trace_id: TraceId) -> String {
+//!     trace_id.0
+//! }
+//! ```
+//!
+//! Conversely, an argument whose type shares a name with one of kuri's extractors (say, your own
+//! `Session` type) must be marked with `#[arg]`, unless it's named through its path
+//! (`my_app::Session`).
+//!
+//! [`Inject<T>`]: crate::context::Inject
+//! [`FromContext`]: crate::context::FromContext
+//! [`RequestContext`]: crate::context::RequestContext
+//! [`RequestContext::registry`]: crate::context::RequestContext::registry
+
+use crate::{
+    context::{Context, FromContext, RequestContext},
+    registry::Registry,
+};
+use kuri_mcp_protocol::{
+    messages::ClientCapabilities, prompt::PromptError, resource::ResourceError, tool::ToolError,
+};
+use serde_json::{Map, Value};
+use std::{any::type_name, ops::Deref, sync::Arc};
+use thiserror::Error;
+
+pub use crate::peer::Peer;
+pub use kuri_mcp_protocol::jsonrpc::RequestId;
+
+/// Errors raised when an extractor can't be obtained from the request. These are returned to the
+/// client as internal errors.
+#[derive(Error, Debug)]
+pub enum ExtractError {
+    #[error("Tried to inject {0}, which is not in the MCPService's state")]
+    MissingState(&'static str),
+
+    #[error("Tried to inject session state {0}, which is not registered with the MCPService")]
+    MissingSessionState(&'static str),
+
+    #[error("{0} is only available when serving a session")]
+    NoSession(&'static str),
+
+    #[error("The client has not initialised the session")]
+    NotInitialised,
+
+    #[error("{0}")]
+    Other(String),
+}
+
+impl From<ExtractError> for ToolError {
+    fn from(err: ExtractError) -> Self {
+        ToolError::InternalError(err.to_string())
+    }
+}
+
+impl From<ExtractError> for PromptError {
+    fn from(err: ExtractError) -> Self {
+        PromptError::InternalError(err.to_string())
+    }
+}
+
+impl From<ExtractError> for ResourceError {
+    fn from(err: ExtractError) -> Self {
+        ResourceError::ExecutionError(err.to_string())
+    }
+}
+
+/// A trait to obtain a type from the context of the request being handled.
+///
+/// Implementing this for a type allows it to be taken as a parameter by handlers.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` isn't an extractor",
+    label = "taken from the request, rather than the client's arguments",
+    note = "parameters with the same name as one of kuri's extractors (eg `Meta` or `Peer`) are taken to be extractors",
+    note = "if this is an argument given by the client, mark it with `#[arg]`"
+)]
+pub trait FromRequestContext: Sized {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError>;
+
+    /// Check that the type can be extracted given the service's global state, without needing a
+    /// request. Used to verify handlers' dependencies when the service is built, in
+    /// [`MCPServiceBuilder::try_build`].
+    ///
+    /// By default, this succeeds: most extractors depend only on the request.
+    ///
+    /// [`MCPServiceBuilder::try_build`]: crate::MCPServiceBuilder::try_build
+    fn verify_state(_state: &Context) -> Result<(), ExtractError> {
+        Ok(())
+    }
+}
+
+/// Global state (such as [`Inject`]) may be extracted by any request.
+///
+/// [`Inject`]: crate::context::Inject
+impl<T: FromContext> FromRequestContext for T {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+        T::from_context(ctx.state())
+    }
+
+    fn verify_state(state: &Context) -> Result<(), ExtractError> {
+        T::verify(state)
+    }
+}
+
+impl FromRequestContext for RequestContext {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+        Ok(ctx.clone())
+    }
+}
+
+/// The registry of the service handling the request, eg to link to its resources with
+/// [`Registry::resource_link`].
+impl FromRequestContext for Registry {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+        Ok(ctx.registry().clone())
+    }
+}
+
+impl FromRequestContext for RequestId {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+        Ok(ctx.request_id().clone())
+    }
+}
+
+impl FromRequestContext for Peer {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+        ctx.peer().cloned().ok_or(ExtractError::NoSession("Peer"))
+    }
+}
+
+/// The request's metadata (the `_meta` field of its params). Empty if the request had none.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Meta(pub Map<String, Value>);
+
+impl Deref for Meta {
+    type Target = Map<String, Value>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl FromRequestContext for Meta {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+        Ok(Meta(ctx.meta().cloned().unwrap_or_default()))
+    }
+}
+
+/// Details of the client, as given when it initialised the session.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClientInfo {
+    /// The client's name
+    pub name: String,
+    /// The client's version
+    pub version: String,
+    /// The capabilities supported by the client
+    pub capabilities: ClientCapabilities,
+    /// The protocol version negotiated for the session
+    pub protocol_version: String,
+}
+
+impl FromRequestContext for ClientInfo {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+        if ctx.peer().is_none() {
+            return Err(ExtractError::NoSession("ClientInfo"));
+        }
+        ctx.client_info().ok_or(ExtractError::NotInitialised)
+    }
+}
+
+/// State scoped to the client's session, as opposed to [`Inject`]ed state, which is shared by all
+/// sessions.
+///
+/// Each session's state is created by the factory registered with
+/// [`MCPServiceBuilder::with_session_state`] when the client initialises the session, and dropped
+/// when the session ends (for [`serve`], when the connection closes). Like [`Inject`], handlers get
+/// shared access, so state they modify needs interior mutability.
+///
+/// ```rust
+/// use kuri::{extract::Session, tool, MCPServiceBuilder};
+/// use std::sync::Mutex;
+///
+/// #[derive(Default)]
+/// struct Cart(Mutex<Vec<String>>);
+///
+/// #[tool(description = "Add an item to the client's cart")]
+/// async fn add_to_cart(cart: Session<Cart>, item: String) -> String {
+///     let mut items = cart.0.lock().unwrap();
+///     items.push(item);
+///     format!("{} items in cart", items.len())
+/// }
+///
+/// let service = MCPServiceBuilder::new("Shop".to_string())
+///     .with_session_state(|_| Cart::default())
+///     .with_tool(AddToCart)
+///     .build();
+/// ```
+///
+/// [`Inject`]: crate::context::Inject
+/// [`MCPServiceBuilder::with_session_state`]: crate::MCPServiceBuilder::with_session_state
+/// [`serve`]: crate::serve
+pub struct Session<T: ?Sized>(Arc<T>);
+
+// Pass function calls through to the inner object
+impl<T: ?Sized> Deref for Session<T> {
+    type Target = Arc<T>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T: ?Sized> Clone for Session<T> {
+    fn clone(&self) -> Session<T> {
+        Session(Arc::clone(&self.0))
+    }
+}
+
+impl<T: 'static> FromRequestContext for Session<T> {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+        let session = ctx.session().ok_or(ExtractError::NoSession("Session"))?;
+        session.state::<T>().map(Session).ok_or_else(|| {
+            if ctx.state().has_session_state::<T>() {
+                // The factory is registered, so the state is created once the client initialises
+                ExtractError::NotInitialised
+            } else {
+                ExtractError::MissingSessionState(type_name::<T>())
+            }
+        })
+    }
+
+    fn verify_state(state: &Context) -> Result<(), ExtractError> {
+        if state.has_session_state::<T>() {
+            Ok(())
+        } else {
+            Err(ExtractError::MissingSessionState(type_name::<T>()))
+        }
+    }
+}
@@ -1,60 +1,164 @@
-use crate::context::Context;
+use crate::{
+    context::{Context, RequestContext},
+    extract::ExtractError,
+    maybe_send::MaybeSendSync,
+};
 use async_trait::async_trait;
 use kuri_mcp_protocol::{
-    messages::CallToolResult,
+    messages::{CallToolResult, GetPromptResult},
     prompt::{PromptArgument, PromptError},
-    tool::ToolError,
+    resource::{Resource, ResourceContents, ResourceError},
+    tool::{ToolAnnotations, ToolError},
 };
 use serde_json::Value;
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
-#[async_trait(?Send)]
-pub trait ToolHandler: 'static {
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+pub trait ToolHandler: MaybeSendSync + 'static {
     /// The name of the tool
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
 
     /// A description of what the tool does
-    fn description(&self) -> &'static str;
+    fn description(&self) -> &str;
 
     /// JSON schema describing the tool's parameters
     fn schema(&self) -> Value;
 
+    /// JSON schema describing the tool's structured output, if any
+    fn output_schema(&self) -> Option<Value> {
+        None
+    }
+
+    /// Hints describing the tool's behaviour (eg whether it's read-only) to the client
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        None
+    }
+
+    /// Check that the service's state contains everything the handler needs (eg injected types),
+    /// so that missing state is reported when the service is built rather than when the handler is
+    /// called. See [`MCPServiceBuilder::try_build`].
+    ///
+    /// [`MCPServiceBuilder::try_build`]: crate::MCPServiceBuilder::try_build
+    fn verify_state(&self, _state: &Context) -> Result<(), ExtractError> {
+        Ok(())
+    }
+
     /// Execute the tool with the given parameters
-    async fn call(&self, context: &Context, params: Value) -> Result<CallToolResult, ToolError>;
+    async fn call(
+        &self,
+        context: &RequestContext,
+        params: Value,
+    ) -> Result<CallToolResult, ToolError>;
 }
 
-#[async_trait(?Send)]
-pub trait PromptHandler: 'static {
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+pub trait PromptHandler: MaybeSendSync + 'static {
     /// The name of the prompt
-    fn name(&self) -> &'static str;
+    fn name(&self) -> &str;
 
     /// A description of what the prompt does
-    fn description(&self) -> Option<&'static str>;
+    fn description(&self) -> Option<&str>;
 
     /// The arguments that the prompt accepts
     fn arguments(&self) -> Option<Vec<PromptArgument>>;
 
+    /// Check that the service's state contains everything the handler needs (eg injected types),
+    /// so that missing state is reported when the service is built rather than when the handler is
+    /// called. See [`MCPServiceBuilder::try_build`].
+    ///
+    /// [`MCPServiceBuilder::try_build`]: crate::MCPServiceBuilder::try_build
+    fn verify_state(&self, _state: &Context) -> Result<(), ExtractError> {
+        Ok(())
+    }
+
     /// Execute the prompt with the given parameters
     async fn call(
         &self,
-        context: &Context,
+        context: &RequestContext,
         params: HashMap<String, serde_json::Value>,
-    ) -> Result<String, PromptError>;
+    ) -> Result<GetPromptResult, PromptError>;
+}
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+pub trait ResourceHandler: MaybeSendSync + 'static {
+    /// The URI of the resource
+    fn uri(&self) -> &str;
+
+    /// A human-readable name for the resource
+    fn name(&self) -> &str;
+
+    /// A description of what the resource represents
+    fn description(&self) -> Option<&str> {
+        None
+    }
+
+    /// The MIME type of the resource, if known
+    fn mime_type(&self) -> Option<&str> {
+        None
+    }
+
+    /// Check that the service's state contains everything the handler needs (eg injected types),
+    /// so that missing state is reported when the service is built rather than when the handler is
+    /// called. See [`MCPServiceBuilder::try_build`].
+    ///
+    /// [`MCPServiceBuilder::try_build`]: crate::MCPServiceBuilder::try_build
+    fn verify_state(&self, _state: &Context) -> Result<(), ExtractError> {
+        Ok(())
+    }
+
+    /// Read the contents of the resource
+    async fn read(&self, context: &RequestContext) -> Result<Vec<ResourceContents>, ResourceError>;
+}
+
+/// Describe a resource, as it's listed to clients.
+pub(crate) fn resource_meta(resource: &dyn ResourceHandler) -> Resource {
+    Resource {
+        uri: resource.uri().to_string(),
+        name: resource.name().to_string(),
+        description: resource.description().map(str::to_string),
+        mime_type: resource.mime_type().map(str::to_string),
+        annotations: None,
+        size: None,
+    }
+}
+
+/// A type whose methods are tools and prompts, sharing an instance of the type (such as a
+/// database pool) rather than taking it as injected state.
+///
+/// This is implemented by [`#[tool_box]`](crate::tool_box) for the `#[tool]` and `#[prompt]`
+/// methods of an impl block, and the handlers are registered with
+/// [`MCPServiceBuilder::with_tool_box`]. A type may have only one `#[tool_box]` impl block.
+///
+/// [`MCPServiceBuilder::with_tool_box`]: crate::MCPServiceBuilder::with_tool_box
+pub trait ToolBox: MaybeSendSync + 'static {
+    /// The handlers of the tools defined on this type, bound to `this`
+    fn tools(this: &Arc<Self>) -> Vec<Box<dyn ToolHandler>>;
+
+    /// The handlers of the prompts defined on this type, bound to `this`
+    fn prompts(this: &Arc<Self>) -> Vec<Box<dyn PromptHandler>>;
 }
 
 // TODO: this should really be a macro testcase
 #[cfg(test)]
 mod tests {
     use crate::response::IntoCallToolResult;
-    use kuri_mcp_protocol::Content;
+    use kuri_mcp_protocol::{jsonrpc::RequestId, Content};
+    use std::sync::Arc;
 
     use super::*;
+    use crate::Registry;
 
     #[tokio::test]
     async fn test_echo_tool() {
         let tool = EchoTool;
         let result = tool
-            .call(&Context::default(), serde_json::json!({"input": "hello"}))
+            .call(
+                &RequestContext::new(Arc::default(), Registry::default(), RequestId::Num(1), None),
+                serde_json::json!({"input": "hello"}),
+            )
             .await
             .unwrap();
         let content = result.content[0].clone();
@@ -68,7 +172,8 @@ mod tests {
         input
     }
 
-    #[async_trait(?Send)]
+    #[cfg_attr(feature = "send", async_trait)]
+    #[cfg_attr(not(feature = "send"), async_trait(?Send))]
     impl ToolHandler for EchoTool {
         fn name(&self) -> &'static str {
             "echo"
@@ -94,7 +199,7 @@ mod tests {
         #[allow(unused_variables)]
         async fn call(
             &self,
-            context: &Context,
+            context: &RequestContext,
             params: Value,
         ) -> Result<CallToolResult, ToolError> {
             let input = params.get("input").unwrap().as_str().unwrap();
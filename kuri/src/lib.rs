@@ -45,10 +45,81 @@
 //! # Defining tools and prompts
 //!
 //! Handlers are the functions invoked when a tool or prompt is invoked. They're just normal Rust
-//! functions, and can return any type that implements [`IntoCallToolResult`]. Since handlers are
+//! functions. Tool handlers can return any type that implements [`IntoCallToolResult`], and prompt
+//! handlers any type that implements [`IntoGetPromptResult`] (such as a `String`, or a list of
+//! messages). Since handlers are
 //! just Rust functions, you can use them as normal. Testing is also straightforward; just call the
 //! function directly.
 //!
+//! Descriptions of a tool or prompt, and of its parameters, can be given in the attribute, or as
+//! doc comments. A description in the attribute takes precedence over a doc comment.
+//!
+//! ```rust
+//! use kuri::tool;
+//!
+//! /// Convert a temperature from Celsius to Fahrenheit
+//! #[tool]
+//! async fn to_fahrenheit(
+//!     /// The temperature in Celsius
+//!     celsius: f64,
+//! ) -> f64 {
+//!     celsius * 9.0 / 5.0 + 32.0
+//! }
+//! ```
+//!
+//! Parameters can be renamed, or given a default so that clients may omit them, with options in
+//! `params(...)`. `Option<T>` parameters are always optional. A parameter marked `#[flatten]` is
+//! deserialised from the arguments object as a whole, so its fields become the tool's arguments.
+//!
+//! ```rust
+//! use kuri::tool;
+//!
+//! #[derive(serde::Deserialize, schemars::JsonSchema)]
+//! struct Filter {
+//!     language: String,
+//! }
+//!
+//! #[tool(params(max_results(rename = "maxResults", default = 10)))]
+//! async fn search(query: String, max_results: u32, #[flatten] filter: Filter) -> String {
+//!     format!("Up to {max_results} results for {query} in {}", filter.language)
+//! }
+//! ```
+//!
+//! MCP gives prompt arguments as strings, so prompt parameters are parsed with their type's
+//! [`FromStr`](std::str::FromStr) (for example, `"5"` for a `u32` parameter). A prompt whose
+//! parameters are all optional may be got without any arguments.
+//!
+//! To return structured output, wrap a serialisable type in [`Json`]. The tool then advertises an
+//! output schema derived from that type, and clients receive the value as structured content.
+//! Structured output was added in version 2025-06-18 of the protocol, so clients that negotiate an
+//! earlier version are sent only its text.
+//!
+//! Tools may also be given [annotations][`ToolAnnotations`], which hint to clients how the tool
+//! behaves (for example, so a client can auto-approve calls to read-only tools):
+//!
+//! ```rust
+//! use kuri::tool;
+//!
+//! #[tool(description = "Get the current weather", title = "Weather", read_only, open_world)]
+//! async fn get_weather(city: String) -> String {
+//!     format!("It's sunny in {city}")
+//! }
+//! ```
+//!
+//! Handlers needn't be `async`. A synchronous tool that does a lot of work (such as parsing or image
+//! processing) should be marked `blocking`, so that it runs on tokio's blocking thread pool rather
+//! than holding up the connection's other requests. Its arguments and extractors are moved to that
+//! thread, so must be `Send + 'static` (an [`Inject<T>`] is, if `T` is `Send + Sync`).
+//!
+//! ```rust
+//! use kuri::tool;
+//!
+//! #[tool(description = "Count the words in a document", blocking)]
+//! fn word_count(document: String) -> u64 {
+//!     document.split_whitespace().count() as u64
+//! }
+//! ```
+//!
 //! # Handling notifications
 //!
 //! If you wish to handle notifications, you'll need to define your own function to handle the raw
@@ -73,8 +144,29 @@
 //!
 //! The MCP protocol supports two types of errors: RPC errors, and logical errors. kuri tool handlers
 //! can return a [`ToolError`], which combines both types of errors (`ExecutionError` is mapped to
-//! logical errors). You can return your own error type if you prefer; just implement
-//! [`IntoCallToolResult`] for your type.
+//! logical errors). Tools may also return any other error in a `Result`, such as an
+//! `anyhow::Error` or your own type implementing [`std::error::Error`], which is reported to the
+//! client as a logical error.
+//!
+//! To send the client an RPC error it can act on programmatically, tools and prompts can return an
+//! [`ErrorData`], with a custom [`ErrorCode`] and structured `data`. (JSON-RPC reserves codes from
+//! -32768 to -32000, apart from -32099 to -32000 for implementation-defined server errors.) Errors
+//! for a missing tool, prompt or resource also say which in their `data`.
+//!
+//! ```rust
+//! use kuri::tool;
+//! use kuri_mcp_protocol::jsonrpc::{ErrorCode, ErrorData};
+//! use serde_json::json;
+//!
+//! #[tool(description = "Search the web")]
+//! async fn search(query: String) -> Result<String, ErrorData> {
+//!     Err(ErrorData::new(ErrorCode::Custom(429), "Rate limited".to_string())
+//!         .with_data(json!({ "query": query, "retryAfter": 30 })))
+//! }
+//! ```
+//!
+//! [`ErrorData`]: kuri_mcp_protocol::jsonrpc::ErrorData
+//! [`ErrorCode`]: kuri_mcp_protocol::jsonrpc::ErrorCode
 //!
 //! # Middleware and layers
 //!
@@ -115,6 +207,13 @@
 //! MCP service. On return, the handlers are called in reverse order. So the first declared layer
 //! will be the first to process an incoming request, and the last to process an outgoing response.
 //!
+//! ## Validating tool arguments
+//!
+//! With the `validation` feature, `middleware::validation::ValidationLayer` checks the arguments
+//! of each tool call against the tool's JSON schema before the tool is called, and rejects invalid
+//! calls with an error pointing at each failing argument. This applies to hand-written
+//! [`ToolHandler`]s as well as those generated by `#[tool]`.
+//!
 //! ## Per-[tool/prompt/resource] middleware
 //!
 //! For now, you will need to add the code to your handler to invoke your middleware. We're still
@@ -161,11 +260,164 @@
 //! You don't need to use `Inject`, but it's the easiest way to get started. If you have more
 //! specific needs, see the [`FromContext`] trait, which you may implement for your own types.
 //!
+//! If a handler injects a type that isn't in the service's state, requests to it fail with an
+//! internal error. To catch this at startup instead, build your service with
+//! [`MCPServiceBuilder::try_build`], which checks that every handler's dependencies are present.
+//!
+//! ## Tool boxes
+//!
+//! Where a set of tools naturally belongs to a struct (for example, one holding a database pool),
+//! the tools can instead be methods taking `&self`. Mark the impl block with
+//! [`#[tool_box]`](crate::tool_box), and its `#[tool]` and `#[prompt]` methods become handlers
+//! sharing one instance of the struct, which are all registered with
+//! [`MCPServiceBuilder::with_tool_box`]. Methods may take extractors and arguments as usual.
+//!
+//! ```rust
+//! use kuri::{tool_box, MCPServiceBuilder};
+//! use std::{collections::HashMap, sync::Arc};
+//!
+//! struct Dictionary {
+//!     definitions: HashMap<String, String>,
+//! }
+//!
+//! #[tool_box]
+//! impl Dictionary {
+//!     /// Look up the definition of a word
+//!     #[tool]
+//!     async fn define(&self, word: String) -> String {
+//!         self.definitions.get(&word).cloned().unwrap_or_default()
+//!     }
+//!
+//!     /// Ask for a sentence using a word
+//!     #[prompt]
+//!     async fn use_in_a_sentence(&self, word: String) -> String {
+//!         format!("Use the word {word} in a sentence")
+//!     }
+//! }
+//!
+//! let dictionary = Dictionary { definitions: HashMap::new() };
+//! let service = MCPServiceBuilder::new("Dictionary".to_string())
+//!     .with_tool_box(Arc::new(dictionary))
+//!     .build();
+//! ```
+//!
+//! # Request context and extractors
+//!
+//! Besides their arguments, handlers may take [extractors]: parameters obtained from the request
+//! being handled, such as its ID, its `_meta` field, details of the client, or a [`Peer`] to
+//! message the client with. `Inject` is one such extractor.
+//!
+//! ```rust
+//! use kuri::{extract::{ClientInfo, RequestId}, tool};
+//!
+//! #[tool]
+//! async fn whoami(id: RequestId, client: ClientInfo) -> String {
+//!     format!("Request {:?} from {}", id, client.name)
+//! }
+//! ```
+//!
+//! Handlers that implement [`ToolHandler`] (and friends) directly receive the whole
+//! [`RequestContext`].
+//!
+//! # Session state
+//!
+//! State in the [`Context`] is global: every connection shares it. State belonging to a single
+//! client (such as a shopping cart, or who the client is authenticated as) can instead be scoped to
+//! its session. Register a factory with [`MCPServiceBuilder::with_session_state`], and take the
+//! state as a [`Session<T>`]. The factory runs when the client initialises its session, and the
+//! state is dropped when the session ends. [`serve`] runs each connection as its own session.
+//!
+//! ```rust
+//! use kuri::{extract::Session, tool, MCPServiceBuilder};
+//!
+//! struct Identity { client: String }
+//!
+//! #[tool]
+//! async fn whoami(identity: Session<Identity>) -> String {
+//!     identity.client.clone()
+//! }
+//!
+//! let service = MCPServiceBuilder::new("Identity server".to_string())
+//!     .with_session_state(|ctx| Identity {
+//!         client: ctx.client_info().map(|info| info.name).unwrap_or_default(),
+//!     })
+//!     .with_tool(Whoami)
+//!     .build();
+//! ```
+//!
+//! # Changing tools, prompts and resources at runtime
+//!
+//! Handlers registered with the [`MCPServiceBuilder`] form the initial contents of the service's
+//! [`Registry`]. You can get a handle to the registry with [`MCPService::registry`], and add or
+//! remove handlers while the server is running. Connected clients are notified of each change
+//! (through `notifications/tools/list_changed` and friends), so they can fetch the new list.
+//!
+//! Handlers can take the registry as an extractor. A tool can then link to resources with
+//! [`Registry::resource_link`], rather than embedding their contents in its result.
+//!
+//! # Composing services
+//!
+//! Tool sets defined separately (eg in different crates) can be assembled into one server by
+//! combining their builders. [`MCPServiceBuilder::merge`] adds another builder's tools, prompts,
+//! resources and state as they are, while [`MCPServiceBuilder::nest`] prefixes the names of its
+//! tools and prompts:
+//!
+//! ```rust
+//! use kuri::{tool, MCPServiceBuilder};
+//!
+//! #[tool]
+//! async fn search(query: String) -> String {
+//!     format!("Results for {query}")
+//! }
+//!
+//! fn search_tools() -> MCPServiceBuilder {
+//!     MCPServiceBuilder::new("Search".to_string()).with_tool(Search)
+//! }
+//!
+//! // Serves `github_search` and `gitlab_search`
+//! let service = MCPServiceBuilder::new("Forges".to_string())
+//!     .nest("github_", search_tools())
+//!     .nest("gitlab_", search_tools())
+//!     .try_build()
+//!     .unwrap();
+//! ```
+//!
+//! Tool and prompt names (and resource URIs) must be unique. Handlers may be defined in different
+//! modules or crates, so `#[tool]` and `#[prompt]` can't check this when they're compiled: two
+//! functions marked `#[tool(name = "add")]` both compile. Instead, [`MCPServiceBuilder::try_build`]
+//! returns an error if two handlers are registered under the same name (and
+//! [`MCPServiceBuilder::build`] panics). The methods of one `#[tool_box]` impl are checked when
+//! they're compiled.
+//!
+//! # Multi-threaded runtimes
+//!
+//! By default, kuri is single-threaded: handlers don't need to be `Send`, and neither does the
+//! [`MCPService`]. This keeps handlers simple, but means a service can't be moved to another thread
+//! (eg with `tokio::spawn` on a multi-threaded runtime, or within axum).
+//!
+//! Enabling the `send` feature requires handlers, their futures, and any injected state to be
+//! `Send + Sync`. `#[tool]` and `#[prompt]` then generate `Send` handlers, and [`MCPService`] (and
+//! its futures) become `Send + Sync`:
+//!
+//! ```toml
+//! [dependencies]
+//! kuri = { version = "0.2", features = ["send"] }
+//! ```
+//!
+//! If you implement the handler traits yourself, use `#[async_trait]` rather than
+//! `#[async_trait(?Send)]` when the feature is enabled.
+//!
 //! # Transports
 //!
 //! Once you instantiate a [`MCPService`], you can use the [`serve`] function to start the server
 //! over some transport, as in the Hello World example above.
 //!
+//! # Testing
+//!
+//! Handlers are plain functions, so can be tested by calling them directly. To test a whole
+//! session with a service (including notifications sent by handlers), use the [`TestHarness`],
+//! which serves it over an in-memory stream and plays the part of the client.
+//!
 //! # Logging
 //!
 //! kuri uses tokio's tracing throughout for log messages. Typically, applications might consume
@@ -197,39 +449,157 @@
 //! [`ServiceBuilder`]: tower::ServiceBuilder
 //! [tower guide to writing middleware]: https://github.com/tower-rs/tower/blob/master/guides/building-a-middleware-from-scratch.md
 //! [`IntoCallToolResult`]: crate::response::IntoCallToolResult
+//! [`IntoGetPromptResult`]: crate::response::IntoGetPromptResult
 //! [`Notification`]: kuri_mcp_protocol::jsonrpc::Notification
 //! [`SendableMessage`]: kuri_mcp_protocol::jsonrpc::SendableMessage
 //! [`Request`]: kuri_mcp_protocol::jsonrpc::Request
 //! [`MCPService`]: crate::MCPService
 //! [`MCPRequestService`]: crate::MCPRequestService
+//! [`MCPService::registry`]: crate::MCPService::registry
+//! [`MCPServiceBuilder::try_build`]: crate::MCPServiceBuilder::try_build
+//! [`MCPServiceBuilder::merge`]: crate::MCPServiceBuilder::merge
+//! [`MCPServiceBuilder::nest`]: crate::MCPServiceBuilder::nest
+//! [`MCPServiceBuilder::with_session_state`]: crate::MCPServiceBuilder::with_session_state
+//! [`MCPServiceBuilder::with_tool_box`]: crate::MCPServiceBuilder::with_tool_box
+//! [`Session<T>`]: crate::extract::Session
+//! [`Registry`]: crate::Registry
 //! [`MCPServer`]: crate::MCPService
 //! [`Context`]: crate::context::Context
 //! [`Inject`]: crate::context::Inject
+//! [`Inject<T>`]: crate::context::Inject
 //! [`FromContext`]: crate::context::FromContext
+//! [extractors]: crate::extract
+//! [`Peer`]: crate::peer::Peer
+//! [`ToolHandler`]: crate::ToolHandler
+//! [`RequestContext`]: crate::context::RequestContext
+//! [`ToolAnnotations`]: crate::ToolAnnotations
+//! [`Json`]: crate::response::Json
+//! [`TestHarness`]: crate::testing::TestHarness
 
+mod compose;
 pub mod context;
 pub mod errors;
+pub mod extract;
 mod handler;
 pub mod id;
+mod maybe_send;
 pub mod middleware;
+pub mod peer;
+mod registry;
 pub mod response;
 mod serve;
 mod service;
 mod service_ext;
+pub mod session;
+pub mod testing;
 pub mod transport;
 
 // aliases
-pub use handler::{PromptHandler, ToolHandler};
+pub use handler::{PromptHandler, ResourceHandler, ToolBox, ToolHandler};
+pub use maybe_send::{BoxFuture, MaybeSend, MaybeSendSync};
+pub use registry::{Registry, RegistryUpdate};
 pub use serve::serve;
 pub use service::{MCPRequestService, MCPService, MCPServiceBuilder};
 pub use service_ext::ServiceExt;
 
 // re-export certain MCP protocol types
 pub use kuri_mcp_protocol::{
-    messages::CallToolResult, prompt::PromptArgument, prompt::PromptError, resource::ResourceError,
-    tool::generate_tool_schema, tool::ToolError,
+    messages::CallToolResult, messages::GetPromptResult, prompt::PromptArgument,
+    prompt::PromptError, prompt::PromptMessage, prompt::PromptMessageRole, resource::ResourceError,
+    tool::generate_tool_schema, tool::ToolAnnotations, tool::ToolError,
 };
 
 // re-export macros
 pub use kuri_macros::prompt;
 pub use kuri_macros::tool;
+pub use kuri_macros::tool_box;
+
+/// Used by the code the macros generate. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    use crate::{extract::FromRequestContext, PromptError, ToolError};
+    use serde_json::Value;
+    use std::{fmt::Display, marker::PhantomData, str::FromStr};
+
+    /// Run a `#[tool(blocking)]` function on the runtime's blocking thread pool, so that it doesn't
+    /// hold up the connection's other requests. A panic is resumed on the calling task, as it would
+    /// be had the function been called there.
+    pub async fn spawn_blocking<F, R>(f: F) -> Result<R, ToolError>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        match tokio::task::spawn_blocking(f).await {
+            Ok(result) => Ok(result),
+            Err(e) if e.is_panic() => std::panic::resume_unwind(e.into_panic()),
+            Err(e) => Err(ToolError::InternalError(e.to_string())),
+        }
+    }
+
+    /// Checks that the type of a parameter taken as an argument (ie not marked with `#[extract]`,
+    /// nor recognised by name) isn't an extractor. The macros call
+    /// `assert_argument((&ArgumentCheck::<T>::new()).kind())`: method resolution picks
+    /// [`ExtractorKind::kind`] if `T` implements `FromRequestContext`, as it takes the check by
+    /// reference, and otherwise falls back to [`ArgumentKind::kind`].
+    pub struct ArgumentCheck<T>(PhantomData<T>);
+
+    impl<T> ArgumentCheck<T> {
+        #[allow(clippy::new_without_default)]
+        pub fn new() -> Self {
+            Self(PhantomData)
+        }
+    }
+
+    pub struct Argument;
+    pub struct Extractor;
+
+    pub trait ExtractorKind {
+        fn kind(&self) -> Extractor {
+            Extractor
+        }
+    }
+
+    impl<T: FromRequestContext> ExtractorKind for ArgumentCheck<T> {}
+
+    pub trait ArgumentKind {
+        fn kind(&self) -> Argument {
+            Argument
+        }
+    }
+
+    impl<T> ArgumentKind for &ArgumentCheck<T> {}
+
+    #[diagnostic::on_unimplemented(
+        message = "this parameter is an extractor, so must be marked with `#[extract]`",
+        label = "implements `FromRequestContext`",
+        note = "only kuri's own extractors are recognised without `#[extract]`",
+        note = "if this is an argument given by the client, mark it with `#[arg]`"
+    )]
+    pub trait IsArgument {}
+
+    impl IsArgument for Argument {}
+
+    pub fn assert_argument<K: IsArgument>(_kind: K) {}
+
+    /// Parse a `#[prompt]` argument, named `name`. MCP gives prompt arguments as strings, so
+    /// they're parsed with the parameter type's `FromStr`. Numbers and bools are accepted too, for
+    /// clients that send them unquoted.
+    pub fn parse_prompt_argument<T>(name: &str, value: &Value) -> Result<T, PromptError>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        let parsed = match value {
+            Value::String(value) => value.parse(),
+            Value::Number(_) | Value::Bool(_) => value.to_string().parse(),
+            _ => {
+                return Err(PromptError::InvalidParameters(format!(
+                    "Parameter '{name}' must be a string"
+                )))
+            }
+        };
+        parsed.map_err(|e| {
+            PromptError::InvalidParameters(format!("Failed to parse parameter '{name}': {e}"))
+        })
+    }
+}
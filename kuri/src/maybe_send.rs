@@ -0,0 +1,38 @@
+//! Bounds and types that are `Send + Sync` only when the `send` feature is enabled.
+//!
+//! By default, kuri runs on a single thread: handlers and their futures don't need to be `Send`,
+//! so they may hold `Rc`s, `RefCell`s, and so on. With the `send` feature, handlers must be
+//! `Send + Sync` and return `Send` futures. In exchange, `MCPService` may be moved across threads,
+//! served from `tokio::spawn`ed tasks, and used on multi-threaded runtimes.
+
+/// A boxed future, which is `Send` when the `send` feature is enabled.
+#[cfg(feature = "send")]
+pub type BoxFuture<'a, T> = futures::future::BoxFuture<'a, T>;
+
+/// A boxed future, which is `Send` when the `send` feature is enabled.
+#[cfg(not(feature = "send"))]
+pub type BoxFuture<'a, T> = futures::future::LocalBoxFuture<'a, T>;
+
+/// `Send` when the `send` feature is enabled, and implemented for all types otherwise.
+#[cfg(feature = "send")]
+pub trait MaybeSend: Send {}
+#[cfg(feature = "send")]
+impl<T: Send + ?Sized> MaybeSend for T {}
+
+/// `Send` when the `send` feature is enabled, and implemented for all types otherwise.
+#[cfg(not(feature = "send"))]
+pub trait MaybeSend {}
+#[cfg(not(feature = "send"))]
+impl<T: ?Sized> MaybeSend for T {}
+
+/// `Send + Sync` when the `send` feature is enabled, and implemented for all types otherwise.
+#[cfg(feature = "send")]
+pub trait MaybeSendSync: Send + Sync {}
+#[cfg(feature = "send")]
+impl<T: Send + Sync + ?Sized> MaybeSendSync for T {}
+
+/// `Send + Sync` when the `send` feature is enabled, and implemented for all types otherwise.
+#[cfg(not(feature = "send"))]
+pub trait MaybeSendSync {}
+#[cfg(not(feature = "send"))]
+impl<T: ?Sized> MaybeSendSync for T {}
@@ -5,3 +5,5 @@
 //! You can also use other tower middleware, of course, as kuri builds on top of tower.
 
 pub mod tracing;
+#[cfg(feature = "validation")]
+pub mod validation;
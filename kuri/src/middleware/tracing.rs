@@ -1,10 +1,9 @@
 use std::{
     convert::Infallible,
-    future::Future,
-    pin::Pin,
     task::{Context, Poll},
 };
 
+use crate::maybe_send::{BoxFuture, MaybeSend};
 use kuri_mcp_protocol::jsonrpc::{ResponseItem, SendableMessage};
 use tower::{Layer, Service};
 use tracing::Level;
@@ -20,11 +19,11 @@ pub struct TracingService<S> {
 impl<S> Service<SendableMessage> for TracingService<S>
 where
     S: Service<SendableMessage, Response = Option<ResponseItem>, Error = Infallible>,
-    S::Future: 'static,
+    S::Future: MaybeSend + 'static,
 {
     type Response = Option<ResponseItem>;
     type Error = Infallible;
-    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx)
@@ -72,7 +71,7 @@ impl TracingLayer {
 impl<S> Layer<S> for TracingLayer
 where
     S: Service<SendableMessage, Response = Option<ResponseItem>, Error = Infallible>,
-    S::Future: 'static,
+    S::Future: MaybeSend + 'static,
 {
     type Service = TracingService<S>;
 
@@ -0,0 +1,211 @@
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+
+use crate::{
+    maybe_send::{BoxFuture, MaybeSend},
+    Registry,
+};
+use jsonschema::Validator;
+use kuri_mcp_protocol::jsonrpc::{
+    ErrorCode, ErrorData, MethodCall, Params, ResponseItem, SendableMessage,
+};
+use serde_json::{json, Value};
+use tower::{Layer, Service};
+
+/// A service that validates the arguments of `tools/call` requests against the tool's input
+/// schema, before the request reaches the inner service. See [`ValidationLayer`].
+#[derive(Clone)]
+pub struct ValidationService<S> {
+    inner: S,
+    registry: Registry,
+    validators: Validators,
+}
+
+impl<S> Service<SendableMessage> for ValidationService<S>
+where
+    S: Service<SendableMessage, Response = Option<ResponseItem>, Error = Infallible>,
+    S::Future: MaybeSend + 'static,
+{
+    type Response = Option<ResponseItem>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: SendableMessage) -> Self::Future {
+        if let SendableMessage::Request(request) = &req {
+            if let Some(error) = self.validate(request) {
+                let response = ResponseItem::error(request.id.clone(), error);
+                return Box::pin(futures::future::ready(Ok(Some(response))));
+            }
+        }
+        Box::pin(self.inner.call(req))
+    }
+}
+
+impl<S> ValidationService<S> {
+    /// Validate a request's tool arguments, returning the error to respond with if they're
+    /// invalid. Anything that isn't a call to a known tool is left to the inner service.
+    fn validate(&self, request: &MethodCall) -> Option<ErrorData> {
+        if request.method != "tools/call" {
+            return None;
+        }
+        let Some(Params::Map(params)) = &request.params else {
+            return None;
+        };
+        let name = params.get("name")?.as_str()?;
+        let tool = self.registry.tool(name)?;
+
+        // Arguments are optional, and tools without any may omit them (or send null)
+        let empty = Value::Object(Default::default());
+        let arguments = params
+            .get("arguments")
+            .filter(|arguments| !arguments.is_null())
+            .unwrap_or(&empty);
+
+        let validator = self.validators.get(name, tool.schema())?;
+        let errors: Vec<_> = validator
+            .iter_errors(arguments)
+            .map(|error| (error.instance_path.to_string(), error.to_string()))
+            .collect();
+        if errors.is_empty() {
+            return None;
+        }
+
+        let summary = errors
+            .iter()
+            .map(|(pointer, message)| match pointer.as_str() {
+                "" => message.clone(),
+                pointer => format!("{pointer}: {message}"),
+            })
+            .collect::<Vec<_>>()
+            .join("; ");
+        let data = errors
+            .into_iter()
+            .map(|(pointer, message)| json!({ "pointer": pointer, "message": message }))
+            .collect::<Vec<_>>();
+
+        let mut error = ErrorData::new(
+            ErrorCode::InvalidParams,
+            format!("Invalid arguments for tool `{name}`: {summary}"),
+        );
+        error.data = Some(json!({ "errors": data }));
+        Some(error)
+    }
+}
+
+/// Compiled validators, by tool name. Tools may be replaced at runtime, so each validator is kept
+/// with the schema it was compiled from, and recompiled if the tool's schema changes.
+#[derive(Clone, Default)]
+struct Validators(Arc<Mutex<HashMap<String, Compiled>>>);
+
+struct Compiled {
+    schema: Value,
+    validator: Arc<Validator>,
+}
+
+impl Validators {
+    /// The validator for a tool's schema, or `None` if the schema isn't valid JSON Schema.
+    fn get(&self, name: &str, schema: Value) -> Option<Arc<Validator>> {
+        let mut validators = self.0.lock().expect("validator cache lock poisoned");
+        if let Some(compiled) = validators.get(name) {
+            if compiled.schema == schema {
+                return Some(compiled.validator.clone());
+            }
+        }
+
+        let validator = match jsonschema::validator_for(&schema) {
+            Ok(validator) => Arc::new(validator),
+            Err(e) => {
+                tracing::warn!(
+                    tool = name,
+                    error = %e,
+                    "Tool has an invalid input schema, so its arguments aren't validated"
+                );
+                return None;
+            }
+        };
+        let compiled = Compiled {
+            schema,
+            validator: validator.clone(),
+        };
+        validators.insert(name.to_string(), compiled);
+        Some(validator)
+    }
+}
+
+/// A layer that validates the arguments of each `tools/call` request against the tool's
+/// [`schema`], rejecting invalid calls before they're dispatched. Requires the `validation`
+/// feature.
+///
+/// Without this layer, arguments are only checked by the tool itself: tools defined with
+/// [`#[tool]`](crate::tool) report a generic error if the arguments can't be deserialised, and
+/// hand-written [`ToolHandler`]s receive whatever the client sent. With it, invalid calls are
+/// rejected with an `InvalidParams` error naming the JSON pointer of each failing argument, so
+/// that the model can correct its call. The error's `data` lists the failures:
+///
+/// ```json
+/// { "errors": [{ "pointer": "/y", "message": "\"two\" is not of type \"integer\"" }] }
+/// ```
+///
+/// The layer looks up tools in the service's [`Registry`], so tools added at runtime are
+/// validated too. Calls to unknown tools, and to tools whose schema isn't valid JSON Schema, are
+/// passed on to the inner service.
+///
+/// ```rust
+/// use kuri::{middleware::validation::ValidationLayer, tool, MCPServiceBuilder};
+/// use tower::ServiceBuilder;
+///
+/// #[tool(description = "Add two numbers")]
+/// async fn add(x: i32, y: i32) -> i32 {
+///     x + y
+/// }
+///
+/// let service = MCPServiceBuilder::new("Calculator".to_string())
+///     .with_tool(Add)
+///     .build();
+/// let service = ServiceBuilder::new()
+///     .layer(ValidationLayer::new(service.registry()))
+///     .service(service);
+/// ```
+///
+/// [`schema`]: crate::ToolHandler::schema
+/// [`ToolHandler`]: crate::ToolHandler
+#[derive(Clone)]
+pub struct ValidationLayer {
+    registry: Registry,
+    validators: Validators,
+}
+
+impl ValidationLayer {
+    /// Create a layer validating calls to the tools in `registry`, which is usually that of the
+    /// service being wrapped.
+    pub fn new(registry: Registry) -> Self {
+        Self {
+            registry,
+            validators: Validators::default(),
+        }
+    }
+}
+
+impl<S> Layer<S> for ValidationLayer
+where
+    S: Service<SendableMessage, Response = Option<ResponseItem>, Error = Infallible>,
+    S::Future: MaybeSend + 'static,
+{
+    type Service = ValidationService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ValidationService {
+            inner,
+            registry: self.registry.clone(),
+            validators: self.validators.clone(),
+        }
+    }
+}
@@ -0,0 +1,185 @@
+use crate::session::{SessionHandle, SessionState};
+use kuri_mcp_protocol::jsonrpc::{
+    ErrorData, MethodCall, Notification, Params, RequestId, ResponseItem, SendableMessage,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use thiserror::Error;
+use tokio::sync::{mpsc, oneshot};
+
+/// Locks are only held briefly to update the map, so poisoning indicates a bug in kuri.
+const POISONED: &str = "pending requests lock poisoned";
+
+type Pending = Arc<Mutex<HashMap<RequestId, oneshot::Sender<ResponseItem>>>>;
+
+/// Errors raised when sending a message to a peer.
+#[derive(Error, Debug)]
+pub enum PeerError {
+    #[error("The connection to the peer is closed")]
+    ConnectionClosed,
+
+    #[error("JSON serialisation error: {0}")]
+    Serialisation(#[from] serde_json::Error),
+
+    /// The peer responded to a request with an error.
+    #[error("The peer returned an error: {}", .0.message)]
+    Response(ErrorData),
+}
+
+/// A handle to the other side of a connection (usually, the MCP client), which can be used to send
+/// messages to it outside of the usual request/response cycle. For example, notifying the client
+/// that the list of tools has changed, or [requesting] its roots.
+///
+/// Messages are queued and written to the transport by the connection's [`serve`] loop. Sending is
+/// non-blocking, and a `Peer` may be cloned freely.
+///
+/// [requesting]: Peer::request
+///
+/// [`serve`]: crate::serve
+#[derive(Clone, Debug)]
+pub struct Peer {
+    tx: mpsc::UnboundedSender<SendableMessage>,
+    /// Requests sent to the peer which are awaiting a response.
+    pending: Pending,
+    next_id: Arc<AtomicU64>,
+}
+
+impl Peer {
+    /// Create a new peer, along with the receiving end of its outbound message queue. Whoever owns
+    /// the receiver is responsible for writing the messages to the transport, and for passing the
+    /// peer's responses to [`Peer::respond`].
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<SendableMessage>) {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let peer = Self {
+            tx,
+            pending: Pending::default(),
+            next_id: Arc::new(AtomicU64::new(1)),
+        };
+        (peer, rx)
+    }
+
+    /// Send a request to the peer, and wait for its result. For example, to list the client's
+    /// roots, or ask it to sample from an LLM.
+    ///
+    /// The peer's response must be read from the transport while waiting; [`serve`] does this.
+    /// Fails if the peer responds with an error, or the connection closes first.
+    ///
+    /// [`serve`]: crate::serve
+    pub async fn request(
+        &self,
+        method: impl Into<String>,
+        params: Option<Params>,
+    ) -> Result<Value, PeerError> {
+        let id = RequestId::Num(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().expect(POISONED).insert(id.clone(), tx);
+
+        let request = MethodCall::new(id.clone(), method.into(), params);
+        if self.tx.send(SendableMessage::Request(request)).is_err() {
+            self.pending.lock().expect(POISONED).remove(&id);
+            return Err(PeerError::ConnectionClosed);
+        }
+
+        match rx.await {
+            Ok(ResponseItem::Success { result, .. }) => Ok(result),
+            Ok(ResponseItem::Error { error, .. }) => Err(PeerError::Response(error)),
+            // The connection closed before the peer responded
+            Err(_) => Err(PeerError::ConnectionClosed),
+        }
+    }
+
+    /// Send a request with the given method and (serialisable) params to the peer, and wait for
+    /// its result. See [`Peer::request`].
+    pub async fn request_with<P: Serialize>(
+        &self,
+        method: impl Into<String>,
+        params: Option<P>,
+    ) -> Result<Value, PeerError> {
+        let params = params
+            .map(|p| serde_json::to_value(p).and_then(Params::try_from))
+            .transpose()?;
+        self.request(method, params).await
+    }
+
+    /// Pass the peer's response to the request awaiting it. Returns `false` if no request is
+    /// awaiting a response with its ID.
+    pub fn respond(&self, response: ResponseItem) -> bool {
+        let id = match &response {
+            ResponseItem::Success { id, .. } | ResponseItem::Error { id, .. } => id,
+        };
+        match self.pending.lock().expect(POISONED).remove(id) {
+            Some(tx) => tx.send(response).is_ok(),
+            None => false,
+        }
+    }
+
+    /// Fail the requests awaiting a response, as the connection has closed.
+    pub(crate) fn close(&self) {
+        self.pending.lock().expect(POISONED).clear();
+    }
+
+    /// Send a notification to the peer.
+    pub fn notify(&self, notification: Notification) -> Result<(), PeerError> {
+        self.tx
+            .send(SendableMessage::Notification(notification))
+            .map_err(|_| PeerError::ConnectionClosed)
+    }
+
+    /// Send a notification with the given method and (serialisable) params to the peer.
+    pub fn notify_with<P: Serialize>(
+        &self,
+        method: impl Into<String>,
+        params: Option<P>,
+    ) -> Result<(), PeerError> {
+        let params = params
+            .map(|p| serde_json::to_value(p).and_then(Params::try_from))
+            .transpose()?;
+        self.notify(Notification::new(method.into(), params))
+    }
+
+    /// Whether the connection to the peer has been closed.
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed()
+    }
+
+    /// Wait until the connection to the peer has been closed.
+    pub(crate) async fn closed(&self) {
+        self.tx.closed().await
+    }
+
+    /// Whether both handles refer to the same peer.
+    pub fn same_peer(&self, other: &Peer) -> bool {
+        self.tx.same_channel(&other.tx)
+    }
+
+    /// The peer of the connection currently being served, if any.
+    ///
+    /// This is only available to code running within [`serve`]'s connection task (which includes
+    /// all handlers). Code that spawns a new task should obtain the peer beforehand.
+    ///
+    /// [`serve`]: crate::serve
+    pub fn current() -> Option<Peer> {
+        SessionState::current().map(|session| session.peer().clone())
+    }
+
+    /// Run `f` with this peer set as the [current] peer, in a new session.
+    ///
+    /// [`serve`] does this for you. This is only needed when driving an `MCPService` through some
+    /// other means (for example, your own transport), if you want handlers to be able to message
+    /// the client, or access session details (such as the client's info). To run several futures
+    /// in the same session, use a [`SessionHandle`] instead.
+    ///
+    /// [current]: Peer::current
+    /// [`serve`]: crate::serve
+    pub async fn scope<F: Future>(self, f: F) -> F::Output {
+        SessionHandle::new(self).scope(f).await
+    }
+}
@@ -0,0 +1,416 @@
+use crate::{
+    handler::{resource_meta, PromptHandler, ResourceHandler, ToolHandler},
+    peer::Peer,
+};
+use kuri_mcp_protocol::{jsonrpc::Notification, Content};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::{Arc, Mutex, RwLock},
+};
+
+pub(crate) type Tools = HashMap<String, Arc<dyn ToolHandler>>;
+pub(crate) type Prompts = HashMap<String, Arc<dyn PromptHandler>>;
+pub(crate) type Resources = HashMap<String, Arc<dyn ResourceHandler>>;
+
+/// Locks are only held briefly to read or update a map, so poisoning indicates a bug in kuri.
+const POISONED: &str = "registry lock poisoned";
+
+const TOOLS_LIST_CHANGED: &str = "notifications/tools/list_changed";
+const PROMPTS_LIST_CHANGED: &str = "notifications/prompts/list_changed";
+const RESOURCES_LIST_CHANGED: &str = "notifications/resources/list_changed";
+
+/// A handle to the tools, prompts and resources served by an [`MCPService`].
+///
+/// The registry allows handlers to be added and removed while the service is running (for
+/// example, as plugins are loaded). Each change notifies connected clients with the relevant
+/// `notifications/*/list_changed` notification, so that they can re-fetch the list.
+///
+/// The registry is cheap to clone, and all clones (and the `MCPService` it was obtained from) refer
+/// to the same set of handlers. With the `send` feature, it may be shared with other threads.
+///
+/// # Examples
+///
+/// ```rust
+/// use kuri::{MCPServiceBuilder, tool};
+///
+/// #[tool]
+/// async fn hello_world_tool() -> String {
+///     "Hello World".to_string()
+/// }
+///
+/// let service = MCPServiceBuilder::new("Plugin server".to_string()).build();
+/// let registry = service.registry();
+///
+/// // Later, once the plugin is loaded
+/// registry.add_tool(HelloWorldTool);
+/// ```
+///
+/// [`MCPService`]: crate::MCPService
+#[derive(Clone, Default)]
+pub struct Registry {
+    tools: Arc<RwLock<Tools>>,
+    prompts: Arc<RwLock<Prompts>>,
+    resources: Arc<RwLock<Resources>>,
+
+    /// Peers (connected clients) that are notified when the registry changes.
+    peers: Arc<Mutex<Vec<Peer>>>,
+}
+
+impl Registry {
+    // Handlers are only `Send + Sync` with the `send` feature, but the same `Arc`s are used either way.
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub(crate) fn new(tools: Tools, prompts: Prompts, resources: Resources) -> Self {
+        Self {
+            tools: Arc::new(RwLock::new(tools)),
+            prompts: Arc::new(RwLock::new(prompts)),
+            resources: Arc::new(RwLock::new(resources)),
+            peers: Arc::default(),
+        }
+    }
+
+    /// Add a tool, replacing any existing tool with the same name.
+    pub fn add_tool(&self, tool: impl ToolHandler) {
+        self.update(|update| update.add_tool(tool));
+    }
+
+    /// Add a tool, unless a tool with the same name is already registered. Returns whether the
+    /// tool was added.
+    pub fn try_add_tool(&self, tool: impl ToolHandler) -> bool {
+        self.update(|update| update.try_add_tool(tool))
+    }
+
+    /// Remove the tool with the given name. Returns whether the tool was present.
+    pub fn remove_tool(&self, name: &str) -> bool {
+        self.update(|update| update.remove_tool(name))
+    }
+
+    /// Add a prompt, replacing any existing prompt with the same name.
+    pub fn add_prompt(&self, prompt: impl PromptHandler) {
+        self.update(|update| update.add_prompt(prompt));
+    }
+
+    /// Add a prompt, unless a prompt with the same name is already registered. Returns whether
+    /// the prompt was added.
+    pub fn try_add_prompt(&self, prompt: impl PromptHandler) -> bool {
+        self.update(|update| update.try_add_prompt(prompt))
+    }
+
+    /// Remove the prompt with the given name. Returns whether the prompt was present.
+    pub fn remove_prompt(&self, name: &str) -> bool {
+        self.update(|update| update.remove_prompt(name))
+    }
+
+    /// Add a resource, replacing any existing resource with the same URI.
+    pub fn add_resource(&self, resource: impl ResourceHandler) {
+        self.update(|update| update.add_resource(resource));
+    }
+
+    /// Add a resource, unless a resource with the same URI is already registered. Returns whether
+    /// the resource was added.
+    pub fn try_add_resource(&self, resource: impl ResourceHandler) -> bool {
+        self.update(|update| update.try_add_resource(resource))
+    }
+
+    /// Remove the resource with the given URI. Returns whether the resource was present.
+    pub fn remove_resource(&self, uri: &str) -> bool {
+        self.update(|update| update.remove_resource(uri))
+    }
+
+    /// Make several changes to the registry, notifying clients once for each kind of handler that
+    /// changed (rather than once per change) when `f` returns.
+    ///
+    /// Each change is visible to requests as soon as it's made. Don't call the registry's own
+    /// methods from `f`; make all the changes through `update`.
+    ///
+    /// ```rust
+    /// use kuri::{MCPServiceBuilder, tool};
+    ///
+    /// #[tool]
+    /// async fn add(a: i32, b: i32) -> i32 {
+    ///     a + b
+    /// }
+    ///
+    /// #[tool]
+    /// async fn subtract(a: i32, b: i32) -> i32 {
+    ///     a - b
+    /// }
+    ///
+    /// let service = MCPServiceBuilder::new("Calculator".to_string()).build();
+    /// // Clients are sent a single `notifications/tools/list_changed`
+    /// service.registry().update(|update| {
+    ///     update.add_tool(Add);
+    ///     update.add_tool(Subtract);
+    /// });
+    /// ```
+    pub fn update<R>(&self, f: impl FnOnce(&mut RegistryUpdate<'_>) -> R) -> R {
+        let mut update = RegistryUpdate {
+            registry: self,
+            tools_changed: false,
+            prompts_changed: false,
+            resources_changed: false,
+        };
+        let result = f(&mut update);
+        if update.tools_changed {
+            self.notify(TOOLS_LIST_CHANGED);
+        }
+        if update.prompts_changed {
+            self.notify(PROMPTS_LIST_CHANGED);
+        }
+        if update.resources_changed {
+            self.notify(RESOURCES_LIST_CHANGED);
+        }
+        result
+    }
+
+    /// A link to the resource with the given URI, for a tool to return instead of the resource's
+    /// contents (which the client can read if it needs them). Returns `None` if no such resource
+    /// is registered.
+    ///
+    /// Tools can get the registry by taking it as an `#[extract]` parameter (or from the
+    /// [`RequestContext`](crate::context::RequestContext)). Clients older than protocol version
+    /// 2025-06-18 are given a description of the resource instead.
+    ///
+    /// ```rust
+    /// use kuri::{tool, Registry};
+    /// use kuri_mcp_protocol::Content;
+    ///
+    /// #[tool(description = "Find the files mentioning a word")]
+    /// async fn search(#[extract] registry: Registry, word: String) -> Vec<Content> {
+    ///     let hits = ["file:///notes.txt", "file:///todo.txt"];
+    ///     hits.iter()
+    ///         .filter_map(|uri| registry.resource_link(uri))
+    ///         .collect()
+    /// }
+    /// ```
+    pub fn resource_link(&self, uri: &str) -> Option<Content> {
+        let resource = self.resource(uri)?;
+        Some(Content::resource_link(resource_meta(&*resource)))
+    }
+
+    pub(crate) fn tool(&self, name: &str) -> Option<Arc<dyn ToolHandler>> {
+        self.tools.read().expect(POISONED).get(name).cloned()
+    }
+
+    pub(crate) fn tools(&self) -> Vec<Arc<dyn ToolHandler>> {
+        sorted(&self.tools.read().expect(POISONED))
+    }
+
+    pub(crate) fn prompt(&self, name: &str) -> Option<Arc<dyn PromptHandler>> {
+        self.prompts.read().expect(POISONED).get(name).cloned()
+    }
+
+    pub(crate) fn prompts(&self) -> Vec<Arc<dyn PromptHandler>> {
+        sorted(&self.prompts.read().expect(POISONED))
+    }
+
+    pub(crate) fn resource(&self, uri: &str) -> Option<Arc<dyn ResourceHandler>> {
+        self.resources.read().expect(POISONED).get(uri).cloned()
+    }
+
+    pub(crate) fn resources(&self) -> Vec<Arc<dyn ResourceHandler>> {
+        sorted(&self.resources.read().expect(POISONED))
+    }
+
+    /// Subscribe a peer to `list_changed` notifications. Subscribing the same peer more than once
+    /// has no effect.
+    ///
+    /// The peer is unsubscribed once its connection closes, so that peers of finished sessions
+    /// don't accumulate while the registry is unchanged.
+    pub(crate) fn subscribe(&self, peer: Peer) {
+        let mut peers = self.peers.lock().expect(POISONED);
+        peers.retain(|p| !p.is_closed());
+        if peers.iter().any(|p| p.same_peer(&peer)) {
+            return;
+        }
+        peers.push(peer.clone());
+
+        // Outside of a runtime, closed peers are instead dropped by the next subscription
+        if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+            let peers = Arc::downgrade(&self.peers);
+            runtime.spawn(async move {
+                peer.closed().await;
+                if let Some(peers) = peers.upgrade() {
+                    peers
+                        .lock()
+                        .expect(POISONED)
+                        .retain(|p| !p.same_peer(&peer));
+                }
+            });
+        }
+    }
+
+    /// Send a notification to every connected client (that has initialised its session).
+    ///
+    /// This is useful for messages that aren't specific to a request, such as log messages or
+    /// `notifications/resources/updated`.
+    pub fn broadcast(&self, notification: Notification) {
+        self.peers
+            .lock()
+            .expect(POISONED)
+            .retain(|peer| peer.notify(notification.clone()).is_ok());
+    }
+
+    /// Notify all subscribed peers, dropping those whose connection has closed.
+    fn notify(&self, method: &str) {
+        self.broadcast(Notification::new(method.to_string(), None));
+    }
+}
+
+/// A batch of changes to a [`Registry`], made with [`Registry::update`].
+pub struct RegistryUpdate<'a> {
+    registry: &'a Registry,
+    tools_changed: bool,
+    prompts_changed: bool,
+    resources_changed: bool,
+}
+
+impl RegistryUpdate<'_> {
+    /// Add a tool, replacing any existing tool with the same name.
+    pub fn add_tool(&mut self, tool: impl ToolHandler) {
+        let name = tool.name().to_string();
+        self.registry
+            .tools
+            .write()
+            .expect(POISONED)
+            .insert(name, Arc::new(tool));
+        self.tools_changed = true;
+    }
+
+    /// Add a tool, unless a tool with the same name is already registered. Returns whether the
+    /// tool was added.
+    pub fn try_add_tool(&mut self, tool: impl ToolHandler) -> bool {
+        let added = try_insert(&self.registry.tools, tool.name().to_string(), || {
+            Arc::new(tool)
+        });
+        self.tools_changed |= added;
+        added
+    }
+
+    /// Remove the tool with the given name. Returns whether the tool was present.
+    pub fn remove_tool(&mut self, name: &str) -> bool {
+        let removed = self
+            .registry
+            .tools
+            .write()
+            .expect(POISONED)
+            .remove(name)
+            .is_some();
+        self.tools_changed |= removed;
+        removed
+    }
+
+    /// Add a prompt, replacing any existing prompt with the same name.
+    pub fn add_prompt(&mut self, prompt: impl PromptHandler) {
+        let name = prompt.name().to_string();
+        self.registry
+            .prompts
+            .write()
+            .expect(POISONED)
+            .insert(name, Arc::new(prompt));
+        self.prompts_changed = true;
+    }
+
+    /// Add a prompt, unless a prompt with the same name is already registered. Returns whether
+    /// the prompt was added.
+    pub fn try_add_prompt(&mut self, prompt: impl PromptHandler) -> bool {
+        let added = try_insert(&self.registry.prompts, prompt.name().to_string(), || {
+            Arc::new(prompt)
+        });
+        self.prompts_changed |= added;
+        added
+    }
+
+    /// Remove the prompt with the given name. Returns whether the prompt was present.
+    pub fn remove_prompt(&mut self, name: &str) -> bool {
+        let removed = self
+            .registry
+            .prompts
+            .write()
+            .expect(POISONED)
+            .remove(name)
+            .is_some();
+        self.prompts_changed |= removed;
+        removed
+    }
+
+    /// Add a resource, replacing any existing resource with the same URI.
+    pub fn add_resource(&mut self, resource: impl ResourceHandler) {
+        let uri = resource.uri().to_string();
+        self.registry
+            .resources
+            .write()
+            .expect(POISONED)
+            .insert(uri, Arc::new(resource));
+        self.resources_changed = true;
+    }
+
+    /// Add a resource, unless a resource with the same URI is already registered. Returns whether
+    /// the resource was added.
+    pub fn try_add_resource(&mut self, resource: impl ResourceHandler) -> bool {
+        let added = try_insert(&self.registry.resources, resource.uri().to_string(), || {
+            Arc::new(resource)
+        });
+        self.resources_changed |= added;
+        added
+    }
+
+    /// Remove the resource with the given URI. Returns whether the resource was present.
+    pub fn remove_resource(&mut self, uri: &str) -> bool {
+        let removed = self
+            .registry
+            .resources
+            .write()
+            .expect(POISONED)
+            .remove(uri)
+            .is_some();
+        self.resources_changed |= removed;
+        removed
+    }
+}
+
+/// Insert the handler made by `handler` under `key`, unless the key is taken. Returns whether it
+/// was inserted.
+fn try_insert<H: ?Sized>(
+    handlers: &RwLock<HashMap<String, Arc<H>>>,
+    key: String,
+    handler: impl FnOnce() -> Arc<H>,
+) -> bool {
+    match handlers.write().expect(POISONED).entry(key) {
+        Entry::Occupied(_) => false,
+        Entry::Vacant(entry) => {
+            entry.insert(handler());
+            true
+        }
+    }
+}
+
+/// The handlers in a map, ordered by key, so that listings are stable.
+fn sorted<H: ?Sized>(handlers: &HashMap<String, Arc<H>>) -> Vec<Arc<H>> {
+    let mut entries: Vec<_> = handlers.iter().collect();
+    entries.sort_unstable_by_key(|(key, _)| *key);
+    entries
+        .into_iter()
+        .map(|(_, handler)| handler.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_unsubscribe_closed_peers() {
+        let registry = Registry::default();
+        let (peer, outbound) = Peer::new();
+        let (other, _other_outbound) = Peer::new();
+        registry.subscribe(peer.clone());
+        registry.subscribe(peer);
+        registry.subscribe(other);
+        assert_eq!(registry.peers.lock().unwrap().len(), 2);
+
+        // The session ends, without the registry changing
+        drop(outbound);
+        tokio::task::yield_now().await;
+        assert_eq!(registry.peers.lock().unwrap().len(), 1);
+    }
+}
@@ -1,5 +1,16 @@
-use kuri_mcp_protocol::{messages::CallToolResult, tool::ToolError, Content};
-use std::fmt;
+use kuri_mcp_protocol::{
+    binary,
+    content::ImageContent,
+    jsonrpc::ErrorData,
+    messages::{CallToolResult, GetPromptResult},
+    prompt::{PromptError, PromptMessage, PromptMessageRole},
+    tool::{generate_tool_schema, ToolError},
+    Content,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::Value;
+use std::{error::Error, fmt};
 
 /// Trait for generating tool responses.
 ///
@@ -20,15 +31,32 @@ use std::fmt;
 /// [tool error handling].
 ///
 /// [tool error handling]: https://modelcontextprotocol.io/specification/2025-03-26/server/tools#error-handling
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be returned from a tool",
+    label = "not a tool result",
+    note = "tools can return text (eg a `String` or number), `Json<T>`, an `Image`, `Content` or `Vec<Content>`, `()`, or an `Option` or `Result` of one of these",
+    note = "to return another type, implement `IntoCallToolResult` for it"
+)]
 pub trait IntoCallToolResult {
     /// Create a `CallToolResult` from the current type.
     fn into_call_tool_result(self) -> Result<CallToolResult, ToolError>;
+
+    /// A JSON schema describing the structured content produced by this type, if any.
+    ///
+    /// `#[tool]` uses this to advertise a tool's `outputSchema`, based on the handler's return type.
+    fn output_schema() -> Option<Value>
+    where
+        Self: Sized,
+    {
+        None
+    }
 }
 
 /// Helper function to create a successful CallToolResult with a single text content
 fn successful_text_response<S: Into<String>>(text: S) -> Result<CallToolResult, ToolError> {
     Ok(CallToolResult {
         content: vec![Content::text(text)],
+        structured_content: None,
         is_error: false,
     })
 }
@@ -55,57 +83,268 @@ impl IntoCallToolResult for Vec<Content> {
     fn into_call_tool_result(self) -> Result<CallToolResult, ToolError> {
         Ok(CallToolResult {
             content: self,
+            structured_content: None,
             is_error: false,
         })
     }
 }
 
+impl IntoCallToolResult for Content {
+    fn into_call_tool_result(self) -> Result<CallToolResult, ToolError> {
+        vec![self].into_call_tool_result()
+    }
+}
+
+impl IntoCallToolResult for ImageContent {
+    fn into_call_tool_result(self) -> Result<CallToolResult, ToolError> {
+        Content::Image(self).into_call_tool_result()
+    }
+}
+
 impl IntoCallToolResult for () {
     fn into_call_tool_result(self) -> Result<CallToolResult, ToolError> {
         Ok(CallToolResult {
             content: vec![],
+            structured_content: None,
             is_error: false,
         })
     }
 }
 
-/// Handler returns a Result<T, ToolError>
+/// Handler returns an `Option<T>`
 ///
-/// ExecutionErrors are converted into an error `CallToolResponse`, while other cases are
-/// propagated up to the transport.
-impl<T> IntoCallToolResult for Result<T, ToolError>
+/// `None` is an empty result, like `()`. As `None` has no structured content, `Option<Json<T>>`
+/// doesn't advertise an output schema.
+impl<T> IntoCallToolResult for Option<T>
 where
     T: IntoCallToolResult,
 {
     fn into_call_tool_result(self) -> Result<CallToolResult, ToolError> {
         match self {
-            Ok(value) => value.into_call_tool_result(),
-            Err(err) => match err {
-                // Map ExecutionError to Ok result with error content
-                ToolError::ExecutionError(msg) => Ok(CallToolResult {
-                    content: vec![Content::text(format!("Error: {}", msg))],
-                    is_error: true,
-                }),
-                // Propagate other ToolError variants directly
-                other_err => Err(other_err),
-            },
+            Some(value) => value.into_call_tool_result(),
+            None => ().into_call_tool_result(),
         }
     }
 }
 
-/// Handler returns a Result<T, S>, where S implements Display
-/// We treat these as logical errors.
-impl<T> IntoCallToolResult for Result<T, DisplayableError>
+/// Handler returns a `Result<T, E>`, where `E` is any error (including `anyhow::Error`, and a
+/// `String` or `&str` message)
+///
+/// A [`ToolError`] is handled specially: `ExecutionError`s are converted into an error
+/// `CallToolResult`, while other cases are propagated up to the transport. An [`ErrorData`] is also
+/// propagated, and sent to the client as a JSON-RPC error with its own code and data. Any other
+/// error is a logical error, reported to the client as an error `CallToolResult` describing the
+/// error and its sources.
+///
+/// [`ErrorData`]: kuri_mcp_protocol::jsonrpc::ErrorData
+impl<T, E> IntoCallToolResult for Result<T, E>
 where
     T: IntoCallToolResult,
+    E: Into<Box<dyn Error>>,
 {
     fn into_call_tool_result(self) -> Result<CallToolResult, ToolError> {
-        match self {
-            Ok(value) => value.into_call_tool_result(),
-            Err(err) => Ok(CallToolResult {
-                content: vec![Content::text(err.to_string())],
-                is_error: true,
+        let err = match self {
+            Ok(value) => return value.into_call_tool_result(),
+            Err(err) => err.into(),
+        };
+        let err = match err.downcast::<ErrorData>() {
+            Ok(error) => return Err(ToolError::Rpc(*error)),
+            Err(err) => err,
+        };
+        let message = match err.downcast::<ToolError>() {
+            // Map ExecutionError to Ok result with error content
+            Ok(tool_error) => match *tool_error {
+                ToolError::ExecutionError(msg) => format!("Error: {}", msg),
+                // Propagate other ToolError variants directly
+                other_err => return Err(other_err),
+            },
+            Err(err) => error_chain(&*err),
+        };
+        Ok(CallToolResult {
+            content: vec![Content::text(message)],
+            structured_content: None,
+            is_error: true,
+        })
+    }
+
+    fn output_schema() -> Option<Value> {
+        T::output_schema()
+    }
+}
+
+/// Describe an error along with its sources, eg "Failed to read config: file not found".
+fn error_chain(err: &dyn Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(err) = source {
+        message.push_str(&format!(": {err}"));
+        source = err.source();
+    }
+    message
+}
+
+/// Trait for generating prompt responses.
+///
+/// The MCP protocol requires `prompts/get` invocations to return a list of [`PromptMessage`]s, and
+/// optionally a description. Prompt handlers must return a value that implements this trait:
+///
+/// * A `String` is returned as a single text message from the user.
+/// * A `Vec<PromptMessage>` may contain multiple messages (eg a conversation with assistant turns),
+///   with any kind of content.
+/// * A [`GetPromptResult`] additionally allows setting the result's description.
+/// * A `Result<T, E>` of any of the above propagates the error to the client, where `E` is a
+///   `PromptError` or an [`ErrorData`] (a JSON-RPC error with its own code and data).
+///
+/// [`ErrorData`]: kuri_mcp_protocol::jsonrpc::ErrorData
+///
+/// As with [`IntoCallToolResult`], you may implement this trait for your own types.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` can't be returned from a prompt",
+    label = "not a prompt result",
+    note = "prompts can return a `String`, `Vec<PromptMessage>`, `GetPromptResult`, or a `Result` of one of these with a `PromptError` or `ErrorData`",
+    note = "to return another type, implement `IntoGetPromptResult` for it"
+)]
+pub trait IntoGetPromptResult {
+    /// Create a `GetPromptResult` from the current type.
+    fn into_get_prompt_result(self) -> Result<GetPromptResult, PromptError>;
+}
+
+impl IntoGetPromptResult for String {
+    fn into_get_prompt_result(self) -> Result<GetPromptResult, PromptError> {
+        vec![PromptMessage::new_text(PromptMessageRole::User, self)].into_get_prompt_result()
+    }
+}
+
+impl IntoGetPromptResult for Vec<PromptMessage> {
+    fn into_get_prompt_result(self) -> Result<GetPromptResult, PromptError> {
+        Ok(GetPromptResult {
+            description: None,
+            messages: self,
+        })
+    }
+}
+
+impl IntoGetPromptResult for GetPromptResult {
+    fn into_get_prompt_result(self) -> Result<GetPromptResult, PromptError> {
+        Ok(self)
+    }
+}
+
+impl<T, E> IntoGetPromptResult for Result<T, E>
+where
+    T: IntoGetPromptResult,
+    E: Into<PromptError>,
+{
+    fn into_get_prompt_result(self) -> Result<GetPromptResult, PromptError> {
+        self.map_err(Into::into).and_then(T::into_get_prompt_result)
+    }
+}
+
+/// Structured tool output.
+///
+/// Returning `Json<T>` from a tool responds with `T` as the result's structured content, along with
+/// its pretty-printed JSON serialisation as text content (for clients that don't support
+/// structured output).
+/// `#[tool]` also advertises `T`'s JSON schema as the tool's output schema.
+///
+/// The spec requires structured content to be a JSON object, so `T` should serialise to one (for
+/// example, a struct).
+///
+/// ```rust
+/// use kuri::{response::Json, tool};
+/// use schemars::JsonSchema;
+/// use serde::Serialize;
+///
+/// #[derive(Serialize, JsonSchema)]
+/// struct Weather {
+///     temperature: f32,
+///     conditions: String,
+/// }
+///
+/// #[tool(description = "Get the current weather")]
+/// async fn get_weather(city: String) -> Json<Weather> {
+///     Json(Weather {
+///         temperature: 21.5,
+///         conditions: "Sunny".to_string(),
+///     })
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Json<T>(pub T);
+
+impl<T> IntoCallToolResult for Json<T>
+where
+    T: Serialize + JsonSchema,
+{
+    fn into_call_tool_result(self) -> Result<CallToolResult, ToolError> {
+        match serde_json::to_value(&self.0) {
+            Ok(structured) => Ok(CallToolResult {
+                content: vec![Content::text(format!("{structured:#}"))],
+                structured_content: Some(structured),
+                is_error: false,
             }),
+            // Reported to the client in the same way as a handler's execution error
+            Err(e) => Err::<(), _>(ToolError::ExecutionError(format!(
+                "Failed to serialise output: {e}"
+            )))
+            .into_call_tool_result(),
+        }
+    }
+
+    fn output_schema() -> Option<Value> {
+        generate_tool_schema::<T>().ok()
+    }
+}
+
+/// An image tool output, given as raw bytes, which are base64-encoded in the response. Images
+/// over the [`binary::DEFAULT_MAX_SIZE`] (or the limit given with [`Image::with_limit`]) are
+/// reported to the client as an execution error.
+///
+/// To identify the image's MIME type from its data instead, return
+/// [`Content::image_bytes`](kuri_mcp_protocol::Content::image_bytes).
+///
+/// ```rust
+/// use kuri::{response::Image, tool};
+///
+/// #[tool(description = "Plot some values as a chart")]
+/// async fn plot(values: Vec<f64>) -> Image {
+///     Image::new(render_png(&values), "image/png")
+/// }
+/// # fn render_png(_values: &[f64]) -> Vec<u8> {
+/// #     Vec::new()
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Image {
+    data: Vec<u8>,
+    mime_type: String,
+    limit: usize,
+}
+
+impl Image {
+    /// An image with the given bytes, of type `mime_type` (eg `image/png`).
+    pub fn new(data: impl Into<Vec<u8>>, mime_type: impl Into<String>) -> Self {
+        Self {
+            data: data.into(),
+            mime_type: mime_type.into(),
+            limit: binary::DEFAULT_MAX_SIZE,
+        }
+    }
+
+    /// Allow images of up to `limit` bytes, rather than the [`binary::DEFAULT_MAX_SIZE`].
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+impl IntoCallToolResult for Image {
+    fn into_call_tool_result(self) -> Result<CallToolResult, ToolError> {
+        match binary::encode_with_limit(&self.data, self.limit) {
+            Ok(data) => Content::image(data, self.mime_type).into_call_tool_result(),
+            Err(e) => {
+                Err::<(), _>(ToolError::ExecutionError(e.to_string())).into_call_tool_result()
+            }
         }
     }
 }
@@ -119,6 +358,8 @@ impl fmt::Display for DisplayableError {
     }
 }
 
+impl Error for DisplayableError {}
+
 /// Allow converting anything `Into<String>` into a `DisplayableError`
 impl<E: Into<String>> From<E> for DisplayableError {
     fn from(err: E) -> Self {
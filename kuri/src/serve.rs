@@ -1,10 +1,18 @@
-use crate::transport::{MessageParseError, TransportError};
+use crate::{
+    peer::Peer,
+    transport::{MessageParseError, TransportError},
+};
 use futures::{SinkExt, StreamExt};
 use kuri_mcp_protocol::jsonrpc::{
-    ErrorCode, ErrorData, Request, RequestId, Response, ResponseItem,
+    ErrorCode, ErrorData, Request, RequestId, Response, ResponseItem, SendableMessage,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::{collections::VecDeque, convert::Infallible};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc::UnboundedReceiver,
 };
-use std::convert::Infallible;
-use tokio::io::{AsyncRead, AsyncWrite};
 use tokio_util::codec::{Framed, LinesCodec, LinesCodecError};
 use tower::Service;
 
@@ -14,20 +22,55 @@ fn parse_message(line: Result<String, LinesCodecError>) -> Result<Request, Messa
     serde_json::from_str::<Request>(&line).map_err(MessageParseError::Deserialisation)
 }
 
-/// Write a JSON-RPC response on the transport.
+/// Parse the line as the client's response to a request sent through the [`Peer`], if it is one.
+fn parse_response(line: &str) -> Option<ResponseItem> {
+    let value = serde_json::from_str::<Value>(line).ok()?;
+    let map = value.as_object()?;
+    if map.contains_key("method")
+        || !map.contains_key("id")
+        || !(map.contains_key("result") || map.contains_key("error"))
+    {
+        return None;
+    }
+    serde_json::from_value(value).ok()
+}
+
+/// Pass the client's response to the handler awaiting it.
+fn route_response(peer: &Peer, response: ResponseItem) {
+    if !peer.respond(response) {
+        tracing::debug!("Received a response to an unknown request");
+    }
+}
+
+/// Write a JSON-RPC message (usually a response) on the transport.
 #[inline]
-async fn write_message<T>(
+async fn write_message<T, M>(
     frame: &mut Framed<T, LinesCodec>,
-    msg: Response,
+    msg: M,
 ) -> Result<(), TransportError>
 where
     T: AsyncWrite + Unpin,
+    M: Serialize,
 {
     let json = serde_json::to_string(&msg)?;
     frame.send(json).await?;
     Ok(())
 }
 
+/// Write all messages currently queued for the client (eg notifications sent by handlers).
+async fn flush_outbound<T>(
+    frame: &mut Framed<T, LinesCodec>,
+    outbound: &mut UnboundedReceiver<SendableMessage>,
+) -> Result<(), TransportError>
+where
+    T: AsyncWrite + Unpin,
+{
+    while let Ok(msg) = outbound.try_recv() {
+        write_message(frame, msg).await?;
+    }
+    Ok(())
+}
+
 async fn handle_connection<S, T>(mut service: S, transport: T) -> Result<(), TransportError>
 where
     S: Service<Request, Response = Response, Error = Infallible>,
@@ -37,39 +80,143 @@ where
     // TODO: consider a max length for lines
     let mut frame = Framed::new(transport, LinesCodec::new());
 
-    // Process the stream in lines indefinitely, until the connection closes
-    while let Some(line) = frame.next().await {
-        match parse_message(line) {
-            Ok(message) => {
-                // Process the message
-                let response = service
-                    .call(message)
-                    .await
-                    .expect("MCPService is infallible");
-                if !response.is_empty() {
-                    // Write the response, if needed
-                    if let Err(e) = write_message(&mut frame, response).await {
-                        tracing::error!(error = ?e, "Error writing response over transport");
+    // Messages sent to the client outside of the request/response cycle (eg notifications and
+    // requests) are queued through the peer, and written as soon as possible.
+    let (peer, mut outbound) = Peer::new();
+    let connection_peer = peer.clone();
+
+    peer.scope(async move {
+        let peer = connection_peer;
+        // Lines received while a request was being processed, which are handled in order after it
+        let mut backlog = VecDeque::new();
+
+        // Process the stream in lines indefinitely, until the connection closes
+        loop {
+            if let Some(line) = backlog.pop_front() {
+                handle_line(
+                    &mut service,
+                    &peer,
+                    &mut frame,
+                    &mut outbound,
+                    &mut backlog,
+                    line,
+                )
+                .await?;
+                continue;
+            }
+
+            tokio::select! {
+                line = frame.next() => match line {
+                    Some(line) => {
+                        handle_line(
+                            &mut service,
+                            &peer,
+                            &mut frame,
+                            &mut outbound,
+                            &mut backlog,
+                            line,
+                        )
+                        .await?
+                    }
+                    None => break,
+                },
+                Some(msg) = outbound.recv() => {
+                    if let Err(e) = write_message(&mut frame, msg).await {
+                        tracing::error!(error = ?e, "Error writing message over transport");
                     }
                 }
             }
-            Err(e) => {
-                // per JSON-RPC spec, we should respond with an "Invalid Request" error
-                // see: https://www.jsonrpc.org/specification#examples
-                match e {
-                    MessageParseError::Deserialisation(_) => {
-                        let error_data = ErrorData::new(
-                            ErrorCode::ParseError,
-                            "JSON parsing error when deserialising the message".to_string(),
-                        );
-                        let msg = ResponseItem::error(RequestId::Null, error_data);
-                        write_message(&mut frame, Response::Single(Some(msg))).await?;
-                        tracing::debug!(error = ?e, "Transport error (deserialisation)");
-                    }
-                    MessageParseError::LinesCodecError(_) => {
-                        // Transport error. But don't terminate the connection: we continue looping
-                        tracing::error!(error = ?e, "Transport error");
+        }
+
+        // The client can no longer respond to requests
+        peer.close();
+
+        // Flush any messages queued while handling the final request
+        flush_outbound(&mut frame, &mut outbound).await
+    })
+    .await
+}
+
+/// Process a single line received over the transport, writing the response (if any), along with
+/// any messages queued for the client while processing it.
+///
+/// While a request is processed, messages queued for the client are written straight away, and
+/// the client's responses to them are passed on to the handler awaiting them. Other lines received
+/// in the meantime are added to the backlog.
+async fn handle_line<S, T>(
+    service: &mut S,
+    peer: &Peer,
+    frame: &mut Framed<T, LinesCodec>,
+    outbound: &mut UnboundedReceiver<SendableMessage>,
+    backlog: &mut VecDeque<Result<String, LinesCodecError>>,
+    line: Result<String, LinesCodecError>,
+) -> Result<(), TransportError>
+where
+    S: Service<Request, Response = Response, Error = Infallible>,
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    if let Some(response) = line.as_deref().ok().and_then(parse_response) {
+        route_response(peer, response);
+        return Ok(());
+    }
+
+    match parse_message(line) {
+        Ok(message) => {
+            // Process the message
+            let call = service.call(message);
+            tokio::pin!(call);
+            let mut closed = false;
+            let response = loop {
+                tokio::select! {
+                    response = &mut call => break response.expect("MCPService is infallible"),
+                    Some(msg) = outbound.recv() => {
+                        if let Err(e) = write_message(frame, msg).await {
+                            tracing::error!(error = ?e, "Error writing message over transport");
+                        }
                     }
+                    line = frame.next(), if !closed => match line {
+                        Some(line) => match line.as_deref().ok().and_then(parse_response) {
+                            Some(response) => route_response(peer, response),
+                            None => backlog.push_back(line),
+                        },
+                        None => {
+                            // The client can no longer respond to requests
+                            closed = true;
+                            peer.close();
+                        }
+                    },
+                }
+            };
+
+            // Messages sent while handling the request (eg progress notifications) precede the
+            // response
+            if let Err(e) = flush_outbound(frame, outbound).await {
+                tracing::error!(error = ?e, "Error writing message over transport");
+            }
+
+            if !response.is_empty() {
+                // Write the response, if needed
+                if let Err(e) = write_message(frame, response).await {
+                    tracing::error!(error = ?e, "Error writing response over transport");
+                }
+            }
+        }
+        Err(e) => {
+            // per JSON-RPC spec, we should respond with an "Invalid Request" error
+            // see: https://www.jsonrpc.org/specification#examples
+            match e {
+                MessageParseError::Deserialisation(_) => {
+                    let error_data = ErrorData::new(
+                        ErrorCode::ParseError,
+                        "JSON parsing error when deserialising the message".to_string(),
+                    );
+                    let msg = ResponseItem::error(RequestId::Null, error_data);
+                    write_message(frame, Response::Single(Some(msg))).await?;
+                    tracing::debug!(error = ?e, "Transport error (deserialisation)");
+                }
+                MessageParseError::LinesCodecError(_) => {
+                    // Transport error. But don't terminate the connection: we continue looping
+                    tracing::error!(error = ?e, "Transport error");
                 }
             }
         }
@@ -1,20 +1,25 @@
 use crate::{
-    context::{Context, Inject},
-    errors::RequestError,
-    handler::{PromptHandler, ToolHandler},
+    compose::Prefixed,
+    context::{Context, Inject, RequestContext},
+    errors::{BuildError, HandlerKind, RequestError},
+    extract::{ClientInfo, ExtractError},
+    handler::{resource_meta, PromptHandler, ResourceHandler, ToolBox, ToolHandler},
+    maybe_send::{BoxFuture, MaybeSend, MaybeSendSync},
+    registry::{Prompts, Registry, Resources, Tools},
+    session::SessionState,
 };
-use futures::future::LocalBoxFuture;
 use kuri_mcp_protocol::{
     jsonrpc::{
         ErrorCode, ErrorData, MethodCall, Notification, Params, Request, RequestId, Response,
         ResponseItem, SendableMessage,
     },
     messages::{
-        CallToolResult, GetPromptResult, Implementation, InitializeResult, ListPromptsResult,
-        ListResourcesResult, ListToolsResult, PromptsCapability, ReadResourceResult,
-        ResourcesCapability, ServerCapabilities, ToolsCapability,
+        negotiate_protocol_version, CallToolResult, GetPromptResult, Implementation,
+        InitializeParams, InitializeResult, ListPromptsResult, ListResourcesResult,
+        ListToolsResult, PromptsCapability, ReadResourceResult, ResourcesCapability,
+        ServerCapabilities, ToolsCapability, LATEST_PROTOCOL_VERSION,
     },
-    prompt::{Prompt as PromptMeta, PromptError, PromptMessage, PromptMessageRole},
+    prompt::{Prompt as PromptMeta, PromptError},
     resource::{Resource as ResourceMeta, ResourceContents, ResourceError},
     tool::{Tool as ToolMeta, ToolError},
 };
@@ -22,12 +27,14 @@ use serde_json::json;
 use serde_json::Value;
 use std::task::Poll;
 use std::{collections::HashMap, future::Future};
-use std::{convert::Infallible, rc::Rc};
+use std::{convert::Infallible, sync::Arc};
 use tower::Service;
 
-type Tools = HashMap<String, Rc<dyn ToolHandler>>;
-type Prompts = HashMap<String, Rc<dyn PromptHandler>>;
-type NotificationHandler = Rc<dyn Fn(&Context, Notification) -> LocalBoxFuture<'static, ()>>;
+#[cfg(feature = "send")]
+type NotificationHandler =
+    Arc<dyn Fn(&Context, Notification) -> BoxFuture<'static, ()> + Send + Sync>;
+#[cfg(not(feature = "send"))]
+type NotificationHandler = Arc<dyn Fn(&Context, Notification) -> BoxFuture<'static, ()>>;
 
 /// A service that handles MCP requests.
 ///
@@ -36,28 +43,44 @@ type NotificationHandler = Rc<dyn Fn(&Context, Notification) -> LocalBoxFuture<'
 /// tools, prompts, and context, as well as the capabilities of the server. This is in contrast to
 /// `server.rs`, which runs continuously in a loop handling requests (passing them to `MCPService`)
 /// and middlemanning communication with the transport layer.
+///
+/// Tools, prompts and resources may be changed while the service is running, through its
+/// [`Registry`].
 #[derive(Clone)]
 pub struct MCPService {
     name: String,
     version: String,
     instructions: Option<String>,
-    tools: Rc<Tools>,
-    prompts: Rc<Prompts>,
-    ctx: Rc<Context>,
+    registry: Registry,
+    ctx: Arc<Context>,
 
     // raw message handlers
     notification_handler: Option<NotificationHandler>,
 }
 
-/// Build an MCPService. Tools and structs are defined when the MCPService is built. They cannot be
-/// modified after that time.
+/// Build an MCPService. State is defined when the MCPService is built, and cannot be modified after
+/// that time. Tools, prompts and resources registered here form the initial contents of the
+/// service's [`Registry`], which may be changed later.
+///
+/// Services assembled from several builders (eg tool sets defined in separate crates) can be
+/// combined with [`merge`] and [`nest`].
+///
+/// [`merge`]: MCPServiceBuilder::merge
+/// [`nest`]: MCPServiceBuilder::nest
 pub struct MCPServiceBuilder {
     name: String,
     version: String,
     instructions: Option<String>,
     tools: Tools,
     prompts: Prompts,
+    resources: Resources,
     ctx: Context,
+    /// Handlers registered under a name that was already taken, as (kind, name). Reported when
+    /// the service is built.
+    duplicates: Vec<(HandlerKind, String)>,
+    /// Types of state registered by more than one of the builders combined into this one.
+    /// Reported when the service is built.
+    conflicting_state: Vec<&'static str>,
 
     // raw message handlers
     notification_handler: Option<NotificationHandler>,
@@ -71,7 +94,10 @@ impl MCPServiceBuilder {
             instructions: None,
             tools: HashMap::new(),
             prompts: HashMap::new(),
+            resources: HashMap::new(),
             ctx: Context::default(),
+            duplicates: Vec::new(),
+            conflicting_state: Vec::new(),
             notification_handler: None,
         }
     }
@@ -87,37 +113,241 @@ impl MCPServiceBuilder {
     }
 
     pub fn with_tool(mut self, tool: impl ToolHandler) -> Self {
-        self.tools.insert(tool.name().to_string(), Rc::new(tool));
+        self.insert_tool(Arc::new(tool));
         self
     }
 
     pub fn with_prompt(mut self, prompt: impl PromptHandler) -> Self {
-        self.prompts
-            .insert(prompt.name().to_string(), Rc::new(prompt));
+        self.insert_prompt(Arc::new(prompt));
+        self
+    }
+
+    pub fn with_resource(mut self, resource: impl ResourceHandler) -> Self {
+        self.insert_resource(Arc::new(resource));
+        self
+    }
+
+    /// Register all the tools and prompts defined on `T` with [`#[tool_box]`](crate::tool_box).
+    /// Each handler calls its method on the shared `tool_box`.
+    pub fn with_tool_box<T: ToolBox>(mut self, tool_box: Arc<T>) -> Self {
+        for tool in T::tools(&tool_box) {
+            self.insert_tool(tool.into());
+        }
+        for prompt in T::prompts(&tool_box) {
+            self.insert_prompt(prompt.into());
+        }
         self
     }
 
-    pub fn with_state<T: 'static>(mut self, state: Inject<T>) -> Self {
+    /// Add the tools, prompts, resources and state of another builder to this one.
+    ///
+    /// The service keeps this builder's name, version and instructions. If both have a
+    /// notification handler, each notification is passed to both.
+    ///
+    /// Tools and prompts must have unique names (and resources unique URIs) across the merged
+    /// builders, and only one of them may register state of a given type. Collisions are reported
+    /// when the service is built; use [`nest`] to avoid name collisions.
+    ///
+    /// [`nest`]: MCPServiceBuilder::nest
+    pub fn merge(self, other: MCPServiceBuilder) -> Self {
+        self.nest("", other)
+    }
+
+    /// Add the tools, prompts, resources and state of another builder to this one, with `prefix`
+    /// prepended to the names of its tools and prompts. For example, after `nest("github_", github)`,
+    /// a `search` tool from `github` is served as `github_search`. Resources are identified by URI,
+    /// so aren't prefixed.
+    ///
+    /// Otherwise, this behaves like [`merge`].
+    ///
+    /// [`merge`]: MCPServiceBuilder::merge
+    // Handlers are only `Send + Sync` with the `send` feature, but the same `Arc` is used either way.
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn nest(mut self, prefix: &str, other: MCPServiceBuilder) -> Self {
+        for (kind, name) in other.duplicates {
+            let name = match kind {
+                HandlerKind::Resource => name,
+                HandlerKind::Tool | HandlerKind::Prompt => format!("{prefix}{name}"),
+            };
+            self.duplicates.push((kind, name));
+        }
+
+        for (name, tool) in other.tools {
+            if prefix.is_empty() {
+                self.insert_tool(tool);
+            } else {
+                self.insert_tool(Arc::new(Prefixed::new(prefix, &name, tool)));
+            }
+        }
+        for (name, prompt) in other.prompts {
+            if prefix.is_empty() {
+                self.insert_prompt(prompt);
+            } else {
+                self.insert_prompt(Arc::new(Prefixed::new(prefix, &name, prompt)));
+            }
+        }
+        for (_, resource) in other.resources {
+            self.insert_resource(resource);
+        }
+
+        self.conflicting_state.extend(other.conflicting_state);
+        let conflicts = self.ctx.merge(other.ctx);
+        self.conflicting_state.extend(conflicts);
+        self.notification_handler = match (self.notification_handler, other.notification_handler) {
+            (Some(first), Some(second)) => Some(Arc::new(move |ctx: &Context, notification| {
+                let first = first(ctx, Notification::clone(&notification));
+                let second = second(ctx, notification);
+                Box::pin(async move {
+                    first.await;
+                    second.await;
+                }) as BoxFuture<'static, ()>
+            })),
+            (first, second) => first.or(second),
+        };
+        self
+    }
+
+    pub fn with_state<T: MaybeSendSync + 'static>(mut self, state: Inject<T>) -> Self {
         self.ctx.insert(state);
         self
     }
 
+    /// Register a factory for state scoped to each session, which handlers may take as
+    /// [`Session<T>`]. Each session gets its own state, created by calling `factory` with the
+    /// context of the `initialize` request (so the client's details are available), and dropped
+    /// when the session ends. A session that's initialised again gets new state.
+    ///
+    /// [`Session<T>`]: crate::extract::Session
+    pub fn with_session_state<T, F>(mut self, factory: F) -> Self
+    where
+        T: MaybeSendSync + 'static,
+        F: Fn(&RequestContext) -> T + MaybeSendSync + 'static,
+    {
+        self.ctx.insert_session_factory(factory);
+        self
+    }
+
     pub fn with_notification_handler(
         mut self,
-        handler: impl Fn(&Context, Notification) -> LocalBoxFuture<'static, ()> + 'static,
+        handler: impl Fn(&Context, Notification) -> BoxFuture<'static, ()> + MaybeSendSync + 'static,
     ) -> Self {
-        self.notification_handler = Some(Rc::new(handler));
+        self.notification_handler = Some(Arc::new(handler));
         self
     }
 
+    /// Build the service, first checking that no two handlers share a name, that no two combined
+    /// builders registered state of the same type, and that the state needed by each handler (eg
+    /// types injected with [`Inject`]) has been registered with [`with_state`] or
+    /// [`with_session_state`]. Fails if any check does.
+    ///
+    /// Handlers added to the service's [`Registry`] after it's built aren't checked. If they're
+    /// missing state, requests to them fail with an internal error.
+    ///
+    /// [`with_state`]: MCPServiceBuilder::with_state
+    /// [`with_session_state`]: MCPServiceBuilder::with_session_state
+    pub fn try_build(self) -> Result<MCPService, BuildError> {
+        if let Some((kind, name)) = self.duplicates.first() {
+            return Err(BuildError::DuplicateName {
+                kind: *kind,
+                name: name.clone(),
+            });
+        }
+        if let Some(type_name) = self.conflicting_state.first() {
+            return Err(BuildError::ConflictingState { type_name });
+        }
+        self.verify_state()?;
+        Ok(self.build_unchecked())
+    }
+
+    /// Build the service.
+    ///
+    /// # Panics
+    ///
+    /// Panics if two handlers share a name, as a client couldn't tell which it would be served.
+    /// The other checks made by [`try_build`] are logged as warnings instead:
+    /// * Where combined builders registered state of the same type, the state of the builder
+    ///   that the others were merged into is kept.
+    /// * Requests to handlers missing state fail with an internal error.
+    ///
+    /// [`try_build`]: MCPServiceBuilder::try_build
     pub fn build(self) -> MCPService {
+        if let Some((kind, name)) = self.duplicates.first() {
+            let e = BuildError::DuplicateName {
+                kind: *kind,
+                name: name.clone(),
+            };
+            panic!("{e}");
+        }
+        for type_name in &self.conflicting_state {
+            let e = BuildError::ConflictingState { type_name };
+            tracing::warn!(error = %e, "Only the first builder's state is kept");
+        }
+        if let Err(e) = self.verify_state() {
+            tracing::warn!(error = %e, "Handler is missing state, so requests to it will fail");
+        }
+        self.build_unchecked()
+    }
+
+    fn insert_tool(&mut self, tool: Arc<dyn ToolHandler>) {
+        let name = tool.name().to_string();
+        if self.tools.insert(name.clone(), tool).is_some() {
+            self.duplicates.push((HandlerKind::Tool, name));
+        }
+    }
+
+    fn insert_prompt(&mut self, prompt: Arc<dyn PromptHandler>) {
+        let name = prompt.name().to_string();
+        if self.prompts.insert(name.clone(), prompt).is_some() {
+            self.duplicates.push((HandlerKind::Prompt, name));
+        }
+    }
+
+    fn insert_resource(&mut self, resource: Arc<dyn ResourceHandler>) {
+        let uri = resource.uri().to_string();
+        if self.resources.insert(uri.clone(), resource).is_some() {
+            self.duplicates.push((HandlerKind::Resource, uri));
+        }
+    }
+
+    /// Check that the state needed by every handler is present.
+    fn verify_state(&self) -> Result<(), BuildError> {
+        fn unsatisfied<'a>(
+            kind: HandlerKind,
+            name: &'a str,
+        ) -> impl FnOnce(ExtractError) -> BuildError + 'a {
+            move |source| BuildError::UnsatisfiedDependency {
+                kind,
+                name: name.to_string(),
+                source,
+            }
+        }
+
+        for (name, tool) in &self.tools {
+            tool.verify_state(&self.ctx)
+                .map_err(unsatisfied(HandlerKind::Tool, name))?;
+        }
+        for (name, prompt) in &self.prompts {
+            prompt
+                .verify_state(&self.ctx)
+                .map_err(unsatisfied(HandlerKind::Prompt, name))?;
+        }
+        for (uri, resource) in &self.resources {
+            resource
+                .verify_state(&self.ctx)
+                .map_err(unsatisfied(HandlerKind::Resource, uri))?;
+        }
+        Ok(())
+    }
+
+    // State is only `Send + Sync` with the `send` feature, but the same `Arc` is used either way.
+    #[allow(clippy::arc_with_non_send_sync)]
+    fn build_unchecked(self) -> MCPService {
         MCPService {
             name: self.name,
             version: self.version,
             instructions: self.instructions,
-            tools: Rc::new(self.tools),
-            prompts: Rc::new(self.prompts),
-            ctx: Rc::new(self.ctx),
+            registry: Registry::new(self.tools, self.prompts, self.resources),
+            ctx: Arc::new(self.ctx),
             notification_handler: self.notification_handler,
         }
     }
@@ -162,7 +392,6 @@ impl CapabilitiesBuilder {
     }
 
     /// Enable resources capability
-    #[allow(dead_code)]
     pub fn with_resources(mut self, subscribe: bool, list_changed: bool) -> Self {
         self.resources = Some(ResourcesCapability {
             subscribe: Some(subscribe),
@@ -191,17 +420,23 @@ trait MCPServiceTrait: 'static {
     fn list_tools(&self) -> Vec<ToolMeta>;
     fn call_tool(
         &self,
+        context: RequestContext,
         tool_name: &str,
         arguments: Value,
-    ) -> LocalBoxFuture<'static, Result<CallToolResult, ToolError>>;
+    ) -> BoxFuture<'static, Result<CallToolResult, ToolError>>;
     fn list_resources(&self) -> Vec<ResourceMeta>;
-    fn read_resource(&self, uri: &str) -> LocalBoxFuture<'static, Result<String, ResourceError>>;
+    fn read_resource(
+        &self,
+        context: RequestContext,
+        uri: &str,
+    ) -> BoxFuture<'static, Result<Vec<ResourceContents>, ResourceError>>;
     fn list_prompts(&self) -> Vec<PromptMeta>;
     fn get_prompt(
         &self,
+        context: RequestContext,
         prompt_name: &str,
         arguments: HashMap<String, serde_json::Value>,
-    ) -> LocalBoxFuture<'static, Result<String, PromptError>>;
+    ) -> BoxFuture<'static, Result<GetPromptResult, PromptError>>;
 }
 
 impl MCPServiceTrait for MCPService {
@@ -218,29 +453,26 @@ impl MCPServiceTrait for MCPService {
     }
 
     fn capabilities(&self) -> kuri_mcp_protocol::messages::ServerCapabilities {
-        // MCPService only allows tools and prompts to be registered at build time, after which they
-        // cannot be changed. Consequently, we set `list_changed` to false, though "true" would be
-        // equally correct.
-
-        let mut builder = CapabilitiesBuilder::new();
-        if !self.tools.is_empty() {
-            builder = builder.with_tools(false);
-        }
-        if !self.prompts.is_empty() {
-            builder = builder.with_prompts(false);
-        }
-        // if self.resources.len() > 0 {
-        //     builder.with_resources(true, true);
-        // }
-
-        builder.build()
+        // Tools, prompts and resources may be added to the registry at any time, even if there are
+        // none when the client connects. So we advertise all of them, and notify clients when the
+        // lists change.
+        CapabilitiesBuilder::new()
+            .with_tools(true)
+            .with_prompts(true)
+            .with_resources(false, true)
+            .build()
     }
 
     /// List tool schema for all tools registered with this MCP server.
     fn list_tools(&self) -> Vec<ToolMeta> {
-        self.tools
+        self.registry
+            .tools()
             .iter()
-            .map(|(name, tool)| ToolMeta::new(name.clone(), tool.description(), tool.schema()))
+            .map(|tool| ToolMeta {
+                output_schema: tool.output_schema(),
+                annotations: tool.annotations(),
+                ..ToolMeta::new(tool.name(), tool.description(), tool.schema())
+            })
             .collect()
     }
 
@@ -252,37 +484,55 @@ impl MCPServiceTrait for MCPService {
     ///   contain arguments not used by the tool handler.
     fn call_tool(
         &self,
+        context: RequestContext,
         tool_name: &str,
         arguments: serde_json::Value,
-    ) -> LocalBoxFuture<'static, Result<CallToolResult, ToolError>> {
-        let tool = match self.tools.get(tool_name) {
-            Some(tool) => tool.clone(),
+    ) -> BoxFuture<'static, Result<CallToolResult, ToolError>> {
+        let tool = match self.registry.tool(tool_name) {
+            Some(tool) => tool,
             None => {
                 return Box::pin(futures::future::ready(Err(ToolError::NotFound(
                     tool_name.to_string(),
                 ))))
             }
         };
-        let ctx = self.ctx.clone();
-        Box::pin(async move { tool.call(&ctx, arguments).await })
+        Box::pin(async move { tool.call(&context, arguments).await })
     }
 
+    /// List metadata for all resources registered with this MCP server.
     fn list_resources(&self) -> Vec<ResourceMeta> {
-        // TODO implement
-        vec![]
+        self.registry
+            .resources()
+            .iter()
+            .map(|resource| resource_meta(&**resource))
+            .collect()
     }
 
-    fn read_resource(&self, _uri: &str) -> LocalBoxFuture<'static, Result<String, ResourceError>> {
-        // TODO implement
-        Box::pin(futures::future::ready(Err(ResourceError::ExecutionError(
-            "Reading resources is not yet implemented".into(),
-        ))))
+    /// Read the resource with the given URI.
+    ///
+    /// Guarantees:
+    /// * `uri` is *not* guaranteed to be a registered resource.
+    fn read_resource(
+        &self,
+        context: RequestContext,
+        uri: &str,
+    ) -> BoxFuture<'static, Result<Vec<ResourceContents>, ResourceError>> {
+        let resource = match self.registry.resource(uri) {
+            Some(resource) => resource,
+            None => {
+                return Box::pin(futures::future::ready(Err(ResourceError::NotFound(
+                    uri.to_string(),
+                ))))
+            }
+        };
+        Box::pin(async move { resource.read(&context).await })
     }
 
     /// List prompt schema for all prompts registered with this MCP server.
     fn list_prompts(&self) -> Vec<PromptMeta> {
-        self.prompts
-            .values()
+        self.registry
+            .prompts()
+            .iter()
             .map(|prompt| PromptMeta::new(prompt.name(), prompt.description(), prompt.arguments()))
             .collect()
     }
@@ -295,25 +545,41 @@ impl MCPServiceTrait for MCPService {
     ///   contain arguments not used by the prompt handler.
     fn get_prompt(
         &self,
+        context: RequestContext,
         prompt_name: &str,
         arguments: HashMap<String, serde_json::Value>,
-    ) -> LocalBoxFuture<'static, Result<String, PromptError>> {
-        let prompt = match self.prompts.get(prompt_name) {
-            Some(prompt) => prompt.clone(),
+    ) -> BoxFuture<'static, Result<GetPromptResult, PromptError>> {
+        let prompt = match self.registry.prompt(prompt_name) {
+            Some(prompt) => prompt,
             None => {
                 return Box::pin(futures::future::ready(Err(PromptError::NotFound(
                     prompt_name.to_string(),
                 ))));
             }
         };
-        let ctx = self.ctx.clone();
         Box::pin(async move {
-            let result = prompt.call(&ctx, arguments).await?;
+            let result = prompt.call(&context, arguments).await?;
             Ok(result)
         })
     }
 }
 
+/// The protocol version negotiated for the current session. Requests handled outside of an
+/// initialised session are served the latest version.
+fn session_protocol_version() -> String {
+    SessionState::current()
+        .and_then(|session| session.client_info())
+        .map(|info| info.protocol_version)
+        .unwrap_or_else(|| LATEST_PROTOCOL_VERSION.to_string())
+}
+
+/// Get the metadata (`_meta` field) from request parameters, if any
+fn get_request_meta(
+    params: &serde_json::Map<String, Value>,
+) -> Option<serde_json::Map<String, Value>> {
+    params.get("_meta").and_then(Value::as_object).cloned()
+}
+
 /// Validate and return request parameters
 fn get_request_params(
     params: Option<Params>,
@@ -349,9 +615,43 @@ impl MCPService {
         req: MethodCall,
     ) -> impl Future<Output = Result<ResponseItem, RequestError>> + '_ {
         async move {
+            let params = match req.params {
+                Some(Params::Map(params)) => params,
+                _ => serde_json::Map::new(),
+            };
+            // Use the client's version if it's supported, otherwise propose the latest
+            let protocol_version = negotiate_protocol_version(
+                params
+                    .get("protocolVersion")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default(),
+            );
+
+            if let Some(session) = SessionState::current() {
+                // Record the client's details for the rest of the session. These aren't required
+                // by kuri itself, so clients sending malformed details aren't rejected.
+                if let Ok(init) =
+                    serde_json::from_value::<InitializeParams>(Value::Object(params.clone()))
+                {
+                    session.set_client_info(ClientInfo {
+                        name: init.client_info.name,
+                        version: init.client_info.version,
+                        capabilities: init.capabilities,
+                        protocol_version: protocol_version.to_string(),
+                    });
+                }
+
+                // Create the session's state (replacing any from a previous initialisation)
+                let context = self.request_context(req.id.clone(), &params);
+                session.set_state(self.ctx.create_session_state(&context));
+
+                // Notify the client of changes to the registry from now on
+                self.registry.subscribe(session.peer().clone());
+            }
+
             // Build response content
             let result = InitializeResult {
-                protocol_version: "2024-11-05".to_string(),
+                protocol_version: protocol_version.to_string(),
                 capabilities: self.capabilities(),
                 server_info: Implementation {
                     name: self.name(),
@@ -376,7 +676,12 @@ impl MCPService {
             // No request arguments required.
 
             // Build response content
-            let tools = self.list_tools();
+            let version = session_protocol_version();
+            let tools = self
+                .list_tools()
+                .into_iter()
+                .map(|tool| tool.for_protocol_version(&version))
+                .collect();
             let result = ListToolsResult { tools };
 
             // Serialise response
@@ -401,9 +706,13 @@ impl MCPService {
                 .ok_or_else(|| RequestError::InvalidParams("No tool name was provided".into()))?;
 
             let arguments = params.get("arguments").cloned().unwrap_or(Value::Null);
+            let context = self.request_context(req.id.clone(), &params);
 
             // Call tool and build response content
-            let result = self.call_tool(name, arguments).await?;
+            let result = self
+                .call_tool(context, name, arguments)
+                .await?
+                .for_protocol_version(&session_protocol_version());
 
             // Serialise response
             let result = serde_json::to_value(result)
@@ -445,15 +754,14 @@ impl MCPService {
                 .and_then(Value::as_str)
                 .ok_or_else(|| RequestError::InvalidParams("Missing resource URI".into()))?;
 
+            let context = self.request_context(req.id.clone(), &params);
+
             // Read resource and build response content
-            let contents = self.read_resource(uri).await.map_err(RequestError::from)?;
-            let result = ReadResourceResult {
-                contents: vec![ResourceContents::TextResourceContents {
-                    uri: uri.to_string(),
-                    mime_type: Some("text/plain".to_string()),
-                    text: contents,
-                }],
-            };
+            let contents = self
+                .read_resource(context, uri)
+                .await
+                .map_err(RequestError::from)?;
+            let result = ReadResourceResult { contents };
 
             let result = serde_json::to_value(result)
                 .map_err(|e| RequestError::Internal(format!("JSON serialization error: {}", e)))?;
@@ -494,53 +802,68 @@ impl MCPService {
                 .and_then(Value::as_str)
                 .ok_or_else(|| RequestError::InvalidParams("Missing prompt name".into()))?;
 
-            // Ensure arguments are provided,
-            // TODO: Only error if arguments are required.
-            let arguments = params
-                .get("arguments")
-                .and_then(Value::as_object)
-                .ok_or_else(|| RequestError::InvalidParams("Missing arguments object".into()))?;
-            // then convert from serde_json::Map<String, Value> to HashMap<String, Value>
-            let arguments: HashMap<String, serde_json::Value> = arguments
-                .iter()
-                .map(|(k, v)| (k.to_string(), v.clone()))
-                .collect();
+            // Arguments may be omitted, in which case the prompt reports any it requires
+            let arguments: HashMap<String, serde_json::Value> = match params.get("arguments") {
+                None | Some(Value::Null) => HashMap::new(),
+                Some(Value::Object(arguments)) => arguments
+                    .iter()
+                    .map(|(k, v)| (k.to_string(), v.clone()))
+                    .collect(),
+                Some(_) => {
+                    return Err(RequestError::InvalidParams(
+                        "Prompt arguments must be an object".into(),
+                    ))
+                }
+            };
 
-            // Call prompt handler and build response content
-            let prompt_message =
-                self.get_prompt(prompt_name, arguments)
-                    .await
-                    .map_err(|e| match e {
-                        PromptError::InvalidParameters(_) => {
-                            RequestError::InvalidParams(e.to_string())
-                        }
-                        PromptError::NotFound(_) => RequestError::InvalidParams(e.to_string()),
-                        PromptError::InternalError(_) => RequestError::Internal(e.to_string()),
-                    })?;
+            let context = self.request_context(req.id.clone(), &params);
 
-            let messages = vec![PromptMessage::new_text(
-                // TODO: Unclear role correctness.
-                PromptMessageRole::User,
-                prompt_message.to_string(),
-            )];
+            // Call prompt handler
+            let prompt_result = self
+                .get_prompt(context, prompt_name, arguments)
+                .await
+                .map_err(|e| match e {
+                    PromptError::InvalidParameters(msg) => RequestError::InvalidParams(msg),
+                    PromptError::NotFound(name) => RequestError::PromptNotFound(name),
+                    PromptError::InternalError(msg) => RequestError::Internal(msg),
+                    PromptError::Rpc(error) => RequestError::Rpc(error),
+                })?;
 
             // Build final response and serialise
-            let result = serde_json::to_value(GetPromptResult {
-                // TODO: Unclear if we need `description` here.
-                description: None,
-                messages,
-            })
-            .map_err(|e| RequestError::Internal(format!("JSON serialization error: {}", e)))?;
+            let result = serde_json::to_value(prompt_result)
+                .map_err(|e| RequestError::Internal(format!("JSON serialization error: {}", e)))?;
             let response = ResponseItem::success(req.id, result);
             Ok(response)
         }
     }
 }
 
+impl MCPService {
+    /// Create the context for a request handled by this service.
+    fn request_context(
+        &self,
+        id: RequestId,
+        params: &serde_json::Map<String, Value>,
+    ) -> RequestContext {
+        RequestContext::new(
+            self.ctx.clone(),
+            self.registry.clone(),
+            id,
+            get_request_meta(params),
+        )
+    }
+
+    /// Get a handle to the service's registry of tools, prompts and resources, which may be used to
+    /// change them while the service is running.
+    pub fn registry(&self) -> Registry {
+        self.registry.clone()
+    }
+}
+
 impl Service<SendableMessage> for MCPService {
     type Response = Option<ResponseItem>;
     type Error = Infallible;
-    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
         Poll::Ready(Ok(()))
@@ -608,7 +931,9 @@ impl<S> MCPRequestService<S>
 where
     S: Service<SendableMessage, Response = Option<ResponseItem>, Error = Infallible>
         + Clone
+        + MaybeSend
         + 'static,
+    S::Future: MaybeSend,
 {
     pub fn new(inner: S) -> Self {
         Self { inner }
@@ -619,11 +944,13 @@ impl<S> Service<Request> for MCPRequestService<S>
 where
     S: Service<SendableMessage, Response = Option<ResponseItem>, Error = Infallible>
         + Clone
+        + MaybeSend
         + 'static,
+    S::Future: MaybeSend,
 {
     type Response = Response;
     type Error = Infallible;
-    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
 
     fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> Poll<Result<(), Self::Error>> {
         Poll::Ready(Ok(()))
@@ -669,11 +996,11 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::cell::RefCell;
+    use std::sync::atomic::{AtomicBool, Ordering};
 
     #[tokio::test]
     async fn test_notification_handler() {
-        let called = Rc::new(RefCell::new(false));
+        let called = Arc::new(AtomicBool::new(false));
         let called_clone = called.clone();
 
         let mut server = MCPServiceBuilder::new("Notification server".to_string())
@@ -681,7 +1008,7 @@ mod tests {
                 let called = called_clone.clone();
                 Box::pin(async move {
                     if notification.method == "my_notification" {
-                        *called.borrow_mut() = true;
+                        called.store(true, Ordering::SeqCst);
                     }
                 })
             })
@@ -693,7 +1020,7 @@ mod tests {
             .await;
 
         // Then
-        assert!(*called.borrow());
+        assert!(called.load(Ordering::SeqCst));
     }
 
     #[tokio::test]
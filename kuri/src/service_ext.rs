@@ -3,7 +3,7 @@ use std::convert::Infallible;
 use kuri_mcp_protocol::jsonrpc::{ResponseItem, SendableMessage};
 use tower::Service;
 
-use crate::MCPRequestService;
+use crate::{maybe_send::MaybeSend, MCPRequestService};
 
 /// Extension trait that adds additional methods to any [`Service`] that processes MCP messages.
 pub trait ServiceExt<R>: Service<R> + Sized {
@@ -17,7 +17,9 @@ where
     S: Service<SendableMessage, Response = Option<ResponseItem>, Error = Infallible>
         + Sized
         + Clone
+        + MaybeSend
         + 'static,
+    S::Future: MaybeSend,
 {
     fn into_request_service(self) -> MCPRequestService<Self> {
         MCPRequestService::new(self)
@@ -0,0 +1,113 @@
+//! Sessions: the state kept for a single connected client.
+//!
+//! [`serve`] runs each connection as a session, so most servers don't need anything here. It's
+//! only needed by transports which drive an `MCPService` through other means; see
+//! [`SessionHandle`].
+//!
+//! [`serve`]: crate::serve
+
+use crate::{context::AnyState, extract::ClientInfo, peer::Peer};
+use std::{
+    any::TypeId,
+    collections::HashMap,
+    future::Future,
+    sync::{Arc, RwLock},
+};
+
+tokio::task_local! {
+    /// The session currently being served. Set by [`serve`] for the lifetime of the connection.
+    ///
+    /// [`serve`]: crate::serve
+    static CURRENT_SESSION: Arc<SessionState>;
+}
+
+/// Locks are only held briefly to read or update a field, so poisoning indicates a bug in kuri.
+const POISONED: &str = "session lock poisoned";
+
+/// State scoped to a single session (ie a single connected client), as opposed to the global state
+/// held in the service's [`Context`].
+///
+/// [`Context`]: crate::context::Context
+pub(crate) struct SessionState {
+    peer: Peer,
+    /// Set when the client initialises the session.
+    client_info: RwLock<Option<ClientInfo>>,
+    /// State registered with [`MCPServiceBuilder::with_session_state`], created when the client
+    /// initialises the session. Each value is an `Arc<T>`, keyed by the TypeId of T.
+    ///
+    /// [`MCPServiceBuilder::with_session_state`]: crate::MCPServiceBuilder::with_session_state
+    state: RwLock<HashMap<TypeId, Box<AnyState>>>,
+}
+
+impl SessionState {
+    pub(crate) fn new(peer: Peer) -> Self {
+        Self {
+            peer,
+            client_info: RwLock::new(None),
+            state: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// The session currently being served, if any.
+    pub(crate) fn current() -> Option<Arc<SessionState>> {
+        CURRENT_SESSION.try_with(Arc::clone).ok()
+    }
+
+    pub(crate) fn peer(&self) -> &Peer {
+        &self.peer
+    }
+
+    pub(crate) fn client_info(&self) -> Option<ClientInfo> {
+        self.client_info.read().expect(POISONED).clone()
+    }
+
+    pub(crate) fn set_client_info(&self, client_info: ClientInfo) {
+        *self.client_info.write().expect(POISONED) = Some(client_info);
+    }
+
+    /// Get the session's state of type T, if it has been created.
+    pub(crate) fn state<T: 'static>(&self) -> Option<Arc<T>> {
+        self.state
+            .read()
+            .expect(POISONED)
+            .get(&TypeId::of::<T>())
+            .and_then(|boxed| boxed.downcast_ref::<Arc<T>>())
+            .cloned()
+    }
+
+    /// Replace the session's state, dropping any previous state.
+    pub(crate) fn set_state(&self, state: HashMap<TypeId, Box<AnyState>>) {
+        *self.state.write().expect(POISONED) = state;
+    }
+}
+
+/// A handle to a session, under which requests to an `MCPService` may be handled.
+///
+/// Requests handled under the same session share its client info and [session state]; those
+/// handled under different sessions don't. The session's state is dropped once every handle to it
+/// has been dropped (and the requests running under it have completed).
+///
+/// [`serve`] creates one session per connection, and [`Peer::scope`] runs a future in a new
+/// session. A transport where a session spans many separately handled requests (such as HTTP)
+/// should instead create a handle when the session starts, and run each of its requests with
+/// [`scope`](SessionHandle::scope).
+///
+/// [session state]: crate::extract::Session
+/// [`serve`]: crate::serve
+#[derive(Clone)]
+pub struct SessionHandle(Arc<SessionState>);
+
+impl SessionHandle {
+    /// Create a new session, in which `peer` may be used to message the client.
+    // Session state is only `Send + Sync` with the `send` feature, but the same `Arc` is used
+    // either way.
+    #[allow(clippy::arc_with_non_send_sync)]
+    pub fn new(peer: Peer) -> Self {
+        Self(Arc::new(SessionState::new(peer)))
+    }
+
+    /// Run `f` with this session set as the current session.
+    pub async fn scope<F: Future>(&self, f: F) -> F::Output {
+        CURRENT_SESSION.scope(Arc::clone(&self.0), f).await
+    }
+}
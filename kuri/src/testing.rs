@@ -0,0 +1,420 @@
+//! An in-memory test harness, for testing a service end-to-end.
+//!
+//! [`TestHarness`] serves an [`MCPService`] over an in-memory duplex stream, with [`serve`], and
+//! plays the part of the client. Tests can then run a whole session through the same code path as
+//! a real connection, including notifications sent by handlers, and requests sent by the server.
+//!
+//! ```rust
+//! use kuri::{peer::Peer, testing::TestHarness, tool, MCPServiceBuilder};
+//! use kuri_mcp_protocol::Content;
+//! use serde_json::json;
+//!
+//! #[tool]
+//! async fn greet(peer: Peer, name: String) -> String {
+//!     let log = json!({ "level": "info", "data": format!("Greeting {name}") });
+//!     let _ = peer.notify_with("notifications/message", Some(log));
+//!     format!("Hello, {name}!")
+//! }
+//!
+//! # #[tokio::main]
+//! # async fn main() {
+//! let service = MCPServiceBuilder::new("Greeter".to_string())
+//!     .with_tool(Greet)
+//!     .build();
+//! let mut harness = TestHarness::new(service);
+//! harness.initialize().await;
+//!
+//! let result = harness.call_tool("greet", json!({ "name": "Alice" })).await.unwrap();
+//! assert_eq!(result.content, vec![Content::text("Hello, Alice!")]);
+//! harness.expect_notification("notifications/message").await;
+//!
+//! harness.close().await.unwrap();
+//! # }
+//! ```
+//!
+//! The server only makes progress while the harness is awaited, so services needn't be `Send`.
+//! Helpers panic if the server doesn't respond as expected, or doesn't respond within a timeout
+//! (see [`TestHarness::with_timeout`]).
+//!
+//! [`serve`]: crate::serve
+
+use crate::{serve, transport::TransportError, MCPService, ServiceExt};
+use futures::future::LocalBoxFuture;
+use kuri_mcp_protocol::{
+    jsonrpc::{ErrorData, MethodCall, Notification, Request, RequestId, Response, ResponseItem},
+    messages::{
+        CallToolResult, GetPromptResult, InitializeResult, ListPromptsResult, ListResourcesResult,
+        ListToolsResult, ReadResourceResult, LATEST_PROTOCOL_VERSION,
+    },
+};
+use serde::de::DeserializeOwned;
+use serde_json::{json, Value};
+use std::{collections::VecDeque, convert::Infallible, time::Duration};
+use tokio::io::{
+    AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, Lines, ReadHalf, WriteHalf,
+};
+use tower::Service;
+
+/// Size of the in-memory stream's buffer, in each direction.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// A client connected to a service over an in-memory stream. See the [module docs](self).
+pub struct TestHarness {
+    /// The server's connection, until it ends.
+    server: Option<LocalBoxFuture<'static, Result<(), TransportError>>>,
+    server_result: Option<Result<(), TransportError>>,
+    lines: Lines<BufReader<ReadHalf<DuplexStream>>>,
+    write: WriteHalf<DuplexStream>,
+    next_id: u64,
+    timeout: Duration,
+    /// Messages from the server, other than responses, which haven't been consumed yet.
+    queued: VecDeque<Value>,
+}
+
+impl TestHarness {
+    /// Serve `service`, and connect to it. The connection isn't initialised.
+    pub fn new(service: MCPService) -> Self {
+        Self::from_service(service.into_request_service())
+    }
+
+    /// Serve a service accepting requests (such as an [`MCPService`] wrapped in middleware), and
+    /// connect to it.
+    pub fn from_service<S>(service: S) -> Self
+    where
+        S: Service<Request, Response = Response, Error = Infallible> + Clone + 'static,
+    {
+        let (client, transport) = tokio::io::duplex(BUFFER_SIZE);
+        Self::from_parts(Box::pin(serve(service, transport)), client)
+    }
+
+    fn from_parts(
+        server: LocalBoxFuture<'static, Result<(), TransportError>>,
+        client: DuplexStream,
+    ) -> Self {
+        let (read, write) = tokio::io::split(client);
+        Self {
+            server: Some(server),
+            server_result: None,
+            lines: BufReader::new(read).lines(),
+            write,
+            next_id: 1,
+            timeout: Duration::from_secs(5),
+            queued: VecDeque::new(),
+        }
+    }
+
+    /// How long to wait for a message from the server before panicking. Defaults to 5 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Initialise the connection, with a client that has no capabilities.
+    pub async fn initialize(&mut self) -> InitializeResult {
+        let params = json!({
+            "protocolVersion": LATEST_PROTOCOL_VERSION,
+            "capabilities": {},
+            "clientInfo": { "name": "TestHarness", "version": env!("CARGO_PKG_VERSION") }
+        });
+        self.initialize_with(params).await
+    }
+
+    /// Initialise the connection, with the given `initialize` params.
+    pub async fn initialize_with(&mut self, params: Value) -> InitializeResult {
+        let result = self
+            .request_typed("initialize", params)
+            .await
+            .unwrap_or_else(|e| panic!("Failed to initialise: {}", e.message));
+        self.notify("notifications/initialized", None).await;
+        result
+    }
+
+    pub async fn list_tools(&mut self) -> Result<ListToolsResult, ErrorData> {
+        self.request_typed("tools/list", json!({})).await
+    }
+
+    /// Call a tool. A tool that fails returns a result with `is_error` set, rather than an error.
+    pub async fn call_tool(
+        &mut self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, ErrorData> {
+        self.request_typed(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    pub async fn list_prompts(&mut self) -> Result<ListPromptsResult, ErrorData> {
+        self.request_typed("prompts/list", json!({})).await
+    }
+
+    pub async fn get_prompt(
+        &mut self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<GetPromptResult, ErrorData> {
+        self.request_typed(
+            "prompts/get",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    pub async fn list_resources(&mut self) -> Result<ListResourcesResult, ErrorData> {
+        self.request_typed("resources/list", json!({})).await
+    }
+
+    pub async fn read_resource(&mut self, uri: &str) -> Result<ReadResourceResult, ErrorData> {
+        self.request_typed("resources/read", json!({ "uri": uri }))
+            .await
+    }
+
+    /// Send a request, and wait for the response. Messages the server sends beforehand are queued,
+    /// to be consumed with [`expect_notification`] or [`expect_request`].
+    ///
+    /// [`expect_notification`]: TestHarness::expect_notification
+    /// [`expect_request`]: TestHarness::expect_request
+    pub async fn request(&mut self, method: &str, params: Value) -> ResponseItem {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await;
+
+        loop {
+            let message = self.receive().await;
+            let is_response = message.get("method").is_none();
+            if is_response && message["id"] == id {
+                return serde_json::from_value(message)
+                    .unwrap_or_else(|e| panic!("Invalid response to `{method}`: {e}"));
+            }
+            self.queued.push_back(message);
+        }
+    }
+
+    /// Send a notification.
+    pub async fn notify(&mut self, method: &str, params: Option<Value>) {
+        let mut notification = json!({ "jsonrpc": "2.0", "method": method });
+        if let Some(params) = params {
+            notification["params"] = params;
+        }
+        self.send(notification).await;
+    }
+
+    /// Wait for the next message from the server (other than a response), and check that it's a
+    /// notification with the given method.
+    pub async fn expect_notification(&mut self, method: &str) -> Notification {
+        let message = self.next_message().await;
+        match serde_json::from_value::<Notification>(message.clone()) {
+            Ok(notification) if message.get("id").is_none() && notification.method == method => {
+                notification
+            }
+            _ => panic!("Expected a `{method}` notification, got {message}"),
+        }
+    }
+
+    /// Wait for the next message from the server (other than a response), and check that it's a
+    /// request with the given method. Reply with [`respond`] or [`respond_error`].
+    ///
+    /// [`respond`]: TestHarness::respond
+    /// [`respond_error`]: TestHarness::respond_error
+    pub async fn expect_request(&mut self, method: &str) -> MethodCall {
+        let message = self.next_message().await;
+        match serde_json::from_value::<MethodCall>(message.clone()) {
+            Ok(request) if request.method == method => request,
+            _ => panic!("Expected a `{method}` request, got {message}"),
+        }
+    }
+
+    /// Reply to a request from the server.
+    pub async fn respond(&mut self, id: RequestId, result: Value) {
+        let response = ResponseItem::success(id, result);
+        self.send(serde_json::to_value(response).expect("response is valid JSON"))
+            .await;
+    }
+
+    /// Reply to a request from the server with an error.
+    pub async fn respond_error(&mut self, id: RequestId, error: ErrorData) {
+        let response = ResponseItem::error(id, error);
+        self.send(serde_json::to_value(response).expect("response is valid JSON"))
+            .await;
+    }
+
+    /// Send a raw line (which needn't be valid JSON), for testing how the server handles
+    /// malformed messages.
+    pub async fn send_line(&mut self, line: &str) {
+        self.write
+            .write_all(format!("{line}\n").as_bytes())
+            .await
+            .expect("in-memory stream is open");
+    }
+
+    /// Wait for the next message from the server, other than a response to one of the harness's
+    /// requests. This may be a notification, a request, or a response to a raw message sent with
+    /// [`send_line`](TestHarness::send_line).
+    pub async fn next_message(&mut self) -> Value {
+        match self.queued.pop_front() {
+            Some(message) => message,
+            None => self.receive().await,
+        }
+    }
+
+    /// Close the connection, and wait for the server to finish, returning the result of
+    /// [`serve`].
+    ///
+    /// Panics if the server sent any notifications or requests which weren't consumed, so that
+    /// tests don't miss unexpected messages.
+    pub async fn close(mut self) -> Result<(), TransportError> {
+        self.write
+            .shutdown()
+            .await
+            .expect("in-memory stream is open");
+
+        // Read the server's final messages as it finishes, so that it isn't blocked writing them
+        let server = self.server.take();
+        let read = async {
+            while let Ok(Some(line)) = self.lines.next_line().await {
+                self.queued.push_back(parse(&line));
+            }
+        };
+        let result = match server {
+            Some(server) => tokio::join!(server, read).0,
+            None => {
+                read.await;
+                self.server_result.take().expect("server has finished")
+            }
+        };
+
+        if let Some(message) = self.queued.pop_front() {
+            panic!("Unexpected message from server: {message}");
+        }
+        result
+    }
+
+    async fn request_typed<R: DeserializeOwned>(
+        &mut self,
+        method: &str,
+        params: Value,
+    ) -> Result<R, ErrorData> {
+        match self.request(method, params).await {
+            ResponseItem::Success { result, .. } => Ok(serde_json::from_value(result)
+                .unwrap_or_else(|e| panic!("Invalid result for `{method}`: {e}"))),
+            ResponseItem::Error { error, .. } => Err(error),
+        }
+    }
+
+    async fn send(&mut self, message: Value) {
+        self.send_line(&message.to_string()).await;
+    }
+
+    /// Read the next line from the server, driving the server meanwhile.
+    async fn receive(&mut self) -> Value {
+        let timeout = self.timeout;
+        let line = tokio::time::timeout(timeout, async {
+            loop {
+                let Some(server) = self.server.as_mut() else {
+                    break self.lines.next_line().await;
+                };
+                tokio::select! {
+                    line = self.lines.next_line() => break line,
+                    result = server => {
+                        self.server = None;
+                        self.server_result = Some(result);
+                    }
+                }
+            }
+        })
+        .await;
+
+        match line {
+            Ok(Ok(Some(line))) => parse(&line),
+            Ok(Ok(None)) => panic!("The server closed the connection"),
+            Ok(Err(e)) => panic!("Error reading from the server: {e}"),
+            Err(_) => panic!("Timed out after {timeout:?} waiting for a message from the server"),
+        }
+    }
+}
+
+fn parse(line: &str) -> Value {
+    serde_json::from_str(line).unwrap_or_else(|e| panic!("Server sent invalid JSON ({e}): {line}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use kuri_mcp_protocol::jsonrpc::ErrorCode;
+
+    /// A server which sends a request to the client, then reports the client's response.
+    async fn requesting_server(stream: DuplexStream) -> Result<(), TransportError> {
+        let (read, mut write) = tokio::io::split(stream);
+        let mut lines = BufReader::new(read).lines();
+        let request = json!({ "jsonrpc": "2.0", "id": "a", "method": "roots/list" });
+        write
+            .write_all(format!("{request}\n").as_bytes())
+            .await
+            .unwrap();
+
+        let response = lines.next_line().await.unwrap().unwrap();
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "notifications/message",
+            "params": { "level": "info", "data": parse(&response) }
+        });
+        write
+            .write_all(format!("{notification}\n").as_bytes())
+            .await
+            .unwrap();
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_server_requests() {
+        let (client, server) = tokio::io::duplex(BUFFER_SIZE);
+        let mut harness = TestHarness::from_parts(Box::pin(requesting_server(server)), client);
+
+        let request = harness.expect_request("roots/list").await;
+        assert_eq!(request.id, RequestId::Str("a".to_string()));
+        harness.respond(request.id, json!({ "roots": [] })).await;
+
+        let notification = harness.expect_notification("notifications/message").await;
+        assert_eq!(
+            serde_json::to_value(notification.params).unwrap()["data"],
+            json!({ "jsonrpc": "2.0", "id": "a", "result": { "roots": [] } })
+        );
+        harness.close().await.unwrap();
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "Expected a `roots/list` notification")]
+    async fn test_unexpected_message() {
+        let (client, server) = tokio::io::duplex(BUFFER_SIZE);
+        let mut harness = TestHarness::from_parts(Box::pin(requesting_server(server)), client);
+        harness.expect_notification("roots/list").await;
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "Timed out")]
+    async fn test_timeout() {
+        let (client, _server) = tokio::io::duplex(BUFFER_SIZE);
+        let mut harness = TestHarness::from_parts(Box::pin(std::future::pending()), client)
+            .with_timeout(Duration::from_millis(10));
+        harness.next_message().await;
+    }
+
+    #[tokio::test]
+    async fn test_respond_error() {
+        let (client, server) = tokio::io::duplex(BUFFER_SIZE);
+        let mut harness = TestHarness::from_parts(Box::pin(requesting_server(server)), client);
+
+        let request = harness.expect_request("roots/list").await;
+        let error = ErrorData::new(ErrorCode::MethodNotFound, "No roots".to_string());
+        harness.respond_error(request.id, error).await;
+        let notification = harness.expect_notification("notifications/message").await;
+        assert_eq!(
+            serde_json::to_value(notification.params).unwrap()["data"]["error"]["code"],
+            -32601
+        );
+        harness.close().await.unwrap();
+    }
+}
@@ -0,0 +1,169 @@
+use std::io;
+use std::pin::Pin;
+use std::process::{ExitStatus, Stdio};
+use std::task::{Context, Poll};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, BufReader, ReadBuf};
+use tokio::process::{ChildStdin, ChildStdout, Command};
+use tokio::sync::{oneshot, watch};
+
+/// A transport that spawns a command (usually, an MCP server), and talks to it over its stdin and
+/// stdout. The counterpart of [`StdioTransport`].
+///
+/// Lines the process writes to stderr are logged with `tracing`, at `info` level. The process is
+/// killed when the transport is dropped. Its exit status is available from its [`ChildProcess`]
+/// handle, which may outlive the transport.
+///
+/// ```rust,no_run
+/// use kuri::transport::ChildProcessTransport;
+/// use tokio::process::Command;
+///
+/// # async fn example() -> std::io::Result<()> {
+/// let mut command = Command::new("my-mcp-server");
+/// command.arg("--verbose");
+/// let transport = ChildProcessTransport::spawn(command)?;
+/// let process = transport.process();
+///
+/// // Connect a client over the transport, then once it's dropped...
+/// drop(transport);
+/// println!("Server exited with {:?}", process.wait().await);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`StdioTransport`]: super::StdioTransport
+#[derive(Debug)]
+pub struct ChildProcessTransport {
+    input: ChildStdout,
+    output: ChildStdin,
+    process: ChildProcess,
+    /// Kills the process when sent or dropped.
+    _kill: oneshot::Sender<()>,
+}
+
+impl ChildProcessTransport {
+    /// Spawn `command`, with its stdin, stdout and stderr piped. Must be called within a tokio
+    /// runtime, as the process is monitored by background tasks.
+    pub fn spawn(mut command: Command) -> io::Result<Self> {
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            // In case the runtime shuts down before the monitoring task kills the process
+            .kill_on_drop(true)
+            .spawn()?;
+        let id = child.id();
+
+        // The handles were piped above, so are always present
+        let output = child.stdin.take().expect("stdin is piped");
+        let input = child.stdout.take().expect("stdout is piped");
+        let stderr = child.stderr.take().expect("stderr is piped");
+
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stderr).lines();
+            loop {
+                match lines.next_line().await {
+                    Ok(Some(line)) => tracing::info!(pid = id, "{line}"),
+                    Ok(None) => break,
+                    Err(e) => {
+                        tracing::warn!(pid = id, error = ?e, "Error reading child process stderr");
+                        break;
+                    }
+                }
+            }
+        });
+
+        let (kill, killed) = oneshot::channel();
+        let (status_tx, status) = watch::channel(None);
+        tokio::spawn(async move {
+            let result = tokio::select! {
+                result = child.wait() => result,
+                // Sent, or the transport was dropped
+                _ = killed => {
+                    if let Err(e) = child.start_kill() {
+                        tracing::warn!(pid = id, error = ?e, "Error killing child process");
+                    }
+                    child.wait().await
+                }
+            };
+            match result {
+                Ok(exit_status) => {
+                    tracing::debug!(pid = id, %exit_status, "Child process exited");
+                    status_tx.send_replace(Some(exit_status));
+                }
+                // Dropping the sender wakes anyone waiting on the status
+                Err(e) => tracing::error!(pid = id, error = ?e, "Error waiting for child process"),
+            }
+        });
+
+        Ok(Self {
+            input,
+            output,
+            process: ChildProcess { id, status },
+            _kill: kill,
+        })
+    }
+
+    /// A handle to the child process.
+    pub fn process(&self) -> ChildProcess {
+        self.process.clone()
+    }
+}
+
+/// A handle to the process spawned by a [`ChildProcessTransport`], from which its exit status can
+/// be obtained.
+#[derive(Clone, Debug)]
+pub struct ChildProcess {
+    id: Option<u32>,
+    status: watch::Receiver<Option<ExitStatus>>,
+}
+
+impl ChildProcess {
+    /// The OS-assigned process ID, if the process was running when it was spawned.
+    pub fn id(&self) -> Option<u32> {
+        self.id
+    }
+
+    /// The process's exit status, if it has exited.
+    pub fn try_exit_status(&self) -> Option<ExitStatus> {
+        *self.status.borrow()
+    }
+
+    /// Wait for the process to exit, returning its exit status. Returns `None` if its status
+    /// couldn't be obtained.
+    pub async fn wait(&self) -> Option<ExitStatus> {
+        let mut status = self.status.clone();
+        let result = status.wait_for(Option::is_some).await;
+        result.ok().and_then(|status| *status)
+    }
+}
+
+impl AsyncRead for ChildProcessTransport {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.input).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for ChildProcessTransport {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.output).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.output).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.output).poll_shutdown(cx)
+    }
+}
@@ -22,5 +22,7 @@ pub enum TransportError {
     LinesCodecError(#[from] tokio_util::codec::LinesCodecError),
 }
 
+mod child_process;
 mod stdio;
+pub use child_process::{ChildProcess, ChildProcessTransport};
 pub use stdio::StdioTransport;
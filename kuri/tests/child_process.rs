@@ -0,0 +1,61 @@
+#![cfg(unix)]
+
+use kuri::transport::ChildProcessTransport;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::Command;
+
+// Child process transport tests, using shell commands in place of an MCP server
+
+#[tokio::test]
+async fn test_child_process_stdio() {
+    let mut transport = ChildProcessTransport::spawn(Command::new("cat")).unwrap();
+    assert!(transport.process().id().is_some());
+
+    transport
+        .write_all(b"{\"jsonrpc\":\"2.0\",\"method\":\"ping\"}\n")
+        .await
+        .unwrap();
+    let mut line = String::new();
+    BufReader::new(&mut transport)
+        .read_line(&mut line)
+        .await
+        .unwrap();
+    assert_eq!(line, "{\"jsonrpc\":\"2.0\",\"method\":\"ping\"}\n");
+}
+
+#[tokio::test]
+async fn test_child_process_exit_status() {
+    let mut command = Command::new("sh");
+    command.args(["-c", "echo 'Shutting down' >&2; exit 3"]);
+    let transport = ChildProcessTransport::spawn(command).unwrap();
+    let process = transport.process();
+
+    let status = process.wait().await.unwrap();
+    assert_eq!(status.code(), Some(3));
+    assert_eq!(process.try_exit_status(), Some(status));
+
+    // The status remains available once the transport has been dropped
+    drop(transport);
+    assert_eq!(process.wait().await, Some(status));
+}
+
+#[tokio::test]
+async fn test_child_process_killed_on_drop() {
+    let mut command = Command::new("sleep");
+    command.arg("30");
+    let transport = ChildProcessTransport::spawn(command).unwrap();
+    let process = transport.process();
+    assert_eq!(process.try_exit_status(), None);
+
+    drop(transport);
+    let status = process.wait().await.unwrap();
+    assert!(!status.success());
+    assert_eq!(status.code(), None);
+}
+
+#[tokio::test]
+async fn test_child_process_spawn_error() {
+    let command = Command::new("this-command-does-not-exist");
+    let err = ChildProcessTransport::spawn(command).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+}
@@ -0,0 +1,264 @@
+mod common;
+
+use common::call_server;
+use kuri::{
+    context::Inject, errors::BuildError, prompt, tool, MCPService, MCPServiceBuilder, ToolError,
+};
+use kuri_mcp_protocol::{
+    jsonrpc::ResponseItem,
+    messages::{CallToolResult, GetPromptResult, ListPromptsResult, ListToolsResult},
+    Content, TextContent,
+};
+use std::sync::atomic::{AtomicI32, Ordering};
+use tracing_subscriber::EnvFilter;
+
+// Composition tests: services assembled from several builders with `merge` and `nest`
+
+#[tokio::test]
+async fn test_nest_prefixes_names() {
+    let mut server = init_composed_server();
+
+    let response = call_server(&mut server, "tools/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    let result: ListToolsResult = serde_json::from_value(result).unwrap();
+    let mut names: Vec<_> = result.tools.into_iter().map(|tool| tool.name).collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            "counter_increment",
+            "github_search",
+            "gitlab_search",
+            "hello"
+        ]
+    );
+
+    let response = call_server(&mut server, "prompts/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    let result: ListPromptsResult = serde_json::from_value(result).unwrap();
+    let mut names: Vec<_> = result
+        .prompts
+        .into_iter()
+        .map(|prompt| prompt.name)
+        .collect();
+    names.sort();
+    assert_eq!(names, ["github_summarise", "gitlab_summarise"]);
+}
+
+#[tokio::test]
+async fn test_nested_handlers_are_called() {
+    let mut server = init_composed_server();
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "github_search", "arguments": { "query": "kuri" } }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(tool_text(response), "Searched for kuri");
+
+    let response = call_server(
+        &mut server,
+        "prompts/get",
+        serde_json::json!({ "name": "gitlab_summarise", "arguments": { "text": "kuri" } }),
+    )
+    .await
+    .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    let result: GetPromptResult = serde_json::from_value(result).unwrap();
+    assert_eq!(result.messages.len(), 1);
+
+    // The unprefixed name isn't served
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "search", "arguments": { "query": "kuri" } }),
+    )
+    .await
+    .unwrap();
+    assert!(matches!(response, ResponseItem::Error { .. }));
+}
+
+#[tokio::test]
+async fn test_nested_state_is_carried_over() {
+    let mut server = init_composed_server();
+
+    for expected in ["1", "2"] {
+        let response = call_server(
+            &mut server,
+            "tools/call",
+            serde_json::json!({ "name": "counter_increment", "arguments": {} }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tool_text(response), expected);
+    }
+}
+
+#[test]
+fn test_merge_collision() {
+    let result = MCPServiceBuilder::new("Root".to_string())
+        .with_tool(Hello)
+        .merge(MCPServiceBuilder::new("Other".to_string()).with_tool(Hello))
+        .try_build();
+    assert_duplicate(result, "Tool `hello` is registered more than once");
+}
+
+#[test]
+fn test_nest_collision() {
+    let result = MCPServiceBuilder::new("Root".to_string())
+        .nest("github_", search_service())
+        .nest("github_", search_service())
+        .try_build();
+    assert_duplicate(result, "Tool `github_search` is registered more than once");
+}
+
+#[test]
+fn test_with_tool_collision() {
+    let result = MCPServiceBuilder::new("Root".to_string())
+        .with_tool(Hello)
+        .with_tool(Hello)
+        .try_build();
+    assert_duplicate(result, "Tool `hello` is registered more than once");
+}
+
+#[test]
+fn test_renamed_tool_collision() {
+    // Different functions given the same name compile, and are caught when the service is built
+    let result = MCPServiceBuilder::new("Root".to_string())
+        .with_tool(Hello)
+        .with_tool(Hi)
+        .try_build();
+    assert_duplicate(result, "Tool `hello` is registered more than once");
+}
+
+#[test]
+#[should_panic(expected = "Tool `hello` is registered more than once")]
+fn test_build_panics_on_collision() {
+    MCPServiceBuilder::new("Root".to_string())
+        .with_tool(Hello)
+        .merge(MCPServiceBuilder::new("Other".to_string()).with_tool(Hi))
+        .build();
+}
+
+#[test]
+fn test_state_collision() {
+    let result = MCPServiceBuilder::new("Root".to_string())
+        .nest("first_", counter_service(0))
+        .nest("second_", counter_service(10))
+        .try_build();
+    match result {
+        Err(e @ BuildError::ConflictingState { .. }) => assert_eq!(
+            e.to_string(),
+            "State of type `compose::Counter` is registered by more than one of the combined builders"
+        ),
+        Err(e) => panic!("Expected a conflicting state error, got {e}"),
+        Ok(_) => panic!("Expected a conflicting state error"),
+    }
+}
+
+#[tokio::test]
+async fn test_build_keeps_first_state_on_collision() {
+    let mut server = MCPServiceBuilder::new("Root".to_string())
+        .nest("first_", counter_service(0))
+        .nest("second_", counter_service(10))
+        .build();
+
+    // Both tools are given the first counter
+    for (name, expected) in [("first_increment", "1"), ("second_increment", "2")] {
+        let response = call_server(
+            &mut server,
+            "tools/call",
+            serde_json::json!({ "name": name, "arguments": {} }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tool_text(response), expected);
+    }
+}
+
+fn assert_duplicate(result: Result<MCPService, BuildError>, expected: &str) {
+    match result {
+        Err(e @ BuildError::DuplicateName { .. }) => assert_eq!(e.to_string(), expected),
+        Err(e) => panic!("Expected a duplicate name error, got {e}"),
+        Ok(_) => panic!("Expected a duplicate name error"),
+    }
+}
+
+fn tool_text(response: ResponseItem) -> String {
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let result: CallToolResult = serde_json::from_value(result).unwrap();
+            match &result.content[..] {
+                [Content::Text(TextContent { text, .. })] => text.clone(),
+                other => panic!("Expected a single text content, got {other:?}"),
+            }
+        }
+        ResponseItem::Error { .. } => panic!("Expected success response"),
+    }
+}
+
+#[tool]
+async fn hello() -> String {
+    "Hello".to_string()
+}
+
+#[tool(name = "hello")]
+async fn hi() -> String {
+    "Hi".to_string()
+}
+
+#[tool]
+async fn search(query: String) -> String {
+    format!("Searched for {query}")
+}
+
+#[prompt]
+async fn summarise(text: String) -> String {
+    format!("Summarise {text}")
+}
+
+struct Counter(AtomicI32);
+
+fn counter_service(start: i32) -> MCPServiceBuilder {
+    MCPServiceBuilder::new("Counter".to_string())
+        .with_tool(Increment)
+        .with_state(Inject::new(Counter(AtomicI32::new(start))))
+}
+
+#[tool]
+async fn increment(counter: Inject<Counter>) -> Result<i32, ToolError> {
+    Ok(counter.0.fetch_add(1, Ordering::SeqCst) + 1)
+}
+
+fn search_service() -> MCPServiceBuilder {
+    MCPServiceBuilder::new("Search".to_string())
+        .with_tool(Search)
+        .with_prompt(Summarise)
+}
+
+fn init_composed_server() -> MCPService {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+
+    MCPServiceBuilder::new("Composed".to_string())
+        .with_tool(Hello)
+        .nest("github_", search_service())
+        .nest("gitlab_", search_service())
+        .nest("counter_", counter_service(0))
+        .try_build()
+        .unwrap()
+}
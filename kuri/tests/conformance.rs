@@ -0,0 +1,266 @@
+#[path = "../../kuri_mcp_protocol/tests/schema/mod.rs"]
+mod schema;
+
+use async_trait::async_trait;
+use kuri::{
+    context::RequestContext, extract::Peer, prompt, response::Json, testing::TestHarness, tool,
+    MCPService, MCPServiceBuilder, Registry, ResourceHandler, ToolError,
+};
+use kuri_mcp_protocol::{
+    resource::{ResourceContents, ResourceError},
+    Content,
+};
+use schema::Schema;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{collections::HashMap, path::Path};
+
+// Conformance tests: recorded client transcripts are replayed against a service, checking that it
+// responds as recorded, and that everything it sends is valid against the specification's schema.
+//
+// A transcript (in `tests/transcripts`) is a file of lines, each of which is either:
+// * `> {...}`: a message sent by the client
+// * `< {...}`: a message the server is expected to send in reply. After each client request, the
+//   server's messages are read until the response to that request.
+// * `# ...`: a comment, or blank
+//
+// To re-record the server's messages after an intended change in behaviour, run with
+// `KURI_UPDATE_TRANSCRIPTS=1`, and review the diff.
+
+#[tokio::test]
+async fn test_transcript_lifecycle() {
+    replay("lifecycle").await;
+}
+
+#[tokio::test]
+async fn test_transcript_tools() {
+    replay("tools").await;
+}
+
+#[tokio::test]
+async fn test_transcript_structured() {
+    replay("structured").await;
+}
+
+#[tokio::test]
+async fn test_transcript_prompts() {
+    replay("prompts").await;
+}
+
+#[tokio::test]
+async fn test_transcript_resources() {
+    replay("resources").await;
+}
+
+async fn replay(name: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/transcripts")
+        .join(format!("{name}.transcript"));
+    let recorded = std::fs::read_to_string(&path).unwrap();
+
+    let mut harness = TestHarness::new(server());
+    let mut schema = None;
+    let mut methods = HashMap::new();
+    let mut replayed = Vec::new();
+
+    for line in recorded.lines() {
+        let Some(message) = line.strip_prefix("> ") else {
+            // Server messages are replayed below, in response to the client's
+            if !line.starts_with("< ") {
+                replayed.push(line.to_string());
+            }
+            continue;
+        };
+        replayed.push(line.to_string());
+        let message: Value = serde_json::from_str(message).unwrap();
+        harness.send_line(&message.to_string()).await;
+
+        let Some(method) = message["method"].as_str() else {
+            continue;
+        };
+        if method == "initialize" {
+            let version = message["params"]["protocolVersion"].as_str().unwrap();
+            schema = Some(Schema::load(
+                schema::VERSIONS
+                    .into_iter()
+                    .find(|v| *v == version)
+                    .unwrap(),
+            ));
+        }
+        let schema = schema
+            .as_ref()
+            .expect("Transcripts begin with `initialize`");
+        schema.assert_valid_message(&message, None);
+
+        let Some(id) = message.get("id") else {
+            continue;
+        };
+        methods.insert(id.to_string(), method.to_string());
+        loop {
+            let reply = harness.next_message().await;
+            let request_method = reply.get("id").and_then(|id| methods.get(&id.to_string()));
+            schema.assert_valid_message(&reply, request_method.map(String::as_str));
+            replayed.push(format!("< {reply}"));
+
+            let is_response = reply.get("result").is_some() || reply.get("error").is_some();
+            if is_response && reply.get("id") == Some(id) {
+                break;
+            }
+        }
+    }
+    harness.close().await.unwrap();
+
+    if std::env::var_os("KURI_UPDATE_TRANSCRIPTS").is_some() {
+        std::fs::write(&path, replayed.join("\n") + "\n").unwrap();
+        return;
+    }
+
+    let recorded: Vec<_> = recorded.lines().collect();
+    assert_eq!(
+        recorded.len(),
+        replayed.len(),
+        "The server sent a different number of messages than {} records",
+        path.display()
+    );
+    for (n, (recorded, replayed)) in recorded.iter().zip(&replayed).enumerate() {
+        match (recorded.strip_prefix("< "), replayed.strip_prefix("< ")) {
+            (Some(recorded), Some(replayed)) => assert_eq!(
+                serde_json::from_str::<Value>(recorded).unwrap(),
+                serde_json::from_str::<Value>(replayed).unwrap(),
+                "Line {} of {}",
+                n + 1,
+                path.display()
+            ),
+            _ => assert_eq!(recorded, replayed, "Line {} of {}", n + 1, path.display()),
+        }
+    }
+}
+
+#[tool(description = "Add two numbers")]
+async fn add(x: i32, y: i32) -> i32 {
+    x + y
+}
+
+#[tool(description = "Divide two numbers")]
+async fn divide(x: i32, y: i32) -> Result<i32, ToolError> {
+    if y == 0 {
+        return Err(ToolError::ExecutionError("Division by zero".to_string()));
+    }
+    Ok(x / y)
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Weather {
+    city: String,
+    summary: String,
+}
+
+#[tool(description = "Get the weather forecast for a city", read_only)]
+async fn forecast(city: String) -> Json<Weather> {
+    Json(Weather {
+        city,
+        summary: "Sunny".to_string(),
+    })
+}
+
+#[tool(description = "Find the notes", read_only)]
+async fn find_notes(#[extract] registry: Registry) -> Vec<Content> {
+    registry
+        .resource_link("file:///notes.txt")
+        .into_iter()
+        .collect()
+}
+
+#[tool(description = "Count down to lift off, logging each number")]
+async fn count_down(peer: Peer, from: u32) -> String {
+    for n in (1..=from).rev() {
+        let _ = peer.notify_with(
+            "notifications/message",
+            Some(json!({ "level": "info", "logger": "count_down", "data": n })),
+        );
+    }
+    "Lift off!".to_string()
+}
+
+#[prompt(
+    description = "Review some code",
+    params(code = "The code to review", focus = "What to focus on")
+)]
+async fn review(code: String, focus: Option<String>) -> String {
+    match focus {
+        Some(focus) => format!("Review this code, focusing on {focus}:\n{code}"),
+        None => format!("Review this code:\n{code}"),
+    }
+}
+
+struct Notes;
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl ResourceHandler for Notes {
+    fn uri(&self) -> &str {
+        "file:///notes.txt"
+    }
+
+    fn name(&self) -> &str {
+        "notes.txt"
+    }
+
+    fn description(&self) -> Option<&str> {
+        Some("Things to remember")
+    }
+
+    fn mime_type(&self) -> Option<&str> {
+        Some("text/plain")
+    }
+
+    async fn read(
+        &self,
+        _context: &RequestContext,
+    ) -> Result<Vec<ResourceContents>, ResourceError> {
+        Ok(vec![ResourceContents::TextResourceContents {
+            uri: self.uri().to_string(),
+            mime_type: Some("text/plain".to_string()),
+            text: "Remember the milk".to_string(),
+        }])
+    }
+}
+
+struct Logo;
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl ResourceHandler for Logo {
+    fn uri(&self) -> &str {
+        "file:///logo.png"
+    }
+
+    fn name(&self) -> &str {
+        "logo.png"
+    }
+
+    async fn read(
+        &self,
+        _context: &RequestContext,
+    ) -> Result<Vec<ResourceContents>, ResourceError> {
+        Ok(vec![ResourceContents::BlobResourceContents {
+            uri: self.uri().to_string(),
+            mime_type: None,
+            blob: "iVBORw0KGgo=".to_string(),
+        }])
+    }
+}
+
+fn server() -> MCPService {
+    MCPServiceBuilder::new("Conformance".to_string())
+        .with_tool(Add)
+        .with_tool(Divide)
+        .with_tool(CountDown)
+        .with_tool(Forecast)
+        .with_tool(FindNotes)
+        .with_prompt(Review)
+        .with_resource(Notes)
+        .with_resource(Logo)
+        .build()
+}
@@ -1,14 +1,9 @@
-use kuri::{serve, tool, MCPService, MCPServiceBuilder, ServiceExt};
+use kuri::{prompt, testing::TestHarness, tool, MCPService, MCPServiceBuilder};
+use kuri_mcp_protocol::jsonrpc::{ErrorCode, ErrorData};
 use schemars::JsonSchema;
 use serde::Deserialize;
-use std::{
-    fmt::Display,
-    io,
-    pin::Pin,
-    sync::{Arc, Mutex},
-    task::{Context, Poll},
-};
-use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use serde_json::json;
+use std::fmt::Display;
 use tracing_subscriber::EnvFilter;
 
 macro_rules! assert_json_eq {
@@ -66,7 +61,7 @@ async fn test_logical_param_errors() {
     ).await;
     assert_json_eq!(
         &response,
-        r#"{"jsonrpc": "2.0", "error": {"code": -32602, "message": "Invalid parameters: Missing or incorrect tool arguments"}, "id": 1}"#
+        r#"{"jsonrpc": "2.0", "error": {"code": -32602, "message": "Invalid parameters: Missing or incorrect tool arguments: missing field `int`"}, "id": 1}"#
     );
 
     // Type is incorrect
@@ -75,7 +70,7 @@ async fn test_logical_param_errors() {
     ).await;
     assert_json_eq!(
         &response,
-        r#"{"jsonrpc": "2.0", "error": {"code": -32602, "message": "Invalid parameters: Missing or incorrect tool arguments"}, "id": 1}"#
+        r#"{"jsonrpc": "2.0", "error": {"code": -32602, "message": "Invalid parameters: Missing or incorrect tool arguments: missing field `int`"}, "id": 1}"#
     );
 
     // value doesn't match enum variant
@@ -84,7 +79,7 @@ async fn test_logical_param_errors() {
     ).await;
     assert_json_eq!(
         &response,
-        r#"{"jsonrpc": "2.0", "error": {"code": -32602, "message": "Invalid parameters: Missing or incorrect tool arguments"}, "id": 1}"#
+        r#"{"jsonrpc": "2.0", "error": {"code": -32602, "message": "Invalid parameters: Missing or incorrect tool arguments: unknown variant `unrecognised_name`, expected `Bob` or `Alice`"}, "id": 1}"#
     );
 }
 
@@ -142,91 +137,43 @@ async fn test_batch_invalid_json() {
     );
 }
 
-#[derive(Debug, Clone)]
-struct MockTransport {
-    read_buf: Vec<u8>,
-    write_buf: Arc<Mutex<Vec<u8>>>,
-    read_pos: usize,
-}
-
-impl MockTransport {
-    fn new() -> Self {
-        MockTransport {
-            read_buf: Vec::new(),
-            write_buf: Arc::new(Mutex::new(Vec::new())),
-            read_pos: 0,
-        }
-    }
-
-    fn set_read_buf(&mut self, data: &[u8]) {
-        self.read_buf = data.to_vec();
-        self.read_pos = 0;
-    }
-
-    fn get_write_buf(&self) -> Vec<u8> {
-        self.write_buf.lock().unwrap().clone()
-    }
-}
-
-impl AsyncRead for MockTransport {
-    fn poll_read(
-        mut self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
-        buf: &mut ReadBuf<'_>,
-    ) -> Poll<io::Result<()>> {
-        if self.read_pos >= self.read_buf.len() {
-            return Poll::Ready(Ok(()));
-        }
-
-        let len = std::cmp::min(buf.remaining(), self.read_buf.len() - self.read_pos);
-        if len > 0 {
-            buf.put_slice(&self.read_buf[self.read_pos..self.read_pos + len]);
-            self.read_pos += len;
-        }
-        Poll::Ready(Ok(()))
-    }
-}
-
-impl AsyncWrite for MockTransport {
-    fn poll_write(
-        self: Pin<&mut Self>,
-        _cx: &mut Context<'_>,
-        buf: &[u8],
-    ) -> Poll<io::Result<usize>> {
-        self.write_buf.lock().unwrap().extend_from_slice(buf);
-        Poll::Ready(Ok(buf.len()))
-    }
+#[tokio::test]
+async fn test_custom_error_data() {
+    // Tools and prompts can return errors with their own code and data
+    let response = request(
+        r#"{"jsonrpc": "2.0", "method": "tools/call", "params": {"name": "fetch_page", "arguments": {"url": "https://example.com"}}, "id": 1}"#,
+    ).await;
+    assert_json_eq!(
+        &response,
+        r#"{"jsonrpc": "2.0", "error": {"code": -32029, "message": "Rate limited", "data": {"url": "https://example.com", "retryAfter": 30}}, "id": 1}"#
+    );
 
-    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
-    }
+    let response = request(
+        r#"{"jsonrpc": "2.0", "method": "prompts/get", "params": {"name": "summarise_page", "arguments": {"url": "https://example.com/missing"}}, "id": 2}"#,
+    ).await;
+    assert_json_eq!(
+        &response,
+        r#"{"jsonrpc": "2.0", "error": {"code": -32004, "message": "Page not found", "data": {"url": "https://example.com/missing"}}, "id": 2}"#
+    );
 
-    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        Poll::Ready(Ok(()))
-    }
+    // Missing tools and prompts are identified in the error's data
+    let response = request(
+        r#"{"jsonrpc": "2.0", "method": "tools/call", "params": {"name": "fetch_site", "arguments": {}}, "id": 3}"#,
+    ).await;
+    assert_json_eq!(
+        &response,
+        r#"{"jsonrpc": "2.0", "error": {"code": -32602, "message": "Tool not found: fetch_site", "data": {"tool": "fetch_site"}}, "id": 3}"#
+    );
 }
 
-impl Unpin for MockTransport {}
-
 async fn request(input: &str) -> String {
-    let service = init_simple_server();
-    let mut transport = MockTransport::new();
-    transport.set_read_buf(format!("{}\n", input).as_bytes());
+    let mut harness = TestHarness::new(init_simple_server());
+    harness.send_line(input).await;
+    let response = harness.next_message().await;
 
-    let _ = serve(service.into_request_service(), transport.clone()).await;
-
-    let response = transport.get_write_buf();
-    let response_str = std::str::from_utf8(&response).unwrap();
-    let lines: Vec<_> = response_str.lines().collect();
-
-    assert_eq!(lines.len(), 1, "Expected exactly one line of response");
-
-    lines[0].to_string()
-
-    // TODO
-    // // Assert it's serialisable (or not)
-    // let response = serde_json::from_str::<JsonRpcResponse>(lines[0])
-    //     .expect("No valid JSON-RPC response found");
+    // Closing the harness checks that there was exactly one message in response
+    let _ = harness.close().await;
+    response.to_string()
 }
 
 #[tool]
@@ -254,6 +201,22 @@ async fn echo_name(name: Names) -> String {
     format!("Hello, {}!", name)
 }
 
+#[tool]
+async fn fetch_page(url: String) -> Result<String, ErrorData> {
+    Err(
+        ErrorData::new(ErrorCode::Custom(-32029), "Rate limited".to_string())
+            .with_data(json!({ "url": url, "retryAfter": 30 })),
+    )
+}
+
+#[prompt]
+async fn summarise_page(url: String) -> Result<String, ErrorData> {
+    Err(
+        ErrorData::new(ErrorCode::Custom(-32004), "Page not found".to_string())
+            .with_data(json!({ "url": url })),
+    )
+}
+
 pub fn init_simple_server() -> MCPService {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
@@ -263,5 +226,7 @@ pub fn init_simple_server() -> MCPService {
     MCPServiceBuilder::new("Simple server".to_string())
         .with_tool(HelloWorldTool)
         .with_tool(EchoName)
+        .with_tool(FetchPage)
+        .with_prompt(SummarisePage)
         .build()
 }
@@ -0,0 +1,262 @@
+mod common;
+
+use common::call_server;
+use kuri::{
+    context::RequestContext,
+    extract::{ClientInfo, ExtractError, FromRequestContext, Meta, Peer, RequestId},
+    serve, tool, MCPService, MCPServiceBuilder, ServiceExt,
+};
+use kuri_mcp_protocol::{
+    jsonrpc::{ErrorCode, ResponseItem},
+    messages::CallToolResult,
+    Content, TextContent,
+};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing_subscriber::EnvFilter;
+
+// Extractor tests: handler parameters obtained from the request, rather than from the arguments
+
+#[tokio::test]
+async fn test_request_id_and_meta() {
+    let mut server = init_extract_server();
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({
+            "name": "describe_request",
+            "arguments": { "greeting": "Hello" },
+            "_meta": { "tag": "abc" }
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(tool_text(response), "Hello 1 abc");
+
+    // Requests without metadata get an empty `Meta`
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({
+            "name": "describe_request",
+            "arguments": { "greeting": "Hello" }
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(tool_text(response), "Hello 1 none");
+}
+
+#[tokio::test]
+async fn test_custom_extractor() {
+    let mut server = init_extract_server();
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({
+            "name": "traced",
+            "arguments": {},
+            "_meta": { "traceId": "xyz" }
+        }),
+    )
+    .await
+    .unwrap();
+    assert_eq!(tool_text(response), "xyz");
+
+    // Failing to extract is an internal error
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "traced", "arguments": {} }),
+    )
+    .await
+    .unwrap();
+    match response {
+        ResponseItem::Error { error, .. } => {
+            assert_eq!(error.code, ErrorCode::InternalError);
+            assert_eq!(error.message, "Internal error: Missing trace ID");
+        }
+        ResponseItem::Success { .. } => panic!("Expected error response"),
+    }
+}
+
+#[tokio::test]
+async fn test_argument_named_like_extractor() {
+    let mut server = init_extract_server();
+
+    // A client argument whose type shares a name with one of kuri's extractors
+    for name in ["join_session", "join_session_by_path"] {
+        let response = call_server(
+            &mut server,
+            "tools/call",
+            serde_json::json!({
+                "name": name,
+                "arguments": { "session": { "room": "lobby" } }
+            }),
+        )
+        .await
+        .unwrap();
+        assert_eq!(tool_text(response), "Joined lobby");
+    }
+}
+
+#[tokio::test]
+async fn test_session_extractors_outside_session() {
+    // Without `serve`, there's no session (so no client info or peer)
+    let mut server = init_extract_server();
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "whoami", "arguments": {} }),
+    )
+    .await
+    .unwrap();
+    match response {
+        ResponseItem::Error { error, .. } => {
+            assert_eq!(error.code, ErrorCode::InternalError);
+            assert_eq!(
+                error.message,
+                "Internal error: Peer is only available when serving a session"
+            );
+        }
+        ResponseItem::Success { .. } => panic!("Expected error response"),
+    }
+}
+
+#[tokio::test]
+async fn test_session_extractors() {
+    let server = init_extract_server();
+
+    let (client, transport) = tokio::io::duplex(4096);
+    let (read, mut write) = tokio::io::split(client);
+    let mut lines = BufReader::new(read).lines();
+
+    let client = async move {
+        write
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\",\"params\":{\"protocolVersion\":\"2024-11-05\",\"capabilities\":{},\"clientInfo\":{\"name\":\"ExampleClient\",\"version\":\"1.0.0\"}}}\n")
+            .await
+            .unwrap();
+        let response: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(response["id"], 1);
+
+        write
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":2,\"method\":\"tools/call\",\"params\":{\"name\":\"whoami\",\"arguments\":{}}}\n")
+            .await
+            .unwrap();
+
+        // The tool notifies the client through its peer, before responding
+        let notification: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(
+            notification,
+            serde_json::json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/message",
+                "params": { "level": "info", "data": "whoami called" }
+            })
+        );
+
+        let response: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(response["id"], 2);
+        assert_eq!(
+            response["result"]["content"][0]["text"],
+            "ExampleClient 1.0.0 (2024-11-05)"
+        );
+
+        write.shutdown().await.unwrap();
+    };
+
+    let (result, _) = tokio::join!(serve(server.into_request_service(), transport), client);
+    result.unwrap();
+}
+
+fn tool_text(response: ResponseItem) -> String {
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let result: CallToolResult = serde_json::from_value(result).unwrap();
+            match &result.content[..] {
+                [Content::Text(TextContent { text, .. })] => text.clone(),
+                other => panic!("Expected a single text content, got {other:?}"),
+            }
+        }
+        ResponseItem::Error { .. } => panic!("Expected success response"),
+    }
+}
+
+// Extractors may appear anywhere in the parameter list
+#[tool]
+async fn describe_request(id: RequestId, greeting: String, meta: Meta) -> String {
+    let tag = meta
+        .get("tag")
+        .and_then(|tag| tag.as_str())
+        .unwrap_or("none");
+    format!("{greeting} {} {tag}", serde_json::json!(id))
+}
+
+#[tool]
+async fn whoami(peer: Peer, client: ClientInfo) -> String {
+    peer.notify_with(
+        "notifications/message",
+        Some(serde_json::json!({ "level": "info", "data": "whoami called" })),
+    )
+    .unwrap();
+    format!(
+        "{} {} ({})",
+        client.name, client.version, client.protocol_version
+    )
+}
+
+struct TraceId(String);
+
+impl FromRequestContext for TraceId {
+    fn from_request_context(ctx: &RequestContext) -> Result<Self, ExtractError> {
+        ctx.meta()
+            .and_then(|meta| meta.get("traceId"))
+            .and_then(|id| id.as_str())
+            .map(|id| TraceId(id.to_string()))
+            .ok_or_else(|| ExtractError::Other("Missing trace ID".to_string()))
+    }
+}
+
+#[tool]
+async fn traced(#[extract] trace_id: TraceId) -> String {
+    trace_id.0
+}
+
+mod chat {
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    pub struct Session {
+        pub room: String,
+    }
+}
+
+use chat::Session;
+
+#[tool]
+async fn join_session(#[arg] session: Session) -> String {
+    format!("Joined {}", session.room)
+}
+
+#[tool]
+async fn join_session_by_path(session: chat::Session) -> String {
+    format!("Joined {}", session.room)
+}
+
+fn init_extract_server() -> MCPService {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+
+    MCPServiceBuilder::new("Extract server".to_string())
+        .with_tool(DescribeRequest)
+        .with_tool(Whoami)
+        .with_tool(Traced)
+        .with_tool(JoinSession)
+        .with_tool(JoinSessionByPath)
+        .build()
+}
@@ -4,7 +4,10 @@ use common::call_server;
 use kuri::{tool, MCPService, MCPServiceBuilder};
 use kuri_mcp_protocol::{
     jsonrpc::{RequestId, ResponseItem},
-    messages::{Implementation, InitializeResult, ServerCapabilities, ToolsCapability},
+    messages::{
+        Implementation, InitializeResult, PromptsCapability, ResourcesCapability,
+        ServerCapabilities, ToolsCapability, LATEST_PROTOCOL_VERSION,
+    },
 };
 use tracing_subscriber::EnvFilter;
 
@@ -56,10 +59,15 @@ async fn test_initialize() {
             let expected = InitializeResult {
                 protocol_version: "2024-11-05".to_string(),
                 capabilities: ServerCapabilities {
-                    prompts: None,
-                    resources: None,
+                    prompts: Some(PromptsCapability {
+                        list_changed: Some(true),
+                    }),
+                    resources: Some(ResourcesCapability {
+                        subscribe: Some(false),
+                        list_changed: Some(true),
+                    }),
                     tools: Some(ToolsCapability {
-                        list_changed: Some(false),
+                        list_changed: Some(true),
                     }),
                 },
                 server_info: Implementation {
@@ -76,6 +84,35 @@ async fn test_initialize() {
     }
 }
 
+#[tokio::test]
+async fn test_initialize_negotiates_protocol_version() {
+    // A supported version is used as is; otherwise the server proposes its latest
+    for (requested, negotiated) in [
+        ("2024-11-05", "2024-11-05"),
+        ("2025-03-26", "2025-03-26"),
+        ("2025-06-18", "2025-06-18"),
+        ("2099-01-01", LATEST_PROTOCOL_VERSION),
+    ] {
+        let mut server = init_simple_server();
+        let response = call_server(
+            &mut server,
+            "initialize",
+            serde_json::json!({
+                "protocolVersion": requested,
+                "capabilities": {},
+                "clientInfo": { "name": "ExampleClient", "version": "1.0.0" }
+            }),
+        )
+        .await
+        .unwrap();
+
+        let ResponseItem::Success { result, .. } = response else {
+            panic!("Expected success response");
+        };
+        assert_eq!(result["protocolVersion"], negotiated);
+    }
+}
+
 // General server and JSON-RPC tests
 
 #[tokio::test]
@@ -1,10 +1,13 @@
 mod common;
 
+use std::str::FromStr;
+
 use common::call_server;
-use kuri::{prompt, MCPService, MCPServiceBuilder};
+use kuri::{prompt, MCPService, MCPServiceBuilder, PromptError};
 use kuri_mcp_protocol::{
     jsonrpc::{ErrorCode, RequestId, ResponseItem},
     messages::{GetPromptResult, ListPromptsResult},
+    prompt::{PromptMessage, PromptMessageRole},
 };
 use tracing_subscriber::EnvFilter;
 
@@ -75,6 +78,217 @@ async fn test_prompts_list() {
     }
 }
 
+#[tokio::test]
+async fn test_prompts_list_doc_comments() {
+    let mut server = MCPServiceBuilder::new("Documented".to_string())
+        .with_prompt(Translate)
+        .with_prompt(Brainstorm)
+        .build();
+
+    let response = call_server(&mut server, "prompts/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: ListPromptsResult = serde_json::from_value(result).unwrap();
+            let prompt = |name: &str| {
+                actual
+                    .prompts
+                    .iter()
+                    .find(|prompt| prompt.name == name)
+                    .unwrap()
+                    .clone()
+            };
+
+            let translate = prompt("translate");
+            assert_eq!(
+                translate.description.as_deref(),
+                Some("Translates text into another language")
+            );
+            assert_eq!(
+                translate.arguments,
+                Some(vec![
+                    kuri_mcp_protocol::prompt::PromptArgument {
+                        name: "text".to_string(),
+                        description: Some("The text to translate".to_string()),
+                        required: Some(true),
+                    },
+                    kuri_mcp_protocol::prompt::PromptArgument {
+                        name: "language".to_string(),
+                        description: Some("The language to translate into".to_string()),
+                        required: Some(true),
+                    },
+                ])
+            );
+
+            // Without a description or doc comment, there's no description
+            let brainstorm = prompt("brainstorm");
+            assert_eq!(brainstorm.description, None);
+            assert_eq!(brainstorm.arguments.unwrap()[0].description, None);
+        }
+        ResponseItem::Error { .. } => {
+            panic!("Expected success response");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_prompts_params_options() {
+    let mut server = MCPServiceBuilder::new("Options".to_string())
+        .with_prompt(Outline)
+        .build();
+
+    let response = call_server(&mut server, "prompts/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    let actual: ListPromptsResult = serde_json::from_value(result).unwrap();
+    assert_eq!(
+        actual.prompts[0].arguments,
+        Some(vec![
+            kuri_mcp_protocol::prompt::PromptArgument {
+                name: "topic".to_string(),
+                description: None,
+                required: Some(true),
+            },
+            kuri_mcp_protocol::prompt::PromptArgument {
+                name: "sectionCount".to_string(),
+                description: Some("How many sections to outline".to_string()),
+                required: Some(false),
+            },
+            kuri_mcp_protocol::prompt::PromptArgument {
+                name: "audience".to_string(),
+                description: None,
+                required: Some(false),
+            },
+        ])
+    );
+
+    for (arguments, expected) in [
+        (
+            serde_json::json!({ "topic": "Rust" }),
+            "Outline Rust in 3 sections for everyone",
+        ),
+        (
+            serde_json::json!({ "topic": "Rust", "sectionCount": 5, "audience": "experts" }),
+            "Outline Rust in 5 sections for experts",
+        ),
+    ] {
+        let response = call_server(
+            &mut server,
+            "prompts/get",
+            serde_json::json!({ "name": "outline", "arguments": arguments }),
+        )
+        .await
+        .unwrap();
+        let ResponseItem::Success { result, .. } = response else {
+            panic!("Expected success response");
+        };
+        let actual: GetPromptResult = serde_json::from_value(result).unwrap();
+        assert_eq!(
+            actual.messages,
+            vec![PromptMessage::new_text(PromptMessageRole::User, expected)]
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_prompts_get_parses_string_arguments() {
+    let mut server = MCPServiceBuilder::new("Stories".to_string())
+        .with_prompt(Story)
+        .build();
+
+    // Arguments are strings, parsed as the parameter's type. Numbers and bools may be unquoted.
+    for arguments in [
+        serde_json::json!({ "topic": "dragons", "paragraphs": "2", "rhyming": "true", "tone": "casual" }),
+        serde_json::json!({ "topic": "dragons", "paragraphs": 2, "rhyming": true, "tone": "casual" }),
+    ] {
+        let response = call_server(
+            &mut server,
+            "prompts/get",
+            serde_json::json!({ "name": "story", "arguments": arguments }),
+        )
+        .await
+        .unwrap();
+        let ResponseItem::Success { result, .. } = response else {
+            panic!("Expected success response");
+        };
+        let actual: GetPromptResult = serde_json::from_value(result).unwrap();
+        assert_eq!(
+            actual.messages,
+            vec![PromptMessage::new_text(
+                PromptMessageRole::User,
+                "Write 2 rhyming paragraphs about dragons, in a Casual tone"
+            )]
+        );
+    }
+
+    let response = call_server(
+        &mut server,
+        "prompts/get",
+        serde_json::json!({
+            "name": "story",
+            "arguments": { "topic": "dragons", "paragraphs": "two", "rhyming": "false" }
+        }),
+    )
+    .await
+    .unwrap();
+    let ResponseItem::Error { error, .. } = response else {
+        panic!("Expected error response");
+    };
+    assert_eq!(error.code, ErrorCode::InvalidParams);
+    assert_eq!(
+        error.message,
+        "Invalid parameters: Failed to parse parameter 'paragraphs': invalid digit found in string"
+    );
+}
+
+#[tokio::test]
+async fn test_prompts_get_without_arguments() {
+    let mut server = MCPServiceBuilder::new("Prompts".to_string())
+        .with_prompt(Inspire)
+        .with_prompt(Brainstorm)
+        .build();
+
+    // The arguments object may be omitted when every argument is optional
+    let response = call_server(
+        &mut server,
+        "prompts/get",
+        serde_json::json!({ "name": "inspire" }),
+    )
+    .await
+    .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    let actual: GetPromptResult = serde_json::from_value(result).unwrap();
+    assert_eq!(
+        actual.messages,
+        vec![PromptMessage::new_text(
+            PromptMessageRole::User,
+            "Suggest something to write about"
+        )]
+    );
+
+    // Otherwise, the missing argument is reported
+    let response = call_server(
+        &mut server,
+        "prompts/get",
+        serde_json::json!({ "name": "brainstorm" }),
+    )
+    .await
+    .unwrap();
+    let ResponseItem::Error { error, .. } = response else {
+        panic!("Expected error response");
+    };
+    assert_eq!(
+        error.message,
+        "Invalid parameters: Missing required parameter: topic"
+    );
+}
+
 #[tokio::test]
 async fn test_prompts_get_simple() {
     let mut server = init_prompt_server();
@@ -132,9 +346,10 @@ async fn test_prompts_get_invalid_prompt() {
         ResponseItem::Error { id, error, .. } => {
             assert_eq!(id, RequestId::Num(1));
             assert_eq!(error.code, ErrorCode::InvalidParams);
+            assert_eq!(error.message, "Prompt not found: some_invalid_prompt");
             assert_eq!(
-                error.message,
-                "Invalid parameters: Prompt not found: some_invalid_prompt"
+                error.data,
+                Some(serde_json::json!({ "prompt": "some_invalid_prompt" }))
             );
         }
         _ => {
@@ -217,6 +432,106 @@ async fn test_prompts_get_optional_params() {
     }
 }
 
+#[tokio::test]
+async fn test_prompts_get_multiple_messages() {
+    let mut server = init_conversation_prompt_server();
+
+    let response = call_server(
+        &mut server,
+        "prompts/get",
+        serde_json::json!({
+            "name": "debug_error",
+            "arguments": {
+                "error": "NullPointerException"
+            }
+        }),
+    )
+    .await
+    .unwrap();
+
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: GetPromptResult = serde_json::from_value(result).unwrap();
+            let expected = GetPromptResult {
+                description: None,
+                messages: vec![
+                    PromptMessage::new_text(
+                        PromptMessageRole::User,
+                        "I'm seeing this error: NullPointerException",
+                    ),
+                    PromptMessage::new_text(
+                        PromptMessageRole::Assistant,
+                        "I'll help debug that. What have you tried so far?",
+                    ),
+                ],
+            };
+            assert_eq!(actual, expected);
+        }
+        ResponseItem::Error { .. } => {
+            panic!("Expected success response");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_prompts_get_result_with_description() {
+    let mut server = init_conversation_prompt_server();
+
+    let response = call_server(
+        &mut server,
+        "prompts/get",
+        serde_json::json!({
+            "name": "greet",
+            "arguments": {
+                "name": "Ferris"
+            }
+        }),
+    )
+    .await
+    .unwrap();
+
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: GetPromptResult = serde_json::from_value(result).unwrap();
+            let expected = GetPromptResult {
+                description: Some("A greeting for Ferris".to_string()),
+                messages: vec![PromptMessage::new_text(
+                    PromptMessageRole::User,
+                    "Say hello to Ferris",
+                )],
+            };
+            assert_eq!(actual, expected);
+        }
+        ResponseItem::Error { .. } => {
+            panic!("Expected success response");
+        }
+    }
+
+    // Errors returned by the handler are propagated to the client
+    let response = call_server(
+        &mut server,
+        "prompts/get",
+        serde_json::json!({
+            "name": "greet",
+            "arguments": {
+                "name": ""
+            }
+        }),
+    )
+    .await
+    .unwrap();
+
+    match response {
+        ResponseItem::Error { error, .. } => {
+            assert_eq!(error.code, ErrorCode::InvalidParams);
+            assert_eq!(error.message, "Invalid parameters: Name must not be empty");
+        }
+        _ => {
+            panic!("Expected error response");
+        }
+    }
+}
+
 #[prompt(
     description = "Generates a code review prompt for the provided code",
     params(code = "The code to review")
@@ -244,6 +559,113 @@ async fn summarise_text(text: String, format: Option<String>) -> String {
     )
 }
 
+#[prompt(
+    description = "Starts a debugging conversation",
+    params(error = "The error message to debug")
+)]
+async fn debug_error(error: String) -> Vec<PromptMessage> {
+    vec![
+        PromptMessage::new_text(
+            PromptMessageRole::User,
+            format!("I'm seeing this error: {}", error),
+        ),
+        PromptMessage::new_text(
+            PromptMessageRole::Assistant,
+            "I'll help debug that. What have you tried so far?",
+        ),
+    ]
+}
+
+#[prompt(description = "Greets someone", params(name = "Who to greet"))]
+async fn greet(name: String) -> Result<GetPromptResult, PromptError> {
+    if name.is_empty() {
+        return Err(PromptError::InvalidParameters(
+            "Name must not be empty".to_string(),
+        ));
+    }
+
+    Ok(GetPromptResult {
+        description: Some(format!("A greeting for {}", name)),
+        messages: vec![PromptMessage::new_text(
+            PromptMessageRole::User,
+            format!("Say hello to {}", name),
+        )],
+    })
+}
+
+/// Translates text into another language
+#[prompt(params(language = "The language to translate into"))]
+async fn translate(
+    /// The text to translate
+    text: String,
+    /// The language
+    language: String,
+) -> String {
+    format!("Translate this into {language}:\n\n{text}")
+}
+
+#[prompt(params(
+    section_count(
+        description = "How many sections to outline",
+        rename = "sectionCount",
+        default = 3
+    ),
+    audience(default = "everyone")
+))]
+async fn outline(topic: String, section_count: u32, audience: String) -> String {
+    format!("Outline {topic} in {section_count} sections for {audience}")
+}
+
+#[prompt]
+async fn brainstorm(topic: String) -> String {
+    format!("Brainstorm some ideas about {topic}")
+}
+
+#[prompt]
+async fn inspire(theme: Option<String>) -> String {
+    match theme {
+        Some(theme) => format!("Suggest something to write about {theme}"),
+        None => "Suggest something to write about".to_string(),
+    }
+}
+
+#[derive(Debug)]
+enum Tone {
+    Formal,
+    Casual,
+}
+
+impl FromStr for Tone {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "formal" => Ok(Tone::Formal),
+            "casual" => Ok(Tone::Casual),
+            _ => Err(format!("unknown tone `{s}`")),
+        }
+    }
+}
+
+#[prompt]
+async fn story(topic: String, paragraphs: u32, rhyming: bool, tone: Option<Tone>) -> String {
+    let rhyming = if rhyming { "rhyming " } else { "" };
+    let tone = tone.unwrap_or(Tone::Formal);
+    format!("Write {paragraphs} {rhyming}paragraphs about {topic}, in a {tone:?} tone")
+}
+
+pub fn init_conversation_prompt_server() -> MCPService {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+
+    MCPServiceBuilder::new("Conversation Prompt Server".to_string())
+        .with_prompt(DebugError)
+        .with_prompt(Greet)
+        .build()
+}
+
 pub fn init_prompt_server() -> MCPService {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
@@ -0,0 +1,275 @@
+mod common;
+
+use async_trait::async_trait;
+use common::call_server;
+use kuri::{
+    context::RequestContext, serve, tool, MCPService, MCPServiceBuilder, Registry, ResourceHandler,
+    ServiceExt,
+};
+use kuri_mcp_protocol::{
+    jsonrpc::{RequestId, ResponseItem},
+    messages::{CallToolResult, ListResourcesResult, ListToolsResult, ReadResourceResult},
+    resource::{Resource, ResourceContents, ResourceError},
+    Content,
+};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tracing_subscriber::EnvFilter;
+
+// Registry tests: changing tools, prompts and resources at runtime
+// Spec: https://spec.modelcontextprotocol.io/specification/2025-03-26/server/tools/#list-changed-notification
+
+#[tokio::test]
+async fn test_add_and_remove_tool_at_runtime() {
+    let mut server = init_registry_server();
+    let registry = server.registry();
+
+    assert_eq!(list_tool_names(&mut server).await, vec!["hello_world_tool"]);
+
+    registry.add_tool(GoodbyeTool);
+    let mut names = list_tool_names(&mut server).await;
+    names.sort();
+    assert_eq!(names, vec!["goodbye_tool", "hello_world_tool"]);
+
+    assert!(registry.remove_tool("hello_world_tool"));
+    assert!(!registry.remove_tool("hello_world_tool"));
+    assert_eq!(list_tool_names(&mut server).await, vec!["goodbye_tool"]);
+
+    // `try_add_tool` doesn't replace an existing tool
+    assert!(!registry.try_add_tool(GoodbyeTool));
+    assert!(registry.try_add_tool(HelloWorldTool));
+    assert!(!registry.try_add_tool(HelloWorldTool));
+    assert!(registry.remove_tool("hello_world_tool"));
+
+    // Removed tools can no longer be called
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "hello_world_tool", "arguments": {} }),
+    )
+    .await
+    .unwrap();
+    assert!(matches!(response, ResponseItem::Error { .. }));
+}
+
+#[tokio::test]
+async fn test_list_changed_notifications() {
+    let server = init_registry_server();
+    let registry = server.registry();
+
+    let (client, transport) = tokio::io::duplex(4096);
+    let (read, mut write) = tokio::io::split(client);
+    let mut lines = BufReader::new(read).lines();
+
+    let client = async move {
+        write
+            .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"initialize\",\"params\":{\"protocolVersion\":\"2024-11-05\",\"capabilities\":{},\"clientInfo\":{\"name\":\"ExampleClient\",\"version\":\"1.0.0\"}}}\n")
+            .await
+            .unwrap();
+        let response: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(response["id"], 1);
+
+        registry.add_tool(GoodbyeTool);
+        let notification: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(
+            notification,
+            serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" })
+        );
+
+        registry.remove_prompt("not_a_prompt");
+        registry.add_resource(StaticResource);
+        let notification: serde_json::Value =
+            serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+        assert_eq!(
+            notification,
+            serde_json::json!({ "jsonrpc": "2.0", "method": "notifications/resources/list_changed" })
+        );
+
+        // A batch of changes is notified once for each kind of handler changed
+        registry.update(|update| {
+            update.add_tool(HelloWorldTool);
+            update.remove_tool("goodbye_tool");
+            update.remove_prompt("not_a_prompt");
+        });
+        registry.remove_resource("file:///greeting.txt");
+        let mut notifications = Vec::new();
+        for _ in 0..2 {
+            let notification: serde_json::Value =
+                serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+            notifications.push(notification["method"].as_str().unwrap().to_string());
+        }
+        assert_eq!(
+            notifications,
+            [
+                "notifications/tools/list_changed",
+                "notifications/resources/list_changed"
+            ]
+        );
+
+        // Close the connection
+        write.shutdown().await.unwrap();
+    };
+
+    let (result, _) = tokio::join!(serve(server.into_request_service(), transport), client);
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn test_resources() {
+    let mut server = init_registry_server();
+    server.registry().add_resource(StaticResource);
+
+    let response = call_server(&mut server, "resources/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: ListResourcesResult = serde_json::from_value(result).unwrap();
+            assert_eq!(actual.resources.len(), 1);
+            assert_eq!(actual.resources[0].uri, "file:///greeting.txt");
+            assert_eq!(actual.resources[0].name, "greeting.txt");
+            assert_eq!(
+                actual.resources[0].mime_type,
+                Some("text/plain".to_string())
+            );
+        }
+        ResponseItem::Error { .. } => panic!("Expected success response"),
+    }
+
+    let response = call_server(
+        &mut server,
+        "resources/read",
+        serde_json::json!({ "uri": "file:///greeting.txt" }),
+    )
+    .await
+    .unwrap();
+    match response {
+        ResponseItem::Success { id, result, .. } => {
+            assert_eq!(id, RequestId::Num(1));
+            let actual: ReadResourceResult = serde_json::from_value(result).unwrap();
+            assert_eq!(
+                actual.contents,
+                vec![ResourceContents::TextResourceContents {
+                    uri: "file:///greeting.txt".to_string(),
+                    mime_type: Some("text/plain".to_string()),
+                    text: "Hello!".to_string(),
+                }]
+            );
+        }
+        ResponseItem::Error { .. } => panic!("Expected success response"),
+    }
+
+    let response = call_server(
+        &mut server,
+        "resources/read",
+        serde_json::json!({ "uri": "file:///missing.txt" }),
+    )
+    .await
+    .unwrap();
+    assert!(matches!(response, ResponseItem::Error { .. }));
+}
+
+#[tokio::test]
+async fn test_tool_links_to_resources() {
+    let mut server = init_registry_server();
+    let registry = server.registry();
+    registry.add_tool(FindGreetings);
+    registry.add_resource(StaticResource);
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "find_greetings", "arguments": {} }),
+    )
+    .await
+    .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    // Only registered resources are linked to, described as they're listed
+    let actual: CallToolResult = serde_json::from_value(result).unwrap();
+    assert_eq!(
+        actual.content,
+        vec![Content::resource_link(Resource {
+            uri: "file:///greeting.txt".to_string(),
+            name: "greeting.txt".to_string(),
+            description: None,
+            mime_type: Some("text/plain".to_string()),
+            annotations: None,
+            size: None,
+        })]
+    );
+}
+
+async fn list_tool_names(server: &mut MCPService) -> Vec<String> {
+    let response = call_server(server, "tools/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: ListToolsResult = serde_json::from_value(result).unwrap();
+            actual.tools.into_iter().map(|tool| tool.name).collect()
+        }
+        ResponseItem::Error { .. } => panic!("Expected success response"),
+    }
+}
+
+#[tool]
+async fn hello_world_tool() -> String {
+    "Hello World".to_string()
+}
+
+#[tool]
+async fn goodbye_tool() -> String {
+    "Goodbye".to_string()
+}
+
+/// Find the files containing greetings
+#[tool]
+async fn find_greetings(#[extract] registry: Registry) -> Vec<Content> {
+    ["file:///greeting.txt", "file:///farewell.txt"]
+        .iter()
+        .filter_map(|uri| registry.resource_link(uri))
+        .collect()
+}
+
+struct StaticResource;
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl ResourceHandler for StaticResource {
+    fn uri(&self) -> &str {
+        "file:///greeting.txt"
+    }
+
+    fn name(&self) -> &str {
+        "greeting.txt"
+    }
+
+    fn mime_type(&self) -> Option<&str> {
+        Some("text/plain")
+    }
+
+    async fn read(
+        &self,
+        _context: &RequestContext,
+    ) -> Result<Vec<ResourceContents>, ResourceError> {
+        Ok(vec![ResourceContents::TextResourceContents {
+            uri: self.uri().to_string(),
+            mime_type: Some("text/plain".to_string()),
+            text: "Hello!".to_string(),
+        }])
+    }
+}
+
+pub fn init_registry_server() -> MCPService {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+
+    MCPServiceBuilder::new("Registry server".to_string())
+        .with_tool(HelloWorldTool)
+        .build()
+}
@@ -0,0 +1,56 @@
+//! Tests for the `send` feature, which allows services to be used across threads.
+#![cfg(feature = "send")]
+
+use kuri::{serve, tool, MCPService, MCPServiceBuilder, Registry, ServiceExt};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+
+fn assert_send_sync<T: Send + Sync>() {}
+
+#[test]
+fn test_service_is_send_sync() {
+    assert_send_sync::<MCPService>();
+    assert_send_sync::<Registry>();
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_spawn_connection_on_multi_thread_runtime() {
+    let service = MCPServiceBuilder::new("Send server".to_string()).build();
+    let registry = service.registry();
+
+    // Tools may be registered from another thread
+    tokio::spawn(async move { registry.add_tool(HelloWorldTool) })
+        .await
+        .unwrap();
+
+    // Serve the connection on its own task
+    let (client, transport) = tokio::io::duplex(4096);
+    let server = tokio::spawn(serve(service.into_request_service(), transport));
+
+    let (read, mut write) = tokio::io::split(client);
+    let mut lines = BufReader::new(read).lines();
+    write
+        .write_all(b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"tools/call\",\"params\":{\"name\":\"hello_world_tool\"}}\n")
+        .await
+        .unwrap();
+    let response: serde_json::Value =
+        serde_json::from_str(&lines.next_line().await.unwrap().unwrap()).unwrap();
+    assert_eq!(
+        response,
+        serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": { "content": [{ "type": "text", "text": "Hello World" }] }
+        })
+    );
+
+    write.shutdown().await.unwrap();
+    server.await.unwrap().unwrap();
+}
+
+#[tool]
+async fn hello_world_tool() -> String {
+    // Hold something across an await point, which must be `Send`
+    let greeting = String::from("Hello World");
+    tokio::task::yield_now().await;
+    greeting
+}
@@ -0,0 +1,250 @@
+mod common;
+
+use common::call_server;
+use kuri::{extract::Session, serve, tool, MCPService, MCPServiceBuilder, ServiceExt};
+use kuri_mcp_protocol::jsonrpc::{ErrorCode, ResponseItem};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc, Mutex,
+};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tracing_subscriber::EnvFilter;
+
+// Session state tests: state created per session, injected with `Session<T>`
+
+#[tokio::test]
+async fn test_session_state_is_per_connection() {
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let server = init_session_server(dropped.clone());
+
+    let (client_a, transport_a) = tokio::io::duplex(4096);
+    let (client_b, transport_b) = tokio::io::duplex(4096);
+
+    let clients = async {
+        let mut a = Client::new(client_a);
+        let mut b = Client::new(client_b);
+        a.initialize("Alice").await;
+        b.initialize("Bob").await;
+
+        assert_eq!(a.add_to_cart("apple").await, "Alice has 1 items: apple");
+        assert_eq!(b.add_to_cart("pear").await, "Bob has 1 items: pear");
+        assert_eq!(
+            a.add_to_cart("banana").await,
+            "Alice has 2 items: apple, banana"
+        );
+
+        // Nothing is dropped while the sessions are open
+        assert_eq!(dropped.load(Ordering::SeqCst), 0);
+
+        a.close().await;
+        b.close().await;
+    };
+
+    let (result_a, result_b, _) = tokio::join!(
+        serve(server.clone().into_request_service(), transport_a),
+        serve(server.clone().into_request_service(), transport_b),
+        clients
+    );
+    result_a.unwrap();
+    result_b.unwrap();
+
+    // Each session's state is dropped when its connection closes
+    assert_eq!(dropped.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_session_state_replaced_on_reinitialise() {
+    let dropped = Arc::new(AtomicUsize::new(0));
+    let server = init_session_server(dropped.clone());
+
+    let (client, transport) = tokio::io::duplex(4096);
+    let client = async {
+        let mut client = Client::new(client);
+        client.initialize("Alice").await;
+        assert_eq!(
+            client.add_to_cart("apple").await,
+            "Alice has 1 items: apple"
+        );
+
+        client.initialize("Carol").await;
+        assert_eq!(dropped.load(Ordering::SeqCst), 1);
+        assert_eq!(client.add_to_cart("kiwi").await, "Carol has 1 items: kiwi");
+
+        client.close().await;
+    };
+
+    let (result, _) = tokio::join!(serve(server.into_request_service(), transport), client);
+    result.unwrap();
+    assert_eq!(dropped.load(Ordering::SeqCst), 2);
+}
+
+#[tokio::test]
+async fn test_session_state_before_initialise() {
+    let server = init_session_server(Arc::default());
+
+    let (client, transport) = tokio::io::duplex(4096);
+    let client = async {
+        let mut client = Client::new(client);
+        let response = client
+            .request(
+                r#"{"jsonrpc":"2.0","id":1,"method":"tools/call","params":{"name":"add_to_cart","arguments":{"item":"apple"}}}"#,
+            )
+            .await;
+        assert_eq!(response["error"]["code"], -32603);
+        assert_eq!(
+            response["error"]["message"],
+            "Internal error: The client has not initialised the session"
+        );
+        client.close().await;
+    };
+
+    let (result, _) = tokio::join!(serve(server.into_request_service(), transport), client);
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn test_session_state_outside_session() {
+    // Without `serve`, there's no session to hold the state
+    let mut server = init_session_server(Arc::default());
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "add_to_cart", "arguments": { "item": "apple" } }),
+    )
+    .await
+    .unwrap();
+    match response {
+        ResponseItem::Error { error, .. } => {
+            assert_eq!(error.code, ErrorCode::InternalError);
+            assert_eq!(
+                error.message,
+                "Internal error: Session is only available when serving a session"
+            );
+        }
+        ResponseItem::Success { .. } => panic!("Expected error response"),
+    }
+}
+
+#[test]
+fn test_session_state_not_registered() {
+    let result = MCPServiceBuilder::new("Session server".to_string())
+        .with_tool(AddToCart)
+        .try_build();
+    match result {
+        Err(e) => assert_eq!(
+            e.to_string(),
+            "Tool `add_to_cart` can't be served: Tried to inject session state session::Cart, which is not registered with the MCPService"
+        ),
+        Ok(_) => panic!("Expected unregistered session state to be reported"),
+    }
+}
+
+struct Cart {
+    client: String,
+    items: Mutex<Vec<String>>,
+    dropped: Arc<AtomicUsize>,
+}
+
+impl Drop for Cart {
+    fn drop(&mut self) {
+        self.dropped.fetch_add(1, Ordering::SeqCst);
+    }
+}
+
+#[tool]
+async fn add_to_cart(cart: Session<Cart>, item: String) -> String {
+    let mut items = cart.items.lock().unwrap();
+    items.push(item);
+    format!(
+        "{} has {} items: {}",
+        cart.client,
+        items.len(),
+        items.join(", ")
+    )
+}
+
+fn init_session_server(dropped: Arc<AtomicUsize>) -> MCPService {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+
+    MCPServiceBuilder::new("Session server".to_string())
+        .with_session_state(move |ctx| Cart {
+            client: ctx.client_info().map(|info| info.name).unwrap_or_default(),
+            items: Mutex::default(),
+            dropped: dropped.clone(),
+        })
+        .with_tool(AddToCart)
+        .try_build()
+        .unwrap()
+}
+
+/// A minimal client, sending one request at a time over a duplex stream.
+struct Client {
+    lines: tokio::io::Lines<BufReader<tokio::io::ReadHalf<DuplexStream>>>,
+    write: tokio::io::WriteHalf<DuplexStream>,
+    next_id: u64,
+}
+
+impl Client {
+    fn new(stream: DuplexStream) -> Self {
+        let (read, write) = tokio::io::split(stream);
+        Self {
+            lines: BufReader::new(read).lines(),
+            write,
+            next_id: 1,
+        }
+    }
+
+    async fn request(&mut self, request: &str) -> serde_json::Value {
+        self.write.write_all(request.as_bytes()).await.unwrap();
+        self.write.write_all(b"\n").await.unwrap();
+        let line = self.lines.next_line().await.unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    async fn call(&mut self, method: &str, params: serde_json::Value) -> serde_json::Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = serde_json::json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "method": method,
+            "params": params
+        });
+        let response = self.request(&request.to_string()).await;
+        assert_eq!(response["id"], id);
+        response
+    }
+
+    async fn initialize(&mut self, name: &str) {
+        self.call(
+            "initialize",
+            serde_json::json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": { "name": name, "version": "1.0.0" }
+            }),
+        )
+        .await;
+    }
+
+    async fn add_to_cart(&mut self, item: &str) -> String {
+        let response = self
+            .call(
+                "tools/call",
+                serde_json::json!({ "name": "add_to_cart", "arguments": { "item": item } }),
+            )
+            .await;
+        response["result"]["content"][0]["text"]
+            .as_str()
+            .unwrap()
+            .to_string()
+    }
+
+    async fn close(mut self) {
+        self.write.shutdown().await.unwrap();
+    }
+}
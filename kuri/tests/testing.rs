@@ -0,0 +1,183 @@
+use kuri::{
+    extract::Peer, middleware::tracing::TracingLayer, prompt, testing::TestHarness, tool,
+    MCPService, MCPServiceBuilder, ServiceExt, ToolError,
+};
+use kuri_mcp_protocol::{
+    jsonrpc::{ErrorCode, ErrorData},
+    Content,
+};
+use serde_json::json;
+use tower::ServiceBuilder;
+use tracing_subscriber::EnvFilter;
+
+// Test harness tests: full sessions against a service, over an in-memory stream
+
+#[tokio::test]
+async fn test_harness_session() {
+    let service = init_harness_server();
+    let registry = service.registry();
+    let mut harness = TestHarness::new(service);
+
+    let result = harness.initialize().await;
+    assert_eq!(result.server_info.name, "Harness server");
+
+    let tools = harness.list_tools().await.unwrap().tools;
+    assert_eq!(tools[0].name, "count_down");
+
+    // Notifications sent while handling a request are queued, and may be consumed afterwards
+    let result = harness
+        .call_tool("count_down", json!({ "from": 2 }))
+        .await
+        .unwrap();
+    assert_eq!(result.content, vec![Content::text("Lift off!")]);
+    for n in [2, 1] {
+        let notification = harness.expect_notification("notifications/message").await;
+        assert_eq!(
+            serde_json::to_value(notification.params).unwrap(),
+            json!({ "level": "info", "data": n })
+        );
+    }
+
+    // Tool errors are results; protocol errors are errors
+    let result = harness
+        .call_tool("count_down", json!({ "from": 0 }))
+        .await
+        .unwrap();
+    assert!(result.is_error);
+    let error = harness.call_tool("count_up", json!({})).await.unwrap_err();
+    assert_eq!(error.code, ErrorCode::InvalidParams);
+
+    let result = harness
+        .get_prompt("mission_brief", json!({ "mission": "Apollo" }))
+        .await
+        .unwrap();
+    assert_eq!(result.messages.len(), 1);
+
+    // Changes to the registry are notified
+    registry.remove_tool("count_down");
+    harness
+        .expect_notification("notifications/tools/list_changed")
+        .await;
+    assert!(harness.list_tools().await.unwrap().tools.is_empty());
+
+    harness.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_harness_with_middleware() {
+    let service = ServiceBuilder::new()
+        .layer(TracingLayer::new())
+        .service(init_harness_server())
+        .into_request_service();
+    let mut harness = TestHarness::from_service(service);
+
+    harness.initialize().await;
+    let result = harness
+        .call_tool("count_down", json!({ "from": 1 }))
+        .await
+        .unwrap();
+    assert_eq!(result.content, vec![Content::text("Lift off!")]);
+    harness.expect_notification("notifications/message").await;
+    harness.close().await.unwrap();
+}
+
+#[tokio::test]
+#[should_panic(expected = "Unexpected message from server")]
+async fn test_harness_unconsumed_notification() {
+    let mut harness = TestHarness::new(init_harness_server());
+    harness.initialize().await;
+    harness
+        .call_tool("count_down", json!({ "from": 1 }))
+        .await
+        .unwrap();
+    harness.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_harness_server_request() {
+    let service = MCPServiceBuilder::new("Roots server".to_string())
+        .with_tool(CountRoots)
+        .build();
+    let mut harness = TestHarness::new(service);
+    harness.initialize().await;
+
+    // The tool awaits the client's response, so the call is sent without waiting for its result
+    let call = json!({
+        "jsonrpc": "2.0",
+        "id": "call",
+        "method": "tools/call",
+        "params": { "name": "count_roots", "arguments": {} }
+    });
+    harness.send_line(&call.to_string()).await;
+    let request = harness.expect_request("roots/list").await;
+
+    // Requests received in the meantime are handled once the call completes
+    harness
+        .send_line(r#"{"jsonrpc": "2.0", "id": "ping", "method": "ping"}"#)
+        .await;
+    harness
+        .respond(request.id, json!({ "roots": [{ "uri": "file:///home" }] }))
+        .await;
+    let response = harness.next_message().await;
+    assert_eq!(response["id"], "call");
+    assert_eq!(response["result"]["content"][0]["text"], "1 roots");
+    assert_eq!(harness.next_message().await["id"], "ping");
+
+    // Errors returned by the client are passed to the handler
+    harness.send_line(&call.to_string()).await;
+    let request = harness.expect_request("roots/list").await;
+    let error = ErrorData::new(ErrorCode::MethodNotFound, "No roots".to_string());
+    harness.respond_error(request.id, error).await;
+    let response = harness.next_message().await;
+    assert_eq!(response["result"]["isError"], true);
+    assert_eq!(
+        response["result"]["content"][0]["text"],
+        "Error: The peer returned an error: No roots"
+    );
+
+    harness.close().await.unwrap();
+}
+
+#[tool(description = "Count the client's roots")]
+async fn count_roots(peer: Peer) -> Result<String, ToolError> {
+    let result = peer
+        .request("roots/list", None)
+        .await
+        .map_err(|e| ToolError::ExecutionError(e.to_string()))?;
+    let roots = result["roots"].as_array().map_or(0, Vec::len);
+    Ok(format!("{roots} roots"))
+}
+
+#[tool(description = "Count down to lift off")]
+async fn count_down(peer: Peer, from: u32) -> Result<String, ToolError> {
+    if from == 0 {
+        return Err(ToolError::ExecutionError("Nothing to count".to_string()));
+    }
+    for n in (1..=from).rev() {
+        let _ = peer.notify_with(
+            "notifications/message",
+            Some(json!({ "level": "info", "data": n })),
+        );
+    }
+    Ok("Lift off!".to_string())
+}
+
+#[prompt(
+    description = "Brief a mission",
+    params(mission = "The mission's name")
+)]
+async fn mission_brief(mission: String) -> String {
+    format!("Brief the crew of {mission}")
+}
+
+fn init_harness_server() -> MCPService {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+
+    MCPServiceBuilder::new("Harness server".to_string())
+        .with_tool(CountDown)
+        .with_prompt(MissionBrief)
+        .build()
+}
@@ -0,0 +1,200 @@
+use kuri::{
+    context::Inject, testing::TestHarness, tool_box, MCPService, MCPServiceBuilder, ToolError,
+};
+use kuri_mcp_protocol::{prompt::PromptMessageRole, Content};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc, Mutex,
+    },
+};
+
+// Tool box tests: tools and prompts defined as methods, sharing an instance of their type
+
+#[tokio::test]
+async fn test_tool_box_tools() {
+    let store = Arc::new(Store::default());
+    let mut harness = TestHarness::new(init_tool_box_server(store.clone()));
+    harness.initialize().await;
+
+    let mut tools = harness.list_tools().await.unwrap().tools;
+    tools.sort_by(|a, b| a.name.cmp(&b.name));
+    let names: Vec<_> = tools.iter().map(|tool| tool.name.as_str()).collect();
+    assert_eq!(names, ["get", "key_count", "max_len", "set", "total_len"]);
+    assert_eq!(tools[3].description, "Store a value under a key");
+    assert_eq!(
+        tools[3].input_schema["properties"]["key"]["description"],
+        "Where to store the value"
+    );
+
+    // Every handler shares the store
+    let result = harness
+        .call_tool("set", json!({ "key": "greeting", "value": "hello" }))
+        .await
+        .unwrap();
+    assert_eq!(result.content, vec![Content::text("1")]);
+    let result = harness
+        .call_tool("get", json!({ "key": "greeting" }))
+        .await
+        .unwrap();
+    assert_eq!(result.content, vec![Content::text("hello")]);
+    assert_eq!(store.values.lock().unwrap()["greeting"], "hello");
+
+    let result = harness.call_tool("get", json!({ "key": "farewell" })).await;
+    assert!(result.unwrap().is_error);
+
+    // Methods without a receiver are handlers too
+    let result = harness.call_tool("max_len", json!({})).await.unwrap();
+    assert_eq!(result.content, vec![Content::text("16")]);
+
+    // Methods may take extractors
+    let result = harness.call_tool("key_count", json!({})).await.unwrap();
+    assert_eq!(result.content, vec![Content::text("1")]);
+
+    // Blocking methods share the store too
+    let result = harness.call_tool("total_len", json!({})).await.unwrap();
+    assert_eq!(result.content, vec![Content::text("5")]);
+    harness.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tool_box_prompts() {
+    let store = Arc::new(Store::default());
+    store
+        .values
+        .lock()
+        .unwrap()
+        .insert("greeting".to_string(), "hello".to_string());
+    let mut harness = TestHarness::new(init_tool_box_server(store));
+    harness.initialize().await;
+
+    let prompts = harness.list_prompts().await.unwrap().prompts;
+    assert_eq!(prompts.len(), 1);
+    assert_eq!(prompts[0].name, "explain");
+    assert_eq!(
+        prompts[0].description.as_deref(),
+        Some("Explain a stored value")
+    );
+
+    let result = harness
+        .get_prompt("explain", json!({ "key": "greeting" }))
+        .await
+        .unwrap();
+    assert_eq!(result.messages.len(), 1);
+    assert_eq!(result.messages[0].role, PromptMessageRole::User);
+    assert_eq!(
+        serde_json::to_value(&result.messages[0].content).unwrap()["text"],
+        "Explain why `greeting` is hello"
+    );
+    harness.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_tool_box_methods_remain_callable() {
+    let store = Store::default();
+    assert_eq!(store.set("a".to_string(), "b".to_string()).await, 1);
+    assert_eq!(store.get("a".to_string()).await.unwrap(), "b");
+}
+
+#[derive(Default)]
+struct Store {
+    values: Mutex<HashMap<String, String>>,
+}
+
+#[tool_box]
+impl Store {
+    /// Store a value under a key
+    #[tool]
+    async fn set(
+        &self,
+        /// Where to store the value
+        key: String,
+        value: String,
+    ) -> u64 {
+        let mut values = self.values.lock().unwrap();
+        values.insert(key, value);
+        values.len() as u64
+    }
+
+    #[tool(description = "Get the value stored under a key", read_only)]
+    async fn get(&self, key: String) -> Result<String, ToolError> {
+        self.values
+            .lock()
+            .unwrap()
+            .get(&key)
+            .cloned()
+            .ok_or_else(|| ToolError::ExecutionError(format!("No value for `{key}`")))
+    }
+
+    /// The longest value that may be stored
+    #[tool]
+    async fn max_len() -> u32 {
+        16
+    }
+
+    /// Count the keys, recording how many times they've been counted
+    #[tool]
+    async fn key_count(&self, counts: Inject<AtomicU32>) -> u64 {
+        counts.fetch_add(1, Ordering::SeqCst);
+        self.values.lock().unwrap().len() as u64
+    }
+
+    /// Explain a stored value
+    #[prompt]
+    async fn explain(&self, key: String) -> Result<String, kuri::PromptError> {
+        let values = self.values.lock().unwrap();
+        let value = values
+            .get(&key)
+            .ok_or_else(|| kuri::PromptError::InvalidParameters(format!("No value for `{key}`")))?;
+        Ok(format!("Explain why `{key}` is {value}"))
+    }
+
+    /// The total length of the stored values
+    #[tool(blocking)]
+    fn total_len(&self) -> u64 {
+        let values = self.values.lock().unwrap();
+        values.values().map(|value| value.len() as u64).sum()
+    }
+
+    // Methods without an attribute are left alone
+    #[allow(dead_code)]
+    fn clear(&self) {
+        self.values.lock().unwrap().clear();
+    }
+}
+
+// A second tool box in the same module, with a handler of the same name
+struct Mirror;
+
+#[tool_box]
+impl Mirror {
+    #[tool]
+    async fn get(&self, text: String) -> String {
+        text.chars().rev().collect()
+    }
+}
+
+#[tokio::test]
+async fn test_tool_box_names_dont_collide() {
+    let service = MCPServiceBuilder::new("Mirror".to_string())
+        .with_tool_box(Arc::new(Mirror))
+        .build();
+    let mut harness = TestHarness::new(service);
+    harness.initialize().await;
+
+    let result = harness
+        .call_tool("get", json!({ "text": "abc" }))
+        .await
+        .unwrap();
+    assert_eq!(result.content, vec![Content::text("cba")]);
+    harness.close().await.unwrap();
+}
+
+fn init_tool_box_server(store: Arc<Store>) -> MCPService {
+    MCPServiceBuilder::new("Tool box".to_string())
+        .with_tool_box(store)
+        .with_state(Inject::new(AtomicU32::new(0)))
+        .build()
+}
@@ -1,15 +1,24 @@
 mod common;
 
-use std::sync::atomic::{AtomicI32, Ordering};
+use anyhow::Context;
+use std::{
+    sync::atomic::{AtomicI32, Ordering},
+    thread::ThreadId,
+};
 
 use common::*;
-use kuri::{context::Inject, tool, MCPService, MCPServiceBuilder, ToolError};
+use kuri::{
+    context::Inject,
+    response::{Image, Json},
+    tool, MCPService, MCPServiceBuilder, ToolAnnotations, ToolError,
+};
 use kuri_mcp_protocol::{
     jsonrpc::{ErrorCode, RequestId, ResponseItem},
     messages::{CallToolResult, ListToolsResult},
     Content, TextContent,
 };
-use serde::Deserialize;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 use tracing_subscriber::EnvFilter;
 
 // Tool tests
@@ -71,6 +80,8 @@ fn validate_tools_list(response: ResponseItem) {
                         "title": "CalculatorParameters",
                         "type": "object"
                     }),
+                    output_schema: None,
+                    annotations: None,
                 }],
             };
             assert_eq!(actual, expected);
@@ -81,6 +92,397 @@ fn validate_tools_list(response: ResponseItem) {
     }
 }
 
+#[tokio::test]
+async fn test_tools_list_annotations() {
+    let mut server = init_tool_server_with_ctx();
+
+    let response = call_server(&mut server, "tools/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: ListToolsResult = serde_json::from_value(result).unwrap();
+            let annotations = |name: &str| {
+                actual
+                    .tools
+                    .iter()
+                    .find(|tool| tool.name == name)
+                    .unwrap()
+                    .annotations
+                    .clone()
+            };
+
+            assert_eq!(
+                annotations("get_value"),
+                Some(ToolAnnotations {
+                    title: Some("Get counter value".to_string()),
+                    read_only_hint: Some(true),
+                    idempotent_hint: Some(true),
+                    ..Default::default()
+                })
+            );
+            assert_eq!(
+                annotations("decrement"),
+                Some(ToolAnnotations {
+                    destructive_hint: Some(false),
+                    ..Default::default()
+                })
+            );
+            assert_eq!(annotations("increment"), None);
+        }
+        ResponseItem::Error { .. } => {
+            panic!("Expected success response");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_tools_list_doc_comments() {
+    let mut server = MCPServiceBuilder::new("Documented".to_string())
+        .with_tool(Scale)
+        .build();
+
+    let response = call_server(&mut server, "tools/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: ListToolsResult = serde_json::from_value(result).unwrap();
+            let tool = &actual.tools[0];
+            assert_eq!(
+                tool.description,
+                "Scale a number by a factor.\n\nThe result is rounded towards zero."
+            );
+            // Descriptions in `params(...)` take precedence over doc comments
+            assert_eq!(
+                tool.input_schema["properties"]["x"]["description"],
+                "The number to scale"
+            );
+            assert_eq!(
+                tool.input_schema["properties"]["factor"]["description"],
+                "What to multiply by"
+            );
+            assert!(tool.input_schema["properties"]["round"]
+                .get("description")
+                .is_none());
+        }
+        ResponseItem::Error { .. } => {
+            panic!("Expected success response");
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_tools_params_options() {
+    let mut server = MCPServiceBuilder::new("Search".to_string())
+        .with_tool(Search)
+        .build();
+
+    let response = call_server(&mut server, "tools/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    let actual: ListToolsResult = serde_json::from_value(result).unwrap();
+    let schema = &actual.tools[0].input_schema;
+    let properties = schema["properties"].as_object().unwrap();
+    let mut names: Vec<_> = properties.keys().map(String::as_str).collect();
+    names.sort();
+    // The flattened struct's fields are arguments in their own right
+    assert_eq!(
+        names,
+        ["language", "maxResults", "page", "query", "safe_search"]
+    );
+    assert_eq!(properties["maxResults"]["default"], 10);
+    assert_eq!(
+        properties["maxResults"]["description"],
+        "How many results to return"
+    );
+    assert_eq!(properties["safe_search"]["default"], true);
+    // Arguments with defaults, and optional arguments, aren't required
+    let mut required: Vec<_> = schema["required"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|name| name.as_str().unwrap())
+        .collect();
+    required.sort();
+    assert_eq!(required, ["language", "query"]);
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({
+            "name": "search",
+            "arguments": { "query": "kuri", "language": "en" }
+        }),
+    )
+    .await
+    .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    let actual: CallToolResult = serde_json::from_value(result).unwrap();
+    assert_eq!(
+        actual.content,
+        vec![Content::text("10 results for kuri (en, safe), page 1")]
+    );
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({
+            "name": "search",
+            "arguments": {
+                "query": "kuri",
+                "maxResults": 3,
+                "page": 2,
+                "language": "fr",
+                "safe_search": false
+            }
+        }),
+    )
+    .await
+    .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    let actual: CallToolResult = serde_json::from_value(result).unwrap();
+    assert_eq!(
+        actual.content,
+        vec![Content::text("3 results for kuri (fr), page 2")]
+    );
+}
+
+#[tokio::test]
+async fn test_tools_call_omitted_arguments_with_defaults() {
+    let mut server = MCPServiceBuilder::new("Dice".to_string())
+        .with_tool(Roll)
+        .build();
+
+    // Arguments may be omitted entirely when none are required
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "roll" }),
+    )
+    .await
+    .unwrap();
+    let ResponseItem::Success { result, .. } = response else {
+        panic!("Expected success response");
+    };
+    let actual: CallToolResult = serde_json::from_value(result).unwrap();
+    assert_eq!(actual.content, vec![Content::text("1d6")]);
+}
+
+#[tokio::test]
+async fn test_tools_call_sync_and_blocking() {
+    let mut server = MCPServiceBuilder::new("Text".to_string())
+        .with_tool(Reverse)
+        .with_tool(WordCount)
+        .with_state(Inject::new(TestThread(std::thread::current().id())))
+        .build();
+
+    let text = |response: Option<ResponseItem>| {
+        let Some(ResponseItem::Success { result, .. }) = response else {
+            panic!("Expected success response");
+        };
+        let result: CallToolResult = serde_json::from_value(result).unwrap();
+        assert!(!result.is_error, "{:?}", result.content);
+        result.content
+    };
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "reverse", "arguments": { "text": "stressed" } }),
+    )
+    .await;
+    assert_eq!(text(response), vec![Content::text("desserts")]);
+
+    // Blocking tools run on another thread, with the service's state
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "word_count", "arguments": { "document": "one two three" } }),
+    )
+    .await;
+    assert_eq!(text(response), vec![Content::text("3")]);
+}
+
+#[tokio::test]
+async fn test_tools_call_result_conversions() {
+    let mut server = MCPServiceBuilder::new("Files".to_string())
+        .with_tool(FindFile)
+        .with_tool(Thumbnail)
+        .with_tool(Icon)
+        .with_tool(Banner)
+        .with_tool(ParsePort)
+        .with_tool(LoadConfig)
+        .build();
+
+    // Options are empty when `None`
+    let found = call(
+        &mut server,
+        "find_file",
+        serde_json::json!({ "name": "notes.txt" }),
+    )
+    .await;
+    assert_eq!(found, (vec![Content::text("/home/notes.txt")], false));
+    let found = call(
+        &mut server,
+        "find_file",
+        serde_json::json!({ "name": "todo.txt" }),
+    )
+    .await;
+    assert_eq!(found, (vec![], false));
+
+    // Images are base64-encoded
+    let thumbnail = call(&mut server, "thumbnail", serde_json::json!({})).await;
+    assert_eq!(
+        thumbnail,
+        (vec![Content::image("iVBORw0KGgo=", "image/png")], false)
+    );
+    let icon = call(&mut server, "icon", serde_json::json!({})).await;
+    assert_eq!(
+        icon,
+        (
+            vec![Content::text(
+                "Error: Binary data is 8 bytes, over the limit of 4 bytes"
+            )],
+            true
+        )
+    );
+    let banner = call(&mut server, "banner", serde_json::json!({})).await;
+    assert_eq!(banner, (vec![Content::text("Welcome")], false));
+
+    // Errors are logical errors, described along with their sources
+    let port = call(
+        &mut server,
+        "parse_port",
+        serde_json::json!({ "port": "eighty" }),
+    )
+    .await;
+    assert_eq!(
+        port,
+        (vec![Content::text("invalid digit found in string")], true)
+    );
+    let config = call(&mut server, "load_config", serde_json::json!({})).await;
+    assert_eq!(
+        config,
+        (
+            vec![Content::text(
+                "Failed to load config: No such file: /etc/tool.toml"
+            )],
+            true
+        )
+    );
+}
+
+/// Call a tool, returning its result's content and whether it's an error
+async fn call(
+    server: &mut MCPService,
+    name: &str,
+    arguments: serde_json::Value,
+) -> (Vec<Content>, bool) {
+    let response = call_server(
+        server,
+        "tools/call",
+        serde_json::json!({ "name": name, "arguments": arguments }),
+    )
+    .await;
+    let Some(ResponseItem::Success { result, .. }) = response else {
+        panic!("Expected success response");
+    };
+    let result: CallToolResult = serde_json::from_value(result).unwrap();
+    (result.content, result.is_error)
+}
+
+#[tokio::test]
+async fn test_tools_structured_output() {
+    let mut server = init_tool_server_structured();
+
+    let response = call_server(&mut server, "tools/list", serde_json::json!({}))
+        .await
+        .unwrap();
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: ListToolsResult = serde_json::from_value(result).unwrap();
+            assert_eq!(
+                actual.tools[0].output_schema,
+                Some(serde_json::json!({
+                    "$schema": "http://json-schema.org/draft-07/schema#",
+                    "properties": {
+                        "quotient": {
+                            "format": "int32",
+                            "type": "integer"
+                        },
+                        "remainder": {
+                            "format": "int32",
+                            "type": "integer"
+                        }
+                    },
+                    "required": ["quotient", "remainder"],
+                    "title": "Division",
+                    "type": "object"
+                }))
+            );
+        }
+        ResponseItem::Error { .. } => {
+            panic!("Expected success response");
+        }
+    }
+
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "divide", "arguments": { "x": 7, "y": 2 } }),
+    )
+    .await
+    .unwrap();
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: CallToolResult = serde_json::from_value(result).unwrap();
+            let expected = serde_json::json!({ "quotient": 3, "remainder": 1 });
+            assert_eq!(actual.structured_content, Some(expected.clone()));
+            // Text fallback, for clients that don't support structured content
+            match &actual.content[..] {
+                [Content::Text(TextContent { text, .. })] => {
+                    assert_eq!(
+                        serde_json::from_str::<serde_json::Value>(text).unwrap(),
+                        expected
+                    )
+                }
+                other => panic!("Expected a single text content, got {other:?}"),
+            }
+            assert!(!actual.is_error);
+        }
+        ResponseItem::Error { .. } => {
+            panic!("Expected success response");
+        }
+    }
+
+    // Errors don't have structured content
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({ "name": "divide", "arguments": { "x": 7, "y": 0 } }),
+    )
+    .await
+    .unwrap();
+    match response {
+        ResponseItem::Success { result, .. } => {
+            let actual: CallToolResult = serde_json::from_value(result).unwrap();
+            assert_eq!(actual.structured_content, None);
+            assert!(actual.is_error);
+        }
+        ResponseItem::Error { .. } => {
+            panic!("Expected success response");
+        }
+    }
+}
+
 #[tokio::test]
 async fn test_tools_call_simple_text() {
     let mut server = init_tool_server_simple();
@@ -119,6 +521,7 @@ async fn verify_calculator(server: &mut MCPService, tool_name: &str) {
                     text: "3".to_string(),
                     annotations: None,
                 })],
+                structured_content: None,
                 is_error: false,
             };
             assert_eq!(actual.content[0], expected.content[0]);
@@ -153,7 +556,7 @@ async fn test_tools_call_with_invalid_parameters() {
             assert_eq!(error.code, ErrorCode::InvalidParams);
             assert_eq!(
                 error.message,
-                "Invalid parameters: Missing or incorrect tool arguments"
+                "Invalid parameters: Missing or incorrect tool arguments: missing field `x`"
             );
         }
         _ => {
@@ -185,7 +588,7 @@ async fn test_tools_call_with_invalid_parameters() {
             assert_eq!(error.code, ErrorCode::InvalidParams);
             assert_eq!(
                 error.message,
-                "Invalid parameters: Missing or incorrect tool arguments"
+                "Invalid parameters: Missing or incorrect tool arguments: missing field `operation`"
             );
         }
     }
@@ -214,10 +617,56 @@ async fn test_tools_call_invalid_tool() {
             assert_eq!(id, RequestId::Num(1));
             assert_eq!(error.code, ErrorCode::InvalidParams);
             assert_eq!(error.message, "Tool not found: some_invalid_tool");
+            assert_eq!(
+                error.data,
+                Some(serde_json::json!({ "tool": "some_invalid_tool" }))
+            );
         }
     }
 }
 
+#[tokio::test]
+async fn test_tools_call_with_missing_state() {
+    // The counter state is never registered
+    let builder = || MCPServiceBuilder::new("Counter".to_string()).with_tool(Increment);
+
+    let err = builder().try_build().err().expect("Expected missing state");
+    assert_eq!(
+        err.to_string(),
+        "Tool `increment` can't be served: Tried to inject tools::Counter, which is not in the MCPService's state"
+    );
+
+    // Without the check, calling the tool is an internal error (rather than a panic)
+    let mut server = builder().build();
+    let response = call_server(
+        &mut server,
+        "tools/call",
+        serde_json::json!({
+            "name": "increment",
+            "arguments": {
+                "quantity": 1
+            }
+        }),
+    )
+    .await
+    .unwrap();
+
+    match response {
+        ResponseItem::Error { error, .. } => {
+            assert_eq!(error.code, ErrorCode::InternalError);
+        }
+        ResponseItem::Success { .. } => {
+            panic!("Expected error response");
+        }
+    }
+
+    // With the state, the service builds
+    assert!(builder()
+        .with_state(Inject::new(Counter::default()))
+        .try_build()
+        .is_ok());
+}
+
 #[tokio::test]
 async fn test_tools_call_with_context() {
     let mut server = init_tool_server_with_ctx();
@@ -242,6 +691,7 @@ async fn test_tools_call_with_context() {
             let actual: CallToolResult = serde_json::from_value(result).unwrap();
             let expected = CallToolResult {
                 content: vec![],
+                structured_content: None,
                 is_error: false,
             };
             assert_eq!(actual.content, expected.content);
@@ -273,6 +723,7 @@ async fn test_tools_call_with_context() {
                     text: "1".to_string(),
                     annotations: None,
                 })],
+                structured_content: None,
                 is_error: false,
             };
             assert_eq!(actual.content, expected.content);
@@ -301,6 +752,7 @@ async fn test_tools_call_with_context() {
                     text: "1".to_string(),
                     annotations: None,
                 })],
+                structured_content: None,
                 is_error: false,
             };
             assert_eq!(actual.content, expected.content);
@@ -344,6 +796,140 @@ pub async fn calculator_no_desc(x: i32, y: i32, operation: String) -> Result<i32
     calculator(x, y, operation).await
 }
 
+/// Scale a number by a factor.
+///
+/// The result is rounded towards zero.
+#[tool(params(factor = "What to multiply by"))]
+async fn scale(
+    /// The number to scale
+    x: f64,
+    /// The factor
+    factor: f64,
+    round: bool,
+) -> f64 {
+    if round {
+        (x * factor).trunc()
+    } else {
+        x * factor
+    }
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SearchFilter {
+    /// The language of the results
+    language: String,
+    #[serde(default = "safe_search_default")]
+    safe_search: bool,
+}
+
+fn safe_search_default() -> bool {
+    true
+}
+
+#[tool(
+    description = "Search for something",
+    params(max_results(
+        description = "How many results to return",
+        rename = "maxResults",
+        default = 10
+    ))
+)]
+async fn search(
+    query: String,
+    max_results: u32,
+    page: Option<u32>,
+    #[flatten] filter: SearchFilter,
+) -> String {
+    let safe = if filter.safe_search { ", safe" } else { "" };
+    format!(
+        "{max_results} results for {query} ({}{safe}), page {}",
+        filter.language,
+        page.unwrap_or(1)
+    )
+}
+
+#[tool(
+    description = "Roll some dice",
+    params(count(default = 1), sides(default = 6))
+)]
+async fn roll(count: u32, sides: u32) -> String {
+    format!("{count}d{sides}")
+}
+
+/// Reverse some text
+#[tool]
+fn reverse(text: String) -> String {
+    text.chars().rev().collect()
+}
+
+/// Find a file by name
+#[tool]
+async fn find_file(name: String) -> Option<String> {
+    (name == "notes.txt").then(|| format!("/home/{name}"))
+}
+
+/// A thumbnail of the current document
+#[tool]
+async fn thumbnail() -> Image {
+    Image::new(b"\x89PNG\r\n\x1a\n".to_vec(), "image/png")
+}
+
+/// The document's icon, which is too large
+#[tool]
+async fn icon() -> Image {
+    Image::new(b"\x89PNG\r\n\x1a\n".to_vec(), "image/png").with_limit(4)
+}
+
+/// A banner to greet the user with
+#[tool]
+async fn banner() -> Content {
+    Content::text("Welcome")
+}
+
+/// Check that a port is valid
+#[tool]
+async fn parse_port(port: String) -> Result<u16, std::num::ParseIntError> {
+    port.parse()
+}
+
+/// Load the tool's config
+#[tool]
+async fn load_config() -> anyhow::Result<String> {
+    let path = "/etc/tool.toml";
+    Err(anyhow::anyhow!("No such file: {path}")).context("Failed to load config")
+}
+
+/// The thread a test runs on
+struct TestThread(ThreadId);
+
+/// Count the words in a document
+#[tool(blocking)]
+fn word_count(test_thread: Inject<TestThread>, document: String) -> Result<u64, ToolError> {
+    if std::thread::current().id() == test_thread.0 {
+        return Err(ToolError::ExecutionError(
+            "Blocking tool ran on the test's thread".into(),
+        ));
+    }
+    Ok(document.split_whitespace().count() as u64)
+}
+
+#[derive(Serialize, JsonSchema)]
+struct Division {
+    quotient: i32,
+    remainder: i32,
+}
+
+#[tool(description = "Divide two numbers, returning the quotient and remainder")]
+async fn divide(x: i32, y: i32) -> Result<Json<Division>, ToolError> {
+    if y == 0 {
+        return Err(ToolError::ExecutionError("Division by zero".into()));
+    }
+    Ok(Json(Division {
+        quotient: x / y,
+        remainder: x % y,
+    }))
+}
+
 #[derive(Default, Deserialize)]
 struct Counter {
     inner: AtomicI32,
@@ -359,13 +945,19 @@ async fn increment(counter: Inject<Counter>, quantity: u32) {
 
 #[tool(
     description = "Decrement the counter by a specified quantity",
-    params(quantity = "How much to decrement the counter by")
+    params(quantity = "How much to decrement the counter by"),
+    destructive = false
 )]
 async fn decrement(counter: Inject<Counter>, quantity: u32) {
     counter.inner.fetch_sub(quantity as i32, Ordering::SeqCst);
 }
 
-#[tool(description = "Get current value of counter")]
+#[tool(
+    description = "Get current value of counter",
+    title = "Get counter value",
+    read_only,
+    idempotent
+)]
 async fn get_value(counter: Inject<Counter>) -> i32 {
     counter.inner.load(Ordering::SeqCst)
 }
@@ -392,6 +984,17 @@ pub fn init_tool_server_no_desc() -> MCPService {
         .build()
 }
 
+pub fn init_tool_server_structured() -> MCPService {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+
+    MCPServiceBuilder::new("Calculator".to_string())
+        .with_tool(Divide)
+        .build()
+}
+
 pub fn init_tool_server_with_ctx() -> MCPService {
     let _ = tracing_subscriber::fmt()
         .with_env_filter(EnvFilter::from_default_env())
@@ -0,0 +1,11 @@
+// Compile-time diagnostics of the macros. Each file in `tests/ui` should fail to compile, with the
+// errors recorded alongside it in a `.stderr` file.
+//
+// To re-record the errors after an intended change, run with `TRYBUILD=overwrite`, and review the
+// diff.
+
+#[test]
+fn ui() {
+    let t = trybuild::TestCases::new();
+    t.compile_fail("tests/ui/*.rs");
+}
@@ -0,0 +1,8 @@
+use kuri::tool;
+
+#[tool(description = "Count the words in a document", blocking)]
+async fn word_count(document: String) -> u64 {
+    document.split_whitespace().count() as u64
+}
+
+fn main() {}
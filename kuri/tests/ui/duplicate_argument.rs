@@ -0,0 +1,13 @@
+use kuri::{prompt, tool};
+
+#[tool(name = "add", name = "sum")]
+async fn add(x: i32, y: i32) -> i32 {
+    x + y
+}
+
+#[prompt(description = "Greet someone", description = "Say hello")]
+async fn greet(name: String) -> String {
+    format!("Hello, {name}")
+}
+
+fn main() {}
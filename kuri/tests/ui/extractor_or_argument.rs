@@ -0,0 +1,19 @@
+use kuri::tool;
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct Meta {
+    tag: String,
+}
+
+// Named like kuri's `Meta` extractor, so taken to be one
+#[tool]
+async fn tag(meta: Meta) -> String {
+    meta.tag
+}
+
+#[tool]
+async fn untag(#[arg] #[extract] meta: Meta) -> String {
+    meta.tag
+}
+
+fn main() {}
@@ -0,0 +1,18 @@
+use kuri::tool;
+
+#[tool(description = 42)]
+async fn add(x: i32, y: i32) -> i32 {
+    x + y
+}
+
+#[tool(read_only = "yes")]
+async fn subtract(x: i32, y: i32) -> i32 {
+    x - y
+}
+
+#[tool(params(x))]
+async fn multiply(x: i32, y: i32) -> i32 {
+    x * y
+}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use kuri::{prompt, tool};
+
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct Filter {
+    language: String,
+}
+
+#[tool(params(limit(maximum = 10)))]
+async fn search(query: String, limit: u32) -> String {
+    format!("{limit} results for {query}")
+}
+
+#[tool(params(filter(rename = "where")))]
+async fn filtered_search(query: String, #[flatten] filter: Filter) -> String {
+    format!("Results for {query} in {}", filter.language)
+}
+
+#[prompt]
+async fn translate(text: String, #[flatten] filter: Filter) -> String {
+    format!("Translate {text} into {}", filter.language)
+}
+
+fn main() {}
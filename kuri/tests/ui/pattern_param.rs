@@ -0,0 +1,8 @@
+use kuri::tool;
+
+#[tool(description = "Add two numbers")]
+async fn add((x, y): (i32, i32)) -> i32 {
+    x + y
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use kuri::prompt;
+
+#[prompt(description = "Compare some languages")]
+async fn compare(languages: Vec<String>) -> String {
+    format!("Compare {}", languages.join(", "))
+}
+
+fn main() {}
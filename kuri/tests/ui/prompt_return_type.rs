@@ -0,0 +1,13 @@
+use kuri::prompt;
+
+#[prompt(description = "Count the characters in some text")]
+async fn count(text: String) -> usize {
+    text.len()
+}
+
+#[prompt(description = "Greet someone")]
+async fn greet(name: String) {
+    println!("Hello, {name}");
+}
+
+fn main() {}
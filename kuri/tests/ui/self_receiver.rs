@@ -0,0 +1,12 @@
+use kuri::tool;
+
+struct Calculator;
+
+impl Calculator {
+    #[tool(description = "Add two numbers")]
+    async fn add(&self, x: i32, y: i32) -> i32 {
+        x + y
+    }
+}
+
+fn main() {}
@@ -0,0 +1,29 @@
+use kuri::tool_box;
+
+struct Calculator;
+
+#[tool_box]
+impl Calculator {
+    #[tool(description = "Add two numbers")]
+    async fn add(&self, x: i32, y: i32) -> i32 {
+        x + y
+    }
+
+    #[tool(name = "add", description = "Add two numbers, saturating")]
+    async fn saturating_add(&self, x: i32, y: i32) -> i32 {
+        x.saturating_add(y)
+    }
+
+    // Tools and prompts are named separately, so a prompt may share a tool's name
+    #[prompt(description = "Ask to add two numbers")]
+    async fn add_prompt(&self, x: i32, y: i32) -> String {
+        format!("What's {x} + {y}?")
+    }
+
+    #[prompt(name = "add_prompt", description = "Ask to add two numbers, in words")]
+    async fn add_in_words(&self, x: i32, y: i32) -> String {
+        format!("What's {x} plus {y}?")
+    }
+}
+
+fn main() {}
@@ -0,0 +1,25 @@
+use kuri::tool_box;
+
+struct Counter(u32);
+
+#[tool_box]
+impl Counter {
+    #[tool(description = "Increment the counter")]
+    async fn increment(&mut self) -> u32 {
+        self.0 += 1;
+        self.0
+    }
+
+    #[tool(description = "Consume the counter")]
+    async fn finish(self) -> u32 {
+        self.0
+    }
+
+    #[tool]
+    #[prompt]
+    async fn value(&self) -> String {
+        self.0.to_string()
+    }
+}
+
+fn main() {}
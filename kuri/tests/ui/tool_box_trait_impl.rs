@@ -0,0 +1,12 @@
+use kuri::tool_box;
+
+struct Greeter;
+
+#[tool_box]
+impl Default for Greeter {
+    fn default() -> Self {
+        Greeter
+    }
+}
+
+fn main() {}
@@ -0,0 +1,10 @@
+use kuri::tool;
+
+struct Sum(i32);
+
+#[tool(description = "Add two numbers")]
+async fn add(x: i32, y: i32) -> Sum {
+    Sum(x + y)
+}
+
+fn main() {}
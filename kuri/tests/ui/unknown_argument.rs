@@ -0,0 +1,13 @@
+use kuri::{prompt, tool};
+
+#[tool(descripton = "Add two numbers")]
+async fn add(x: i32, y: i32) -> i32 {
+    x + y
+}
+
+#[prompt(read_only)]
+async fn greet(name: String) -> String {
+    format!("Hello, {name}")
+}
+
+fn main() {}
@@ -0,0 +1,13 @@
+use kuri::tool;
+
+#[tool(params(x = "The first number", z = "The second number"))]
+async fn add(x: i32, y: i32) -> i32 {
+    x + y
+}
+
+#[tool(params(counter = "The counter"))]
+async fn get_value(counter: kuri::context::Inject<i32>) -> i32 {
+    *counter
+}
+
+fn main() {}
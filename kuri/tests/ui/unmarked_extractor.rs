@@ -0,0 +1,42 @@
+use kuri::{
+    context::RequestContext,
+    extract::{ExtractError, FromRequestContext},
+    prompt, tool,
+};
+
+// An extractor which also happens to be deserialisable, so would otherwise be taken silently from
+// the client's arguments
+#[derive(serde::Deserialize, schemars::JsonSchema)]
+struct TraceId(String);
+
+impl std::str::FromStr for TraceId {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(TraceId(s.to_string()))
+    }
+}
+
+impl FromRequestContext for TraceId {
+    fn from_request_context(_ctx: &RequestContext) -> Result<Self, ExtractError> {
+        Ok(TraceId("trace".to_string()))
+    }
+}
+
+#[tool]
+async fn traced(trace_id: TraceId) -> String {
+    trace_id.0
+}
+
+#[prompt]
+async fn traced_prompt(trace_id: TraceId) -> String {
+    trace_id.0
+}
+
+// Marked either way, the parameter is fine
+#[tool]
+async fn extracted(#[extract] trace_id: TraceId, #[arg] given: TraceId) -> String {
+    format!("{} {}", trace_id.0, given.0)
+}
+
+fn main() {}
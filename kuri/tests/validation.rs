@@ -0,0 +1,189 @@
+#![cfg(feature = "validation")]
+
+use async_trait::async_trait;
+use kuri::{
+    context::RequestContext, middleware::validation::ValidationLayer, testing::TestHarness, tool,
+    CallToolResult, MCPService, MCPServiceBuilder, ServiceExt, ToolError, ToolHandler,
+};
+use kuri_mcp_protocol::{jsonrpc::ErrorCode, Content};
+use serde_json::{json, Value};
+use tower::ServiceBuilder;
+
+// Validation middleware tests: tool arguments are checked against the tool's schema before dispatch
+
+#[tokio::test]
+async fn test_validation_valid_arguments() {
+    let mut harness = harness(server());
+    harness.initialize().await;
+
+    let result = harness
+        .call_tool("add", json!({ "x": 1, "y": 2 }))
+        .await
+        .unwrap();
+    assert_eq!(result.content, vec![Content::text("3")]);
+
+    // Tools without parameters may be called without arguments
+    let response = harness
+        .request("tools/call", json!({ "name": "now" }))
+        .await;
+    assert!(matches!(
+        response,
+        kuri_mcp_protocol::jsonrpc::ResponseItem::Success { .. }
+    ));
+
+    // Or with null arguments, as for omitted ones
+    let response = harness
+        .request("tools/call", json!({ "name": "now", "arguments": null }))
+        .await;
+    assert!(matches!(
+        response,
+        kuri_mcp_protocol::jsonrpc::ResponseItem::Success { .. }
+    ));
+
+    harness.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_validation_reports_pointers() {
+    let mut harness = harness(server());
+    harness.initialize().await;
+
+    let error = harness
+        .call_tool("add", json!({ "x": 1, "y": "two" }))
+        .await
+        .unwrap_err();
+    assert_eq!(error.code, ErrorCode::InvalidParams);
+    assert_eq!(
+        error.message,
+        r#"Invalid arguments for tool `add`: /y: "two" is not of type "integer""#
+    );
+    assert_eq!(
+        error.data,
+        Some(json!({
+            "errors": [{ "pointer": "/y", "message": r#""two" is not of type "integer""# }]
+        }))
+    );
+
+    // Every failure is reported; those of the arguments object as a whole have an empty pointer
+    let error = harness
+        .call_tool("add", json!({ "x": 1.5 }))
+        .await
+        .unwrap_err();
+    let errors = &error.data.unwrap()["errors"];
+    let pointers: Vec<_> = errors
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|error| error["pointer"].as_str().unwrap())
+        .collect();
+    assert_eq!(pointers.len(), 2);
+    assert!(pointers.contains(&""));
+    assert!(pointers.contains(&"/x"));
+
+    harness.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_validation_hand_written_tool() {
+    let service = server();
+    service.registry().add_tool(ReadFile);
+    let mut harness = harness(service);
+    harness.initialize().await;
+
+    let error = harness
+        .call_tool("read_file", json!({ "path": "" }))
+        .await
+        .unwrap_err();
+    assert_eq!(
+        error.message,
+        r#"Invalid arguments for tool `read_file`: /path: "" is shorter than 1 character"#
+    );
+
+    let result = harness
+        .call_tool("read_file", json!({ "path": "notes.txt" }))
+        .await
+        .unwrap();
+    assert_eq!(result.content, vec![Content::text("Contents of notes.txt")]);
+
+    harness.close().await.unwrap();
+}
+
+#[tokio::test]
+async fn test_validation_unknown_tool() {
+    let mut harness = harness(server());
+    harness.initialize().await;
+
+    // Left to the service to report
+    let error = harness
+        .call_tool("subtract", json!({ "x": "one" }))
+        .await
+        .unwrap_err();
+    assert_eq!(error.message, "Tool not found: subtract");
+    assert_eq!(error.data, Some(json!({ "tool": "subtract" })));
+
+    harness.close().await.unwrap();
+}
+
+#[tool(description = "Add two numbers")]
+async fn add(x: i32, y: i32) -> i32 {
+    x + y
+}
+
+#[tool(description = "Get the time")]
+async fn now() -> String {
+    "Noon".to_string()
+}
+
+/// A tool without a generated schema, or deserialisation of its arguments.
+struct ReadFile;
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl ToolHandler for ReadFile {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn description(&self) -> &str {
+        "Read a file"
+    }
+
+    fn schema(&self) -> Value {
+        json!({
+            "type": "object",
+            "properties": { "path": { "type": "string", "minLength": 1 } },
+            "required": ["path"],
+        })
+    }
+
+    async fn call(
+        &self,
+        _context: &RequestContext,
+        params: Value,
+    ) -> Result<CallToolResult, ToolError> {
+        Ok(CallToolResult {
+            content: vec![Content::text(format!(
+                "Contents of {}",
+                params["path"].as_str().unwrap()
+            ))],
+            structured_content: None,
+            is_error: false,
+        })
+    }
+}
+
+fn server() -> MCPService {
+    MCPServiceBuilder::new("Validated".to_string())
+        .with_tool(Add)
+        .with_tool(Now)
+        .build()
+}
+
+fn harness(service: MCPService) -> TestHarness {
+    let registry = service.registry();
+    let service = ServiceBuilder::new()
+        .layer(ValidationLayer::new(registry))
+        .service(service)
+        .into_request_service();
+    TestHarness::from_service(service)
+}
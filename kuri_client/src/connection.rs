@@ -0,0 +1,328 @@
+use crate::{Callbacks, ClientError};
+use futures::{SinkExt, StreamExt};
+use kuri_mcp_protocol::jsonrpc::{
+    ErrorCode, ErrorData, MethodCall, Notification, Params, RequestId, ResponseItem,
+    SendableMessage,
+};
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::{mpsc, oneshot},
+};
+use tokio_util::codec::{FramedRead, FramedWrite, LinesCodec};
+
+/// Locks are only held briefly to update the map, so poisoning indicates a bug in the client.
+const POISONED: &str = "pending requests lock poisoned";
+
+type Pending = Arc<Mutex<HashMap<RequestId, oneshot::Sender<ResponseItem>>>>;
+
+pub(crate) type ProgressCallback = Box<dyn Fn(Notification) + Send + Sync>;
+/// Callbacks for the progress of pending requests, keyed by progress token (which is the
+/// request's ID).
+type Progress = Arc<Mutex<HashMap<RequestId, ProgressCallback>>>;
+
+/// The client's side of a connection to an MCP server.
+///
+/// Messages are read and written by a background task, which routes responses to the request
+/// awaiting them, answers the server's requests, and passes notifications on. The task ends
+/// (closing the transport) once the server disconnects, or the connection is dropped.
+pub(crate) struct Connection {
+    outbound: mpsc::UnboundedSender<String>,
+    pending: Pending,
+    progress: Progress,
+    next_id: AtomicU64,
+}
+
+impl Connection {
+    /// Start a connection over `transport`. Notifications from the server are sent on
+    /// `notifications`, which closes when the connection does.
+    pub(crate) fn start<T>(
+        transport: T,
+        callbacks: Callbacks,
+        notifications: mpsc::UnboundedSender<Notification>,
+    ) -> Self
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (outbound, outbound_rx) = mpsc::unbounded_channel();
+        let pending = Pending::default();
+        let progress = Progress::default();
+        let task = Task {
+            pending: pending.clone(),
+            progress: progress.clone(),
+            callbacks: Arc::new(callbacks),
+            notifications,
+        };
+        tokio::spawn(task.run(transport, outbound_rx));
+
+        Self {
+            outbound,
+            pending,
+            progress,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Send a request to the server, and wait for its result. If `on_progress` is given, the
+    /// request asks the server for progress notifications, which are passed to it rather than the
+    /// notification stream.
+    pub(crate) async fn request(
+        &self,
+        method: &str,
+        params: Value,
+        on_progress: Option<ProgressCallback>,
+    ) -> Result<Value, ClientError> {
+        let id = RequestId::Num(self.next_id.fetch_add(1, Ordering::Relaxed));
+        let params = match (params, &on_progress) {
+            (Value::Null, None) => None,
+            (Value::Null, Some(_)) => Some(Params::Map(Default::default())),
+            (params, _) => Some(
+                Params::try_from(params).map_err(|e| ClientError::InvalidParams(e.to_string()))?,
+            ),
+        };
+        let params = match (params, on_progress) {
+            (Some(Params::Map(mut params)), Some(on_progress)) => {
+                let meta = params
+                    .entry("_meta")
+                    .or_insert_with(|| Value::Object(Default::default()));
+                let Value::Object(meta) = meta else {
+                    return Err(ClientError::InvalidParams(
+                        "`_meta` isn't an object".to_string(),
+                    ));
+                };
+                meta.insert("progressToken".to_string(), serde_json::to_value(&id)?);
+                self.progress
+                    .lock()
+                    .expect(POISONED)
+                    .insert(id.clone(), on_progress);
+                Some(Params::Map(params))
+            }
+            (Some(Params::Array(_)), Some(_)) => {
+                return Err(ClientError::InvalidParams(
+                    "progress can only be requested with object params".to_string(),
+                ));
+            }
+            (params, _) => params,
+        };
+
+        let result = self.send_request(id.clone(), method, params).await;
+        self.progress.lock().expect(POISONED).remove(&id);
+        result
+    }
+
+    async fn send_request(
+        &self,
+        id: RequestId,
+        method: &str,
+        params: Option<Params>,
+    ) -> Result<Value, ClientError> {
+        let request = MethodCall::new(id.clone(), method.to_string(), params);
+        let line = serde_json::to_string(&request)?;
+
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().expect(POISONED).insert(id.clone(), tx);
+        if self.outbound.send(line).is_err() {
+            self.pending.lock().expect(POISONED).remove(&id);
+            return Err(ClientError::Disconnected);
+        }
+
+        match rx.await {
+            Ok(ResponseItem::Success { result, .. }) => Ok(result),
+            Ok(ResponseItem::Error { error, .. }) => Err(ClientError::Response(error)),
+            // The connection closed before the server responded
+            Err(_) => Err(ClientError::Disconnected),
+        }
+    }
+
+    /// Send a notification to the server.
+    pub(crate) fn notify(&self, method: &str, params: Option<Params>) -> Result<(), ClientError> {
+        let line = serde_json::to_string(&Notification::new(method.to_string(), params))?;
+        self.outbound
+            .send(line)
+            .map_err(|_| ClientError::Disconnected)
+    }
+}
+
+/// The background task serving a connection.
+struct Task {
+    pending: Pending,
+    progress: Progress,
+    callbacks: Arc<Callbacks>,
+    notifications: mpsc::UnboundedSender<Notification>,
+}
+
+impl Task {
+    async fn run<T>(self, transport: T, mut outbound: mpsc::UnboundedReceiver<String>)
+    where
+        T: AsyncRead + AsyncWrite,
+    {
+        let (read, write) = tokio::io::split(transport);
+        let mut lines = FramedRead::new(read, LinesCodec::new());
+        let mut writer = FramedWrite::new(write, LinesCodec::new());
+
+        // Replies to the server's requests. Callbacks run in their own tasks, so that a slow
+        // callback doesn't hold up other messages, and send their reply here once done.
+        let (replies_tx, mut replies) = mpsc::unbounded_channel();
+
+        loop {
+            let line = tokio::select! {
+                line = lines.next() => match line {
+                    Some(Ok(line)) => {
+                        self.handle_line(&line, &replies_tx);
+                        continue;
+                    }
+                    Some(Err(e)) => {
+                        tracing::error!(error = ?e, "Error reading from server");
+                        break;
+                    }
+                    None => break,
+                },
+                line = outbound.recv() => match line {
+                    Some(line) => line,
+                    // The client has been dropped
+                    None => break,
+                },
+                // The task holds a sender, so the channel never closes
+                Some(line) = replies.recv() => line,
+            };
+
+            if let Err(e) = writer.send(line).await {
+                tracing::error!(error = ?e, "Error writing to server");
+                break;
+            }
+        }
+
+        // Fail new requests, then those still awaiting a response. Dropping the task closes the
+        // notification stream.
+        drop(outbound);
+        self.pending.lock().expect(POISONED).clear();
+        self.progress.lock().expect(POISONED).clear();
+    }
+
+    fn handle_line(&self, line: &str, replies: &mpsc::UnboundedSender<String>) {
+        let message: Value = match serde_json::from_str(line) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!(error = ?e, "Received invalid JSON from server");
+                return;
+            }
+        };
+
+        // Responses are the only messages without a method
+        if message.get("method").is_none() {
+            self.handle_response(message);
+            return;
+        }
+
+        match serde_json::from_value::<SendableMessage>(message) {
+            Ok(SendableMessage::Notification(notification)) => {
+                // Progress is passed to the request's callback, before its response is handled
+                if let Some(notification) = self.handle_progress(notification) {
+                    // The stream may have been dropped, if the caller isn't interested
+                    let _ = self.notifications.send(notification);
+                }
+            }
+            Ok(SendableMessage::Request(request)) => {
+                let callbacks = self.callbacks.clone();
+                let replies = replies.clone();
+                tokio::spawn(async move {
+                    let response = match callbacks.handle(&request).await {
+                        Ok(result) => ResponseItem::success(request.id, result),
+                        Err(error) => ResponseItem::error(request.id, error),
+                    };
+                    match serde_json::to_string(&response) {
+                        Ok(line) => {
+                            let _ = replies.send(line);
+                        }
+                        Err(e) => tracing::error!(error = ?e, "Error serialising reply to server"),
+                    }
+                });
+            }
+            Ok(SendableMessage::Invalid { .. }) | Err(_) => {
+                tracing::warn!("Received invalid message from server");
+            }
+        }
+    }
+
+    /// Pass a progress notification to the callback of the request it's for. Returns the
+    /// notification if it isn't one.
+    fn handle_progress(&self, notification: Notification) -> Option<Notification> {
+        if notification.method != "notifications/progress" {
+            return Some(notification);
+        }
+        let token = match &notification.params {
+            Some(Params::Map(params)) => params
+                .get("progressToken")
+                .and_then(|token| serde_json::from_value::<RequestId>(token.clone()).ok()),
+            _ => None,
+        };
+        let progress = self.progress.lock().expect(POISONED);
+        match token.and_then(|token| progress.get(&token)) {
+            Some(on_progress) => {
+                on_progress(notification);
+                None
+            }
+            None => Some(notification),
+        }
+    }
+
+    fn handle_response(&self, message: Value) {
+        let response = match serde_json::from_value::<ResponseItem>(message) {
+            Ok(response) => response,
+            Err(e) => {
+                tracing::warn!(error = ?e, "Received invalid response from server");
+                return;
+            }
+        };
+        let id = match &response {
+            ResponseItem::Success { id, .. } | ResponseItem::Error { id, .. } => id,
+        };
+        match self.pending.lock().expect(POISONED).remove(id) {
+            Some(tx) => {
+                let _ = tx.send(response);
+            }
+            None => tracing::warn!(?id, "Received response to unknown request"),
+        }
+    }
+}
+
+impl Callbacks {
+    /// Handle a request from the server.
+    async fn handle(&self, request: &MethodCall) -> Result<Value, ErrorData> {
+        let params = request
+            .params
+            .clone()
+            .map(|params| serde_json::to_value(params).expect("params are valid JSON"))
+            .unwrap_or(Value::Object(Default::default()));
+        let invalid_params = |e: serde_json::Error| {
+            ErrorData::new(
+                ErrorCode::InvalidParams,
+                format!("Invalid params for `{}`: {e}", request.method),
+            )
+        };
+
+        match (request.method.as_str(), &self.sampling, &self.roots) {
+            ("ping", _, _) => Ok(Value::Object(Default::default())),
+            ("sampling/createMessage", Some(sampling), _) => {
+                let params = serde_json::from_value(params).map_err(invalid_params)?;
+                let result = sampling(params).await?;
+                Ok(serde_json::to_value(result).expect("result is valid JSON"))
+            }
+            ("roots/list", _, Some(roots)) => {
+                let result = roots().await?;
+                Ok(serde_json::to_value(result).expect("result is valid JSON"))
+            }
+            (method, _, _) => Err(ErrorData::new(
+                ErrorCode::MethodNotFound,
+                format!("The client doesn't support `{method}` requests"),
+            )),
+        }
+    }
+}
@@ -0,0 +1,514 @@
+use bytes::Bytes;
+use futures::{SinkExt, StreamExt};
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::Incoming,
+    header::{HeaderValue, ACCEPT, CONTENT_TYPE},
+    Method, Request, Response, StatusCode, Uri,
+};
+use hyper_util::{
+    client::legacy::{
+        connect::{Connect, HttpConnector},
+        Client,
+    },
+    rt::TokioExecutor,
+};
+use kuri_mcp_protocol::jsonrpc::{ErrorCode, ErrorData, RequestId, ResponseItem};
+use serde_json::Value;
+use std::{
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll},
+};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, DuplexStream, ReadBuf},
+    sync::mpsc,
+    task::JoinSet,
+};
+use tokio_util::codec::{FramedRead, FramedWrite, LinesCodec};
+
+const SESSION_ID: &str = "mcp-session-id";
+const PROTOCOL_VERSION: &str = "mcp-protocol-version";
+const EVENT_STREAM: &str = "text/event-stream";
+
+/// Size of the in-memory buffer between the transport and the task making HTTP requests.
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// Locks are only held briefly to read or update a field, so poisoning indicates a bug in the
+/// client.
+const POISONED: &str = "session lock poisoned";
+
+/// A transport that talks to an MCP server over the [streamable HTTP] transport.
+///
+/// Each message written to the transport is `POST`ed to the server's MCP endpoint. The server's
+/// replies (whether a single JSON response, or an SSE stream of messages) are read back from the
+/// transport, in the same newline-delimited form as any other transport, so a [`Client`] (or
+/// anything else reading and writing JSON-RPC lines) can use it as is.
+///
+/// The session ID assigned by the server when initialising the connection is sent with every
+/// subsequent request, along with the negotiated protocol version. Once initialised, the transport
+/// also listens for messages the server sends outside of a response (such as notifications that
+/// its tools have changed), if the server offers a stream for them. The session is ended when the
+/// transport is dropped.
+///
+/// If an HTTP request fails, a request sent with it receives an error response instead. Resuming
+/// a broken SSE stream isn't supported.
+///
+/// ```rust,no_run
+/// use kuri_client::{Client, HttpTransport};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let transport = HttpTransport::new("http://localhost:8080/mcp".parse()?);
+/// let client = Client::connect(transport);
+/// client.initialize().await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [streamable HTTP]: https://modelcontextprotocol.io/specification/2025-06-18/basic/transports#streamable-http
+/// [`Client`]: crate::Client
+#[derive(Debug)]
+pub struct HttpTransport {
+    /// The HTTP requests are made by a background task, on the other end of this stream.
+    stream: DuplexStream,
+}
+
+impl HttpTransport {
+    /// Connect to the MCP endpoint at `uri`, over plain HTTP. Must be called within a tokio
+    /// runtime, as HTTP requests are made by a background task.
+    pub fn new(uri: Uri) -> Self {
+        Self::with_connector(uri, HttpConnector::new())
+    }
+
+    /// Connect to the MCP endpoint at `uri`, making connections with `connector`. For example, use
+    /// a TLS connector (such as `hyper-rustls`'s) to connect over HTTPS.
+    pub fn with_connector<C>(uri: Uri, connector: C) -> Self
+    where
+        C: Connect + Clone + Send + Sync + 'static,
+    {
+        let (stream, task_stream) = tokio::io::duplex(BUFFER_SIZE);
+        let session = Arc::new(Session {
+            client: Client::builder(TokioExecutor::new()).build(connector),
+            uri,
+            headers: Mutex::default(),
+        });
+        tokio::spawn(session.run(task_stream));
+        Self { stream }
+    }
+}
+
+impl AsyncRead for HttpTransport {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.stream).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for HttpTransport {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, io::Error>> {
+        Pin::new(&mut self.stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.stream).poll_flush(cx)
+    }
+
+    fn poll_shutdown(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), io::Error>> {
+        Pin::new(&mut self.stream).poll_shutdown(cx)
+    }
+}
+
+/// Headers identifying the session, which are set by the server when initialising it.
+#[derive(Default)]
+struct SessionHeaders {
+    session_id: Option<HeaderValue>,
+    protocol_version: Option<HeaderValue>,
+}
+
+/// Messages for the transport's background task, from the tasks making HTTP requests.
+enum Event {
+    /// A message from the server, to be read from the transport.
+    Message(String),
+    /// The session has been initialised, so the server's stream of messages may be opened.
+    Initialized,
+}
+
+/// An HTTP session with the server, served by a background task.
+struct Session<C> {
+    client: Client<C, Full<Bytes>>,
+    uri: Uri,
+    headers: Mutex<SessionHeaders>,
+}
+
+impl<C> Session<C>
+where
+    C: Connect + Clone + Send + Sync + 'static,
+{
+    /// Send the messages written to the transport to the server, and write the server's messages
+    /// back, until the transport is dropped.
+    async fn run(self: Arc<Self>, stream: DuplexStream) {
+        let (read, write) = tokio::io::split(stream);
+        let mut lines = FramedRead::new(read, LinesCodec::new());
+        let mut writer = FramedWrite::new(write, LinesCodec::new());
+
+        // Requests (and the server's stream) are served by their own tasks, as a response may be
+        // streamed for as long as the request takes. They're aborted once the transport is dropped.
+        let mut tasks = JoinSet::new();
+        let (events_tx, mut events) = mpsc::unbounded_channel();
+
+        loop {
+            tokio::select! {
+                line = lines.next() => match line {
+                    Some(Ok(line)) => self.send(line, &events_tx, &mut tasks).await,
+                    Some(Err(e)) => {
+                        tracing::error!(error = ?e, "Error reading from HTTP transport");
+                        break;
+                    }
+                    None => break,
+                },
+                // The task holds a sender, so the channel never closes
+                Some(event) = events.recv() => match event {
+                    Event::Message(message) => {
+                        if let Err(e) = writer.send(message).await {
+                            tracing::error!(error = ?e, "Error writing to HTTP transport");
+                            break;
+                        }
+                    }
+                    Event::Initialized => {
+                        tasks.spawn(self.clone().listen(events_tx.clone()));
+                    }
+                },
+                Some(_) = tasks.join_next() => {}
+            }
+        }
+
+        tasks.shutdown().await;
+        self.end().await;
+    }
+
+    /// `POST` a message written to the transport to the server. Requests are handled in their own
+    /// task, so that their responses can be streamed concurrently. Other messages (notifications,
+    /// and responses to the server's requests) are only acknowledged, so are sent in order.
+    async fn send(
+        self: &Arc<Self>,
+        line: String,
+        events: &mpsc::UnboundedSender<Event>,
+        tasks: &mut JoinSet<()>,
+    ) {
+        let message = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                tracing::warn!(error = ?e, "Invalid JSON written to HTTP transport");
+                return;
+            }
+        };
+        let request_id = match (message.get("method"), message.get("id")) {
+            (Some(_), Some(id)) => serde_json::from_value::<RequestId>(id.clone()).ok(),
+            _ => None,
+        };
+
+        match request_id {
+            Some(id) => {
+                let initialize = message["method"] == "initialize";
+                let session = self.clone();
+                let events = events.clone();
+                tasks.spawn(async move { session.request(id, line, initialize, events).await });
+            }
+            None => {
+                let result = self.post(line).await;
+                match result {
+                    Ok(response) if response.status().is_success() => {}
+                    Ok(response) => tracing::warn!(
+                        status = %response.status(),
+                        "Server rejected message sent over HTTP"
+                    ),
+                    Err(e) => tracing::warn!(error = %e, "Error sending message over HTTP"),
+                }
+            }
+        }
+    }
+
+    /// `POST` a request to the server, and pass on the messages it responds with.
+    async fn request(
+        &self,
+        id: RequestId,
+        line: String,
+        initialize: bool,
+        events: mpsc::UnboundedSender<Event>,
+    ) {
+        let response = match self.post(line).await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) => {
+                let message = format!("The server responded with HTTP {}", response.status());
+                return fail(&events, id, message);
+            }
+            Err(e) => return fail(&events, id, e.to_string()),
+        };
+
+        if initialize {
+            let session_id = response.headers().get(SESSION_ID).cloned();
+            self.headers.lock().expect(POISONED).session_id = session_id;
+        }
+
+        // Pass on the messages in the response. Once the request's response is among them, the
+        // request is complete (though the server may carry on sending messages).
+        let mut responded = false;
+        let result = read_messages(response, |message| {
+            if let Some(response) = response_to(&message, &id) {
+                responded = true;
+                if let (true, ResponseItem::Success { result, .. }) = (initialize, response) {
+                    let version = result["protocolVersion"].as_str();
+                    let version = version.and_then(|version| HeaderValue::from_str(version).ok());
+                    self.headers.lock().expect(POISONED).protocol_version = version;
+                }
+            }
+            let _ = events.send(Event::Message(message));
+        })
+        .await;
+
+        let error = match result {
+            Ok(()) => "The server didn't respond to the request".to_string(),
+            Err(e) => format!("Error reading the server's response: {e}"),
+        };
+        if !responded {
+            fail(&events, id, error);
+        } else if initialize {
+            let _ = events.send(Event::Initialized);
+        }
+    }
+
+    /// Listen for messages the server sends outside of a response, if it offers a stream for them.
+    async fn listen(self: Arc<Self>, events: mpsc::UnboundedSender<Event>) {
+        let request = self
+            .request_builder(Method::GET)
+            .header(ACCEPT, EVENT_STREAM)
+            .body(Full::default())
+            .expect("request is valid");
+        let response = match self.client.request(request).await {
+            Ok(response) if response.status().is_success() => response,
+            Ok(response) if response.status() == StatusCode::METHOD_NOT_ALLOWED => {
+                tracing::debug!("Server doesn't offer a stream of messages over HTTP");
+                return;
+            }
+            Ok(response) => {
+                let status = response.status();
+                tracing::warn!(%status, "Server rejected request for a stream of messages");
+                return;
+            }
+            Err(e) => {
+                tracing::warn!(error = %e, "Error requesting a stream of messages over HTTP");
+                return;
+            }
+        };
+
+        let result = read_messages(response, |message| {
+            let _ = events.send(Event::Message(message));
+        })
+        .await;
+        if let Err(e) = result {
+            tracing::warn!(error = %e, "Error reading stream of messages over HTTP");
+        }
+    }
+
+    /// End the session, if the server assigned one.
+    async fn end(&self) {
+        if self.headers.lock().expect(POISONED).session_id.is_none() {
+            return;
+        }
+        let request = self
+            .request_builder(Method::DELETE)
+            .body(Full::default())
+            .expect("request is valid");
+        // The server may not allow clients to end sessions, in which case it'll expire the session
+        if let Err(e) = self.client.request(request).await {
+            tracing::debug!(error = %e, "Error ending HTTP session");
+        }
+    }
+
+    async fn post(
+        &self,
+        line: String,
+    ) -> Result<Response<Incoming>, hyper_util::client::legacy::Error> {
+        let request = self
+            .request_builder(Method::POST)
+            .header(CONTENT_TYPE, "application/json")
+            .header(ACCEPT, "application/json, text/event-stream")
+            .body(Full::new(Bytes::from(line)))
+            .expect("request is valid");
+        self.client.request(request).await
+    }
+
+    fn request_builder(&self, method: Method) -> hyper::http::request::Builder {
+        let mut builder = Request::builder().method(method).uri(self.uri.clone());
+        let headers = self.headers.lock().expect(POISONED);
+        if let Some(session_id) = &headers.session_id {
+            builder = builder.header(SESSION_ID, session_id);
+        }
+        if let Some(version) = &headers.protocol_version {
+            builder = builder.header(PROTOCOL_VERSION, version);
+        }
+        builder
+    }
+}
+
+/// Respond to a request with an error, as it couldn't be sent to the server.
+fn fail(events: &mpsc::UnboundedSender<Event>, id: RequestId, message: String) {
+    tracing::warn!(?id, message, "Error sending request over HTTP");
+    let error = ErrorData::new(ErrorCode::InternalError, message);
+    let response = ResponseItem::error(id, error);
+    let line = serde_json::to_string(&response).expect("response is valid JSON");
+    let _ = events.send(Event::Message(line));
+}
+
+/// Parse `message` as the response to the request with the given ID, if it is one.
+fn response_to(message: &str, id: &RequestId) -> Option<ResponseItem> {
+    let response = serde_json::from_str::<ResponseItem>(message).ok()?;
+    match &response {
+        ResponseItem::Success {
+            id: response_id, ..
+        }
+        | ResponseItem::Error {
+            id: response_id, ..
+        } if response_id == id => Some(response),
+        _ => None,
+    }
+}
+
+/// Read the messages in a response's body, which is either a single JSON message (or batch), or a
+/// stream of server-sent events. Each message is passed to `on_message` as it arrives.
+async fn read_messages<F>(
+    response: Response<Incoming>,
+    mut on_message: F,
+) -> Result<(), hyper::Error>
+where
+    F: FnMut(String),
+{
+    let is_stream = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with(EVENT_STREAM));
+    let mut body = response.into_body();
+
+    if !is_stream {
+        let body = body.collect().await?.to_bytes();
+        // eg an empty body with 202 Accepted
+        if body.is_empty() {
+            return Ok(());
+        }
+        split_messages(&body, &mut on_message);
+        return Ok(());
+    }
+
+    let mut events = EventStream::default();
+    while let Some(frame) = body.frame().await {
+        if let Ok(data) = frame?.into_data() {
+            for event in events.push(&data) {
+                split_messages(event.as_bytes(), &mut on_message);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Pass each message in a JSON body (a single message or a batch) to `on_message`, serialised
+/// compactly. The messages are handled as lines, so mustn't span several (as an event's data may).
+fn split_messages(json: &[u8], on_message: &mut impl FnMut(String)) {
+    match serde_json::from_slice::<Value>(json) {
+        Ok(Value::Array(messages)) => messages
+            .iter()
+            .for_each(|message| on_message(message.to_string())),
+        Ok(message) => on_message(message.to_string()),
+        Err(e) => tracing::warn!(error = ?e, "Received invalid JSON over HTTP"),
+    }
+}
+
+/// A parser for a stream of [server-sent events], which yields the data of each event. Other
+/// fields (event types, IDs and retry times) aren't used by MCP servers' streams, so are ignored.
+///
+/// [server-sent events]: https://html.spec.whatwg.org/multipage/server-sent-events.html
+#[derive(Default)]
+struct EventStream {
+    /// Bytes received which don't yet form a complete line.
+    buffer: Vec<u8>,
+    /// Data lines of the event being received.
+    data: Vec<String>,
+}
+
+impl EventStream {
+    /// Add bytes received from the stream, returning the data of any events they complete.
+    fn push(&mut self, bytes: &[u8]) -> Vec<String> {
+        self.buffer.extend_from_slice(bytes);
+        let mut events = Vec::new();
+
+        while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+            let line: Vec<u8> = self.buffer.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            let line = line.trim_end_matches(['\n', '\r']);
+
+            if line.is_empty() {
+                // A blank line ends the event
+                if !self.data.is_empty() {
+                    events.push(self.data.join("\n"));
+                    self.data.clear();
+                }
+            } else if let Some(data) = line.strip_prefix("data") {
+                // Either `data` alone, or `data:` followed by an optional space
+                if let Some(data) = data.strip_prefix(':') {
+                    self.data
+                        .push(data.strip_prefix(' ').unwrap_or(data).to_string());
+                } else if data.is_empty() {
+                    self.data.push(String::new());
+                }
+            }
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_stream() {
+        let mut events = EventStream::default();
+        assert!(events
+            .push(b"event: message\nid: 1\ndata: {\"a\":")
+            .is_empty());
+        assert_eq!(events.push(b" 1}\r\n\r\n"), vec!["{\"a\": 1}"]);
+
+        // Comments and empty events are skipped; data lines are joined
+        assert_eq!(
+            events.push(b": keep-alive\n\ndata:first\ndata: second\n\n"),
+            vec!["first\nsecond"]
+        );
+    }
+
+    #[test]
+    fn test_multi_line_event() {
+        let mut events = EventStream::default();
+        let mut messages = Vec::new();
+        let stream = concat!(
+            "event: message\ndata: {\ndata:   \"jsonrpc\": \"2.0\",\ndata:   \"method\": \"ping\"\ndata: }\n\n",
+            "data: [{\"jsonrpc\": \"2.0\",\ndata: \"method\": \"ping\"}]\n\n",
+        );
+        for event in events.push(stream.as_bytes()) {
+            split_messages(event.as_bytes(), &mut |message| messages.push(message));
+        }
+
+        // Each message is a single line
+        assert_eq!(messages, vec![r#"{"jsonrpc":"2.0","method":"ping"}"#; 2]);
+    }
+}
@@ -0,0 +1,326 @@
+//! A typed client for [Model Context Protocol][mcp-spec] (MCP) servers.
+//!
+//! A [`Client`] connects to a server over any transport implementing `AsyncRead + AsyncWrite`,
+//! carrying newline-delimited JSON-RPC messages (such as kuri's [`ChildProcessTransport`], which
+//! talks to a server over a child process's stdio). Servers using the streamable HTTP transport
+//! are connected to with an [`HttpTransport`]. Requests and their results use the types from
+//! [`kuri_mcp_protocol`].
+//!
+//! ```rust,no_run
+//! use kuri::transport::ChildProcessTransport;
+//! use kuri_client::Client;
+//! use serde_json::json;
+//! use tokio::process::Command;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let transport = ChildProcessTransport::spawn(Command::new("my-mcp-server"))?;
+//!     let client = Client::connect(transport);
+//!     let server = client.initialize().await?;
+//!     println!("Connected to {}", server.server_info.name);
+//!
+//!     for tool in client.list_tools().await?.tools {
+//!         println!("{}: {}", tool.name, tool.description);
+//!     }
+//!     let result = client.call_tool("calculator", json!({ "x": 1, "y": 2 })).await?;
+//!     println!("{:?}", result.content);
+//!     Ok(())
+//! }
+//! ```
+//!
+//! # Server requests and notifications
+//!
+//! Servers may send requests to the client, such as to sample from an LLM, or list the client's
+//! roots. Register callbacks to answer them with a [`ClientBuilder`]. The client advertises the
+//! corresponding capabilities when initialising the connection. Requests without a callback are
+//! rejected.
+//!
+//! Notifications from the server (such as log messages, or changes to its list of tools) are
+//! delivered as a [`Stream`], obtained with [`Client::notifications`]. The progress of a request
+//! sent with [`Client::request_with_progress`] is passed to its own callback instead.
+//!
+//! [mcp-spec]: https://modelcontextprotocol.io/specification/2025-03-26/
+//! [`Stream`]: futures::Stream
+//! [`ChildProcessTransport`]: https://docs.rs/kuri/latest/kuri/transport/struct.ChildProcessTransport.html
+
+mod connection;
+mod http;
+
+use connection::Connection;
+use futures::Stream;
+pub use http::HttpTransport;
+use kuri_mcp_protocol::{
+    jsonrpc::{ErrorData, Notification, Params},
+    messages::{
+        CallToolResult, ClientCapabilities, ClientInfo, CreateMessageParams, CreateMessageResult,
+        GetPromptRequest, GetPromptResult, InitializeParams, InitializeResult, ListPromptsResult,
+        ListResourcesResult, ListRootsResult, ListToolsResult, ReadResourceResult, RootsCapability,
+        LATEST_PROTOCOL_VERSION,
+    },
+};
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    future::Future,
+    pin::Pin,
+    sync::Mutex,
+    task::{Context, Poll},
+};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc,
+};
+
+/// Errors raised when communicating with a server.
+#[derive(Error, Debug)]
+pub enum ClientError {
+    #[error("The connection to the server is closed")]
+    Disconnected,
+
+    #[error("The server returned an error: {}", .0.message)]
+    Response(ErrorData),
+
+    /// A request's params weren't an object or array, so couldn't be sent.
+    #[error("Invalid params: {0}")]
+    InvalidParams(String),
+
+    #[error("JSON serialisation error: {0}")]
+    Serialisation(#[from] serde_json::Error),
+}
+
+type BoxFuture<T> = Pin<Box<dyn Future<Output = T> + Send>>;
+type SamplingCallback = Box<
+    dyn Fn(CreateMessageParams) -> BoxFuture<Result<CreateMessageResult, ErrorData>> + Send + Sync,
+>;
+type RootsCallback = Box<dyn Fn() -> BoxFuture<Result<ListRootsResult, ErrorData>> + Send + Sync>;
+
+/// Callbacks answering requests from the server.
+#[derive(Default)]
+pub(crate) struct Callbacks {
+    pub(crate) sampling: Option<SamplingCallback>,
+    pub(crate) roots: Option<RootsCallback>,
+}
+
+/// Build a [`Client`], with callbacks for requests from the server.
+pub struct ClientBuilder {
+    name: String,
+    version: String,
+    callbacks: Callbacks,
+}
+
+impl ClientBuilder {
+    pub fn new(name: String) -> Self {
+        Self {
+            name,
+            version: "0.1.0".to_string(),
+            callbacks: Callbacks::default(),
+        }
+    }
+
+    pub fn with_version(mut self, version: String) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Answer the server's `sampling/createMessage` requests, which ask the client to sample from
+    /// an LLM.
+    pub fn with_sampling<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: Fn(CreateMessageParams) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<CreateMessageResult, ErrorData>> + Send + 'static,
+    {
+        self.callbacks.sampling = Some(Box::new(move |params| Box::pin(callback(params))));
+        self
+    }
+
+    /// Answer the server's `roots/list` requests. If the roots change, let the server know with
+    /// [`Client::notify_roots_list_changed`].
+    pub fn with_roots<F, Fut>(mut self, callback: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ListRootsResult, ErrorData>> + Send + 'static,
+    {
+        self.callbacks.roots = Some(Box::new(move || Box::pin(callback())));
+        self
+    }
+
+    /// Connect to a server over `transport`. The connection must be initialised with
+    /// [`Client::initialize`] before use.
+    ///
+    /// Messages are read and written by a background task, so this must be called within a tokio
+    /// runtime.
+    pub fn connect<T>(self, transport: T) -> Client
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let capabilities = ClientCapabilities {
+            experimental: None,
+            roots: self.callbacks.roots.as_ref().map(|_| RootsCapability {
+                list_changed: Some(true),
+            }),
+            sampling: self.callbacks.sampling.as_ref().map(|_| json!({})),
+        };
+        let (notifications_tx, notifications) = mpsc::unbounded_channel();
+
+        Client {
+            connection: Connection::start(transport, self.callbacks, notifications_tx),
+            client_info: ClientInfo {
+                name: self.name,
+                version: self.version,
+            },
+            capabilities,
+            notifications: Mutex::new(Some(Notifications { rx: notifications })),
+        }
+    }
+}
+
+/// A connection to an MCP server. See the [crate documentation](crate) for an example.
+///
+/// Requests may be sent concurrently; responses are matched to their request by ID. The connection
+/// is closed when the client is dropped.
+pub struct Client {
+    connection: Connection,
+    client_info: ClientInfo,
+    capabilities: ClientCapabilities,
+    notifications: Mutex<Option<Notifications>>,
+}
+
+impl Client {
+    /// Connect to a server over `transport`, with no callbacks for requests from the server. Use a
+    /// [`ClientBuilder`] to register them.
+    pub fn connect<T>(transport: T) -> Self
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        ClientBuilder::new(env!("CARGO_PKG_NAME").to_string())
+            .with_version(env!("CARGO_PKG_VERSION").to_string())
+            .connect(transport)
+    }
+
+    /// Initialise the connection, returning the server's info and capabilities.
+    pub async fn initialize(&self) -> Result<InitializeResult, ClientError> {
+        let params = InitializeParams {
+            protocol_version: LATEST_PROTOCOL_VERSION.to_string(),
+            capabilities: self.capabilities.clone(),
+            client_info: self.client_info.clone(),
+        };
+        let result = self.request_typed("initialize", params).await?;
+        self.notify("notifications/initialized", None)?;
+        Ok(result)
+    }
+
+    pub async fn list_tools(&self) -> Result<ListToolsResult, ClientError> {
+        self.request_typed("tools/list", json!({})).await
+    }
+
+    /// Call a tool. A tool that fails returns a result with `is_error` set, rather than an error.
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, ClientError> {
+        self.request_typed(
+            "tools/call",
+            json!({ "name": name, "arguments": arguments }),
+        )
+        .await
+    }
+
+    pub async fn list_prompts(&self) -> Result<ListPromptsResult, ClientError> {
+        self.request_typed("prompts/list", json!({})).await
+    }
+
+    pub async fn get_prompt(
+        &self,
+        name: &str,
+        arguments: HashMap<String, String>,
+    ) -> Result<GetPromptResult, ClientError> {
+        let params = GetPromptRequest {
+            name: name.to_string(),
+            arguments: Some(arguments),
+        };
+        self.request_typed("prompts/get", params).await
+    }
+
+    pub async fn list_resources(&self) -> Result<ListResourcesResult, ClientError> {
+        self.request_typed("resources/list", json!({})).await
+    }
+
+    pub async fn read_resource(&self, uri: &str) -> Result<ReadResourceResult, ClientError> {
+        self.request_typed("resources/read", json!({ "uri": uri }))
+            .await
+    }
+
+    /// Let the server know that the client's roots have changed.
+    pub fn notify_roots_list_changed(&self) -> Result<(), ClientError> {
+        self.notify("notifications/roots/list_changed", None)
+    }
+
+    /// Send a request to the server, and wait for its result. Prefer the typed methods (such as
+    /// [`Client::call_tool`]) where available.
+    ///
+    /// `params` must be an object or array, or null for none.
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value, ClientError> {
+        self.connection.request(method, params, None).await
+    }
+
+    /// Send a request to the server asking it to report progress, and wait for its result. The
+    /// request's `_meta.progressToken` is set, and each `notifications/progress` for it is passed
+    /// to `on_progress` (rather than the [notification stream]) as it arrives, before the result.
+    ///
+    /// `params` must be an object, or null for none.
+    ///
+    /// [notification stream]: Client::notifications
+    pub async fn request_with_progress<F>(
+        &self,
+        method: &str,
+        params: Value,
+        on_progress: F,
+    ) -> Result<Value, ClientError>
+    where
+        F: Fn(Notification) + Send + Sync + 'static,
+    {
+        self.connection
+            .request(method, params, Some(Box::new(on_progress)))
+            .await
+    }
+
+    /// Send a notification to the server.
+    pub fn notify(&self, method: &str, params: Option<Params>) -> Result<(), ClientError> {
+        self.connection.notify(method, params)
+    }
+
+    /// The stream of notifications from the server, which ends once the connection closes.
+    ///
+    /// There's a single stream per client, so this returns `None` if it has already been taken.
+    /// Notifications received beforehand are buffered.
+    pub fn notifications(&self) -> Option<Notifications> {
+        self.notifications
+            .lock()
+            .expect("notifications lock poisoned")
+            .take()
+    }
+
+    async fn request_typed<P, R>(&self, method: &str, params: P) -> Result<R, ClientError>
+    where
+        P: serde::Serialize,
+        R: serde::de::DeserializeOwned,
+    {
+        let result = self.request(method, serde_json::to_value(params)?).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+}
+
+/// A stream of notifications from the server. See [`Client::notifications`].
+pub struct Notifications {
+    rx: mpsc::UnboundedReceiver<Notification>,
+}
+
+impl Stream for Notifications {
+    type Item = Notification;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.rx.poll_recv(cx)
+    }
+}
@@ -0,0 +1,392 @@
+use futures::StreamExt;
+use kuri::{
+    extract::{Meta, Peer},
+    prompt, serve, tool, MCPService, MCPServiceBuilder, ServiceExt,
+};
+use kuri_client::{Client, ClientBuilder, ClientError};
+use kuri_mcp_protocol::{
+    content::{Content, Role},
+    jsonrpc::{ErrorCode, ErrorData},
+    messages::{CreateMessageResult, ListRootsResult, Root},
+};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tracing_subscriber::EnvFilter;
+
+// Client tests, against a kuri server, and a hand-written server for server-initiated requests
+
+#[tokio::test]
+async fn test_client_requests() {
+    init_tracing();
+    let (client, transport) = tokio::io::duplex(4096);
+
+    let test = async {
+        let client = Client::connect(client);
+        let result = client.initialize().await.unwrap();
+        assert_eq!(result.server_info.name, "Calculator");
+        assert!(result.capabilities.tools.is_some());
+
+        let tools = client.list_tools().await.unwrap().tools;
+        let mut names: Vec<_> = tools.iter().map(|tool| tool.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, ["add", "count", "divide"]);
+
+        let result = client
+            .call_tool("add", json!({ "x": 1, "y": 2 }))
+            .await
+            .unwrap();
+        assert_eq!(result.content, vec![Content::text("3")]);
+        assert!(!result.is_error);
+
+        // Tool errors are results, not client errors
+        let result = client
+            .call_tool("divide", json!({ "x": 1, "y": 0 }))
+            .await
+            .unwrap();
+        assert!(result.is_error);
+
+        let prompts = client.list_prompts().await.unwrap().prompts;
+        assert_eq!(prompts[0].name, "explain");
+        let result = client
+            .get_prompt(
+                "explain",
+                HashMap::from([("topic".to_string(), "addition".to_string())]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.messages.len(), 1);
+
+        let resources = client.list_resources().await.unwrap().resources;
+        assert!(resources.is_empty());
+
+        // Protocol errors are client errors
+        match client.call_tool("subtract", json!({})).await {
+            Err(ClientError::Response(error)) => assert_eq!(error.code, ErrorCode::InvalidParams),
+            other => panic!("Expected an error response, got {other:?}"),
+        }
+        match client.read_resource("file:///missing.txt").await {
+            Err(ClientError::Response(_)) => {}
+            other => panic!("Expected an error response, got {other:?}"),
+        }
+
+        // Params that aren't an object or array can't be sent
+        match client.request("tools/list", json!("all")).await {
+            Err(e @ ClientError::InvalidParams(_)) => assert_eq!(
+                e.to_string(),
+                "Invalid params: JSON-RPC params must be either an array or object, got String(\"all\")"
+            ),
+            other => panic!("Expected an invalid params error, got {other:?}"),
+        }
+    };
+
+    let (result, _) = tokio::join!(serve(server().into_request_service(), transport), test);
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_concurrent_requests() {
+    init_tracing();
+    let (client, transport) = tokio::io::duplex(4096);
+
+    let test = async {
+        let client = Client::connect(client);
+        client.initialize().await.unwrap();
+
+        // Responses are matched to their requests by ID
+        let (a, b, c) = tokio::join!(
+            client.call_tool("add", json!({ "x": 1, "y": 1 })),
+            client.call_tool("add", json!({ "x": 2, "y": 2 })),
+            client.call_tool("add", json!({ "x": 3, "y": 3 })),
+        );
+        assert_eq!(a.unwrap().content, vec![Content::text("2")]);
+        assert_eq!(b.unwrap().content, vec![Content::text("4")]);
+        assert_eq!(c.unwrap().content, vec![Content::text("6")]);
+    };
+
+    let (result, _) = tokio::join!(serve(server().into_request_service(), transport), test);
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_notifications() {
+    init_tracing();
+    let (client, transport) = tokio::io::duplex(4096);
+
+    let test = async {
+        let client = Client::connect(client);
+        let mut notifications = client.notifications().unwrap();
+        assert!(client.notifications().is_none());
+        client.initialize().await.unwrap();
+
+        client
+            .call_tool("add", json!({ "x": 1, "y": 2 }))
+            .await
+            .unwrap();
+        let notification = notifications.next().await.unwrap();
+        assert_eq!(notification.method, "notifications/message");
+        assert_eq!(
+            serde_json::to_value(notification.params).unwrap(),
+            json!({ "level": "info", "data": "Adding 1 and 2" })
+        );
+
+        // The stream ends once the connection closes
+        drop(client);
+        assert!(notifications.next().await.is_none());
+    };
+
+    let (result, _) = tokio::join!(serve(server().into_request_service(), transport), test);
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_progress() {
+    init_tracing();
+    let (client, transport) = tokio::io::duplex(4096);
+
+    let test = async {
+        let client = Client::connect(client);
+        let mut notifications = client.notifications().unwrap();
+        client.initialize().await.unwrap();
+
+        // Progress is passed to the request's callback before the result, rather than the stream
+        let (progress_tx, mut progress) = tokio::sync::mpsc::unbounded_channel();
+        let result = client
+            .request_with_progress(
+                "tools/call",
+                json!({ "name": "count", "arguments": { "to": 2 } }),
+                move |notification| progress_tx.send(notification).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result["content"][0]["text"], "Counted to 2");
+        for n in 1..=2 {
+            let notification = progress.try_recv().unwrap();
+            assert_eq!(notification.method, "notifications/progress");
+            assert_eq!(
+                serde_json::to_value(notification.params).unwrap(),
+                json!({ "progressToken": 2, "progress": n, "total": 2 })
+            );
+        }
+
+        drop(client);
+        assert!(notifications.next().await.is_none());
+    };
+
+    let (result, _) = tokio::join!(serve(server().into_request_service(), transport), test);
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn test_client_server_disconnects() {
+    init_tracing();
+    let (client, transport) = tokio::io::duplex(4096);
+    let client = Client::connect(client);
+    drop(transport);
+
+    assert!(matches!(
+        client.initialize().await,
+        Err(ClientError::Disconnected)
+    ));
+    assert!(client.notifications().unwrap().next().await.is_none());
+}
+
+#[tokio::test]
+async fn test_client_server_requests() {
+    init_tracing();
+    let (client, server) = tokio::io::duplex(4096);
+
+    let client = ClientBuilder::new("TestClient".to_string())
+        .with_version("1.2.3".to_string())
+        .with_sampling(|params| async move {
+            let Content::Text(prompt) = &params.messages[0].content else {
+                return Err(ErrorData::new(
+                    ErrorCode::InvalidParams,
+                    "Expected text".to_string(),
+                ));
+            };
+            Ok(CreateMessageResult {
+                role: Role::Assistant,
+                content: Content::text(format!("You said: {}", prompt.text)),
+                model: "echo".to_string(),
+                stop_reason: Some("endTurn".to_string()),
+            })
+        })
+        .with_roots(|| async {
+            Ok(ListRootsResult {
+                roots: vec![Root {
+                    uri: "file:///home/user/project".to_string(),
+                    name: Some("Project".to_string()),
+                }],
+            })
+        })
+        .connect(client);
+    let mut server = FakeServer::new(server);
+
+    let initialize = async {
+        client.initialize().await.unwrap();
+    };
+    let serve_initialize = async {
+        let request = server.next_message().await;
+        assert_eq!(request["method"], "initialize");
+        assert_eq!(
+            request["params"]["clientInfo"],
+            json!({ "name": "TestClient", "version": "1.2.3" })
+        );
+        assert_eq!(
+            request["params"]["capabilities"],
+            json!({ "roots": { "listChanged": true }, "sampling": {} })
+        );
+        server
+            .send(json!({
+                "jsonrpc": "2.0",
+                "id": request["id"],
+                "result": {
+                    "protocolVersion": "2024-11-05",
+                    "capabilities": {},
+                    "serverInfo": { "name": "FakeServer", "version": "1.0.0" }
+                }
+            }))
+            .await;
+        assert_eq!(
+            server.next_message().await,
+            json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })
+        );
+    };
+    tokio::join!(initialize, serve_initialize);
+
+    let response = server
+        .call(
+            "sampling/createMessage",
+            json!({
+                "messages": [{ "role": "user", "content": { "type": "text", "text": "Hello" } }],
+                "maxTokens": 100
+            }),
+        )
+        .await;
+    assert_eq!(
+        response["result"],
+        json!({
+            "role": "assistant",
+            "content": { "type": "text", "text": "You said: Hello" },
+            "model": "echo",
+            "stopReason": "endTurn"
+        })
+    );
+
+    let response = server.call("roots/list", json!({})).await;
+    assert_eq!(
+        response["result"]["roots"],
+        json!([{ "uri": "file:///home/user/project", "name": "Project" }])
+    );
+
+    let response = server.call("ping", json!({})).await;
+    assert_eq!(response["result"], json!({}));
+
+    // Invalid params, and requests the client doesn't support, are rejected
+    let response = server
+        .call("sampling/createMessage", json!({ "messages": [] }))
+        .await;
+    assert_eq!(response["error"]["code"], -32602);
+    let response = server.call("elicitation/create", json!({})).await;
+    assert_eq!(response["error"]["code"], -32601);
+
+    client.notify_roots_list_changed().unwrap();
+    assert_eq!(
+        server.next_message().await,
+        json!({ "jsonrpc": "2.0", "method": "notifications/roots/list_changed" })
+    );
+}
+
+#[tool(description = "Add two numbers")]
+async fn add(peer: Peer, x: i32, y: i32) -> i32 {
+    peer.notify_with(
+        "notifications/message",
+        Some(json!({ "level": "info", "data": format!("Adding {x} and {y}") })),
+    )
+    .unwrap();
+    x + y
+}
+
+#[tool(description = "Divide two numbers")]
+async fn divide(x: i32, y: i32) -> Result<i32, kuri_mcp_protocol::tool::ToolError> {
+    if y == 0 {
+        return Err(kuri_mcp_protocol::tool::ToolError::ExecutionError(
+            "Division by zero".to_string(),
+        ));
+    }
+    Ok(x / y)
+}
+
+#[tool(description = "Count up, reporting progress")]
+async fn count(peer: Peer, meta: Meta, to: u32) -> String {
+    for n in 1..=to {
+        peer.notify_with(
+            "notifications/progress",
+            Some(json!({ "progressToken": meta.get("progressToken"), "progress": n, "total": to })),
+        )
+        .unwrap();
+    }
+    format!("Counted to {to}")
+}
+
+#[prompt(description = "Explain a topic", params(topic = "What to explain"))]
+async fn explain(topic: String) -> String {
+    format!("Explain {topic}")
+}
+
+fn server() -> MCPService {
+    MCPServiceBuilder::new("Calculator".to_string())
+        .with_tool(Add)
+        .with_tool(Divide)
+        .with_tool(Count)
+        .with_prompt(Explain)
+        .build()
+}
+
+fn init_tracing() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+}
+
+/// The server's end of a connection, sending requests to the client.
+struct FakeServer {
+    lines: tokio::io::Lines<BufReader<tokio::io::ReadHalf<DuplexStream>>>,
+    write: tokio::io::WriteHalf<DuplexStream>,
+    next_id: u64,
+}
+
+impl FakeServer {
+    fn new(stream: DuplexStream) -> Self {
+        let (read, write) = tokio::io::split(stream);
+        Self {
+            lines: BufReader::new(read).lines(),
+            write,
+            next_id: 1,
+        }
+    }
+
+    async fn next_message(&mut self) -> Value {
+        let line = self.lines.next_line().await.unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    async fn send(&mut self, message: Value) {
+        self.write
+            .write_all(format!("{message}\n").as_bytes())
+            .await
+            .unwrap();
+    }
+
+    async fn call(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await;
+        let response = self.next_message().await;
+        assert_eq!(response["id"], id);
+        response
+    }
+}
@@ -0,0 +1,256 @@
+use bytes::Bytes;
+use futures::{stream, StreamExt};
+use http_body_util::{combinators::BoxBody, BodyExt, Full, StreamBody};
+use hyper::{
+    body::{Frame, Incoming},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode, Uri,
+};
+use hyper_util::rt::TokioIo;
+use kuri_client::{Client, ClientError, HttpTransport};
+use kuri_mcp_protocol::jsonrpc::ErrorCode;
+use serde_json::{json, Value};
+use std::{
+    convert::Infallible,
+    sync::{Arc, Mutex},
+};
+use tokio::{net::TcpListener, sync::mpsc};
+use tracing_subscriber::EnvFilter;
+
+// HTTP transport tests, against a hand-written streamable HTTP server
+
+#[tokio::test]
+async fn test_http_session() {
+    init_tracing();
+    let (server, mut log, stream) = FakeServer::new();
+    let client = Client::connect(HttpTransport::new(server.start().await));
+    let mut notifications = client.notifications().unwrap();
+
+    let result = client.initialize().await.unwrap();
+    assert_eq!(result.server_info.name, "HTTP server");
+
+    // Messages streamed in the response precede the result
+    let (progress_tx, mut progress) = mpsc::unbounded_channel();
+    let result = client
+        .request_with_progress(
+            "tools/call",
+            json!({ "name": "count", "arguments": {} }),
+            move |notification| progress_tx.send(notification).unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(result["content"][0]["text"], "Counted");
+    let notification = progress.try_recv().unwrap();
+    assert_eq!(
+        serde_json::to_value(notification.params).unwrap(),
+        json!({ "progressToken": 2, "progress": 1 })
+    );
+
+    // Messages sent outside of a response arrive through the session's stream
+    let list_changed = json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" });
+    stream.send(list_changed).unwrap();
+    let notification = notifications.next().await.unwrap();
+    assert_eq!(notification.method, "notifications/tools/list_changed");
+
+    // HTTP errors are error responses
+    match client.list_tools().await {
+        Err(ClientError::Response(error)) => {
+            assert_eq!(error.code, ErrorCode::InternalError);
+            assert_eq!(
+                error.message,
+                "The server responded with HTTP 500 Internal Server Error"
+            );
+        }
+        other => panic!("Expected an error response, got {other:?}"),
+    }
+
+    // The session is ended once the client is dropped
+    drop(client);
+    let mut messages = Vec::new();
+    while let Some(message) = log.recv().await {
+        let end = message.starts_with("DELETE");
+        messages.push(message);
+        if end {
+            break;
+        }
+    }
+    // The reply to the server's ping is sent by its own task, so may arrive at any point after it
+    let ping = messages
+        .iter()
+        .position(|message| message.starts_with("response"));
+    assert!(ping.is_some_and(|ping| ping > 2), "{messages:?}");
+    messages.retain(|message| !message.starts_with("response"));
+    assert_eq!(
+        messages,
+        [
+            "initialize (session: None, version: None)",
+            "notifications/initialized (session: Some(\"session-1\"), version: Some(\"2025-06-18\"))",
+            "tools/call (session: Some(\"session-1\"), version: Some(\"2025-06-18\"))",
+            "tools/list (session: Some(\"session-1\"), version: Some(\"2025-06-18\"))",
+            "DELETE (session: Some(\"session-1\"), version: Some(\"2025-06-18\"))",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_http_server_unreachable() {
+    init_tracing();
+    // Bind a port, then free it, so that nothing is listening on it
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let uri = format!("http://{}/mcp", listener.local_addr().unwrap());
+    drop(listener);
+
+    let client = Client::connect(HttpTransport::new(uri.parse().unwrap()));
+    match client.initialize().await {
+        Err(ClientError::Response(error)) => assert_eq!(error.code, ErrorCode::InternalError),
+        other => panic!("Expected an error response, got {other:?}"),
+    }
+}
+
+fn init_tracing() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+}
+
+type Body = BoxBody<Bytes, Infallible>;
+
+/// A streamable HTTP server, which logs the messages it's sent (along with their session headers)
+/// and the end of the session.
+#[derive(Clone)]
+struct FakeServer {
+    log: mpsc::UnboundedSender<String>,
+    /// Messages to send through the session's stream, once the client opens it.
+    stream: Arc<Mutex<Option<mpsc::UnboundedReceiver<Value>>>>,
+}
+
+impl FakeServer {
+    fn new() -> (
+        Self,
+        mpsc::UnboundedReceiver<String>,
+        mpsc::UnboundedSender<Value>,
+    ) {
+        let (log, log_rx) = mpsc::unbounded_channel();
+        let (stream_tx, stream) = mpsc::unbounded_channel();
+        let server = Self {
+            log,
+            stream: Arc::new(Mutex::new(Some(stream))),
+        };
+        (server, log_rx, stream_tx)
+    }
+
+    /// Serve HTTP connections on a local port, returning the URI of the MCP endpoint.
+    async fn start(self) -> Uri {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let uri = format!("http://{}/mcp", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let server = self.clone();
+                let service = service_fn(move |request| server.clone().handle(request));
+                tokio::spawn(http1::Builder::new().serve_connection(TokioIo::new(stream), service));
+            }
+        });
+        uri.parse().unwrap()
+    }
+
+    async fn handle(self, request: Request<Incoming>) -> Result<Response<Body>, Infallible> {
+        let header = |name| {
+            request
+                .headers()
+                .get(name)
+                .map(|value| value.to_str().unwrap().to_string())
+        };
+        let headers = format!(
+            "(session: {:?}, version: {:?})",
+            header("mcp-session-id"),
+            header("mcp-protocol-version")
+        );
+        let in_session = header("mcp-session-id").as_deref() == Some("session-1");
+
+        match *request.method() {
+            Method::GET => match self.stream.lock().unwrap().take() {
+                Some(messages) if in_session => {
+                    let events = stream::unfold(messages, |mut messages| async move {
+                        let message = messages.recv().await?;
+                        Some((event(&message), messages))
+                    });
+                    Ok(sse(BodyExt::boxed(StreamBody::new(events.map(Ok)))))
+                }
+                _ => Ok(status(StatusCode::METHOD_NOT_ALLOWED)),
+            },
+            Method::DELETE => {
+                self.log.send(format!("DELETE {headers}")).unwrap();
+                Ok(status(StatusCode::OK))
+            }
+            Method::POST => {
+                let body = request.into_body().collect().await.unwrap().to_bytes();
+                let message: Value = serde_json::from_slice(&body).unwrap();
+                let method = message["method"].as_str().unwrap_or("response");
+                self.log.send(format!("{method} {headers}")).unwrap();
+                Ok(Self::respond(method, &message, in_session))
+            }
+            _ => Ok(status(StatusCode::METHOD_NOT_ALLOWED)),
+        }
+    }
+
+    fn respond(method: &str, message: &Value, in_session: bool) -> Response<Body> {
+        let id = &message["id"];
+        match method {
+            "initialize" => {
+                let result = json!({
+                    "protocolVersion": "2025-06-18",
+                    "capabilities": { "tools": {} },
+                    "serverInfo": { "name": "HTTP server", "version": "1.0.0" }
+                });
+                let response = json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                let mut response = Response::new(Full::from(response.to_string()).boxed());
+                let headers = response.headers_mut();
+                headers.insert("content-type", "application/json".parse().unwrap());
+                headers.insert("mcp-session-id", "session-1".parse().unwrap());
+                response
+            }
+            _ if !in_session => status(StatusCode::BAD_REQUEST),
+            // Notifications and responses are only acknowledged
+            _ if id.is_null() || message.get("method").is_none() => status(StatusCode::ACCEPTED),
+            "tools/call" => {
+                let token = &message["params"]["_meta"]["progressToken"];
+                let progress = json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/progress",
+                    "params": { "progressToken": token, "progress": 1 }
+                });
+                let ping = json!({ "jsonrpc": "2.0", "id": "ping-1", "method": "ping" });
+                let result = json!({
+                    "jsonrpc": "2.0",
+                    "id": id,
+                    "result": { "content": [{ "type": "text", "text": "Counted" }] }
+                });
+                let events = [progress, ping, result].map(|message| Ok(event(&message)));
+                sse(BodyExt::boxed(StreamBody::new(stream::iter(events))))
+            }
+            _ => status(StatusCode::INTERNAL_SERVER_ERROR),
+        }
+    }
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Full::default().boxed());
+    *response.status_mut() = status;
+    response
+}
+
+fn sse(body: Body) -> Response<Body> {
+    let mut response = Response::new(body);
+    let headers = response.headers_mut();
+    headers.insert("content-type", "text/event-stream".parse().unwrap());
+    response
+}
+
+/// An SSE event with the message as its data, pretty-printed so that it spans several data lines.
+fn event(message: &Value) -> Frame<Bytes> {
+    let json = serde_json::to_string_pretty(message).unwrap();
+    let data: String = json.lines().map(|line| format!("data: {line}\n")).collect();
+    Frame::data(Bytes::from(format!("event: message\n{data}\n")))
+}
@@ -0,0 +1,393 @@
+//! An MCP gateway, which serves the tools, prompts and resources of many downstream MCP servers
+//! through a single kuri service.
+//!
+//! The [`Gateway`] connects to each downstream server as a client, and adds its tools, prompts and
+//! resources to the [`Registry`] of a kuri [`MCPService`]. Calls to them are forwarded to the
+//! downstream server. The names of a server's tools and prompts are prefixed with a namespace, so
+//! that servers with similarly named tools can be served side by side. Resources are identified by
+//! URI, so aren't namespaced. A handler whose name (or URI) is already taken, by the service's own
+//! handlers or another server's, isn't served, and a warning is logged.
+//!
+//! While running, the gateway relays notifications from downstream servers to its clients, and
+//! keeps its lists of tools, prompts and resources in sync with theirs (notifying its clients of
+//! changes). Progress notifications go only to the client whose request they're about; others
+//! (such as log messages) go to every client.
+//!
+//! ```rust,no_run
+//! use kuri::transport::{ChildProcessTransport, StdioTransport};
+//! use kuri::{serve, MCPServiceBuilder, ServiceExt};
+//! use kuri_client::HttpTransport;
+//! use kuri_gateway::Gateway;
+//! use tokio::process::Command;
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let service = MCPServiceBuilder::new("Gateway".to_string()).build();
+//!     let mut gateway = Gateway::new(service.registry());
+//!
+//!     // Connect to a downstream server over a child process's stdio. Its tools are served as
+//!     // `files_<name>`.
+//!     let transport = ChildProcessTransport::spawn(Command::new("my-mcp-server"))?;
+//!     gateway.connect("files_", transport).await?;
+//!
+//!     // And to another over HTTP, serving its tools as `search_<name>`
+//!     let transport = HttpTransport::new("http://localhost:8080/mcp".parse()?);
+//!     gateway.connect("search_", transport).await?;
+//!
+//!     // Serve the gateway's clients until they disconnect
+//!     tokio::select! {
+//!         result = serve(service.into_request_service(), StdioTransport::new()) => result?,
+//!         _ = gateway.run() => {}
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! Downstream servers may be connected over any transport implementing `AsyncRead + AsyncWrite`
+//! with newline-delimited JSON-RPC messages: a [`ChildProcessTransport`] for a local server, or an
+//! [`HttpTransport`] for a server using the streamable HTTP transport. A downstream server is
+//! shared by all of the gateway's clients, so requests from downstream servers to the client
+//! (such as sampling) aren't supported.
+//!
+//! If kuri's `send` feature is enabled, this crate's `send` feature must be too.
+//!
+//! [`Registry`]: kuri::Registry
+//! [`ChildProcessTransport`]: kuri::transport::ChildProcessTransport
+//! [`HttpTransport`]: kuri_client::HttpTransport
+//! [`MCPService`]: kuri::MCPService
+
+mod proxy;
+
+use futures::StreamExt;
+use kuri::{errors::HandlerKind, Registry};
+use kuri_client::{Client, ClientBuilder, ClientError};
+use kuri_mcp_protocol::{
+    jsonrpc::Notification, messages::ServerCapabilities, prompt::Prompt, resource::Resource,
+    tool::Tool,
+};
+use proxy::{ProxyPrompt, ProxyResource, ProxyTool};
+use std::{collections::HashMap, sync::Arc};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    sync::mpsc,
+};
+
+const TOOLS_LIST_CHANGED: &str = "notifications/tools/list_changed";
+const PROMPTS_LIST_CHANGED: &str = "notifications/prompts/list_changed";
+const RESOURCES_LIST_CHANGED: &str = "notifications/resources/list_changed";
+const PROGRESS: &str = "notifications/progress";
+const CANCELLED: &str = "notifications/cancelled";
+
+/// Errors raised when communicating with a downstream server.
+#[derive(Error, Debug)]
+pub enum GatewayError {
+    #[error("A downstream server is already connected with namespace `{0}`")]
+    DuplicateNamespace(String),
+
+    #[error(transparent)]
+    Client(#[from] ClientError),
+}
+
+/// Messages from a downstream server which aren't responses to the gateway's requests.
+enum Event {
+    Notification(Notification),
+    /// The connection has closed. No more events follow.
+    Closed,
+}
+
+/// Serves the tools, prompts and resources of downstream MCP servers through a kuri service. See
+/// the [crate documentation](crate) for an example.
+pub struct Gateway {
+    registry: Registry,
+    downstreams: HashMap<usize, Downstream>,
+    next_key: usize,
+
+    /// Events from every downstream server, tagged with the server's key.
+    events: mpsc::UnboundedReceiver<(usize, Event)>,
+    events_tx: mpsc::UnboundedSender<(usize, Event)>,
+}
+
+impl Gateway {
+    /// Create a gateway, which adds downstream servers' handlers to `registry` (usually obtained
+    /// with [`MCPService::registry`]).
+    ///
+    /// [`MCPService::registry`]: kuri::MCPService::registry
+    pub fn new(registry: Registry) -> Self {
+        let (events_tx, events) = mpsc::unbounded_channel();
+        Self {
+            registry,
+            downstreams: HashMap::new(),
+            next_key: 0,
+            events,
+            events_tx,
+        }
+    }
+
+    /// Connect to a downstream server over `transport`, and serve its tools, prompts and resources.
+    /// The names of its tools and prompts are prefixed with `namespace`.
+    ///
+    /// The connection is initialised, and the server's handlers listed, before this returns.
+    pub async fn connect<T>(&mut self, namespace: &str, transport: T) -> Result<(), GatewayError>
+    where
+        T: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        if self
+            .downstreams
+            .values()
+            .any(|downstream| downstream.namespace == namespace)
+        {
+            return Err(GatewayError::DuplicateNamespace(namespace.to_string()));
+        }
+
+        let key = self.next_key;
+        self.next_key += 1;
+        let client = ClientBuilder::new(env!("CARGO_PKG_NAME").to_string())
+            .with_version(env!("CARGO_PKG_VERSION").to_string())
+            .connect(transport);
+        let mut notifications = client.notifications().expect("notifications not yet taken");
+        let events = self.events_tx.clone();
+        tokio::spawn(async move {
+            while let Some(notification) = notifications.next().await {
+                let _ = events.send((key, Event::Notification(notification)));
+            }
+            let _ = events.send((key, Event::Closed));
+        });
+
+        let result = client.initialize().await?;
+        let mut downstream = Downstream {
+            namespace: namespace.to_string(),
+            client: Arc::new(client),
+            capabilities: result.capabilities,
+            tools: HashMap::new(),
+            prompts: HashMap::new(),
+            resources: HashMap::new(),
+        };
+        if let Err(e) = downstream.sync(&self.registry).await {
+            downstream.unregister(&self.registry);
+            return Err(e);
+        }
+
+        tracing::info!(
+            namespace,
+            server = result.server_info.name,
+            "Connected to downstream server"
+        );
+        self.downstreams.insert(key, downstream);
+        Ok(())
+    }
+
+    /// Relay notifications from downstream servers to the gateway's clients, and keep the
+    /// gateway's tools, prompts and resources in sync with theirs. When a downstream server
+    /// disconnects, its handlers are removed.
+    ///
+    /// Runs until every downstream server has disconnected.
+    pub async fn run(&mut self) {
+        while !self.downstreams.is_empty() {
+            // The gateway holds a sender, so the channel never closes
+            let Some((key, event)) = self.events.recv().await else {
+                break;
+            };
+            // Events from servers which failed to connect are ignored
+            let Some(downstream) = self.downstreams.get_mut(&key) else {
+                continue;
+            };
+
+            match event {
+                Event::Notification(notification) => {
+                    if let Err(e) = downstream
+                        .handle_notification(&self.registry, notification)
+                        .await
+                    {
+                        tracing::warn!(
+                            error = %e,
+                            namespace = downstream.namespace,
+                            "Error handling notification from downstream server"
+                        );
+                    }
+                }
+                Event::Closed => {
+                    tracing::info!(
+                        namespace = downstream.namespace,
+                        "Downstream server disconnected"
+                    );
+                    downstream.unregister(&self.registry);
+                    self.downstreams.remove(&key);
+                }
+            }
+        }
+    }
+}
+
+/// A connected downstream server, and the names of the handlers registered for it.
+struct Downstream {
+    namespace: String,
+    client: Arc<Client>,
+    capabilities: ServerCapabilities,
+    /// The server's tools (by namespaced name) that the gateway serves, as last listed.
+    tools: HashMap<String, Tool>,
+    /// The server's prompts (by namespaced name) that the gateway serves, as last listed.
+    prompts: HashMap<String, Prompt>,
+    /// The server's resources (by URI) that the gateway serves, as last listed.
+    resources: HashMap<String, Resource>,
+}
+
+impl Downstream {
+    async fn handle_notification(
+        &mut self,
+        registry: &Registry,
+        notification: Notification,
+    ) -> Result<(), GatewayError> {
+        match notification.method.as_str() {
+            TOOLS_LIST_CHANGED => self.sync_tools(registry).await,
+            PROMPTS_LIST_CHANGED => self.sync_prompts(registry).await,
+            RESOURCES_LIST_CHANGED => self.sync_resources(registry).await,
+            // Progress for forwarded requests is sent to the requesting client as it arrives (see
+            // `proxy::forward`), so this is progress for a request that has since completed.
+            // Cancellations are of the server's requests to the gateway, which clients never see.
+            PROGRESS | CANCELLED => {
+                tracing::debug!(
+                    namespace = self.namespace,
+                    method = notification.method,
+                    "Dropping notification about a request the gateway's clients didn't make"
+                );
+                Ok(())
+            }
+            // Anything else (such as log messages) concerns every client
+            _ => {
+                registry.broadcast(notification);
+                Ok(())
+            }
+        }
+    }
+
+    /// Register the server's tools, prompts and resources (for each capability it supports).
+    async fn sync(&mut self, registry: &Registry) -> Result<(), GatewayError> {
+        if self.capabilities.tools.is_some() {
+            self.sync_tools(registry).await?;
+        }
+        if self.capabilities.prompts.is_some() {
+            self.sync_prompts(registry).await?;
+        }
+        if self.capabilities.resources.is_some() {
+            self.sync_resources(registry).await?;
+        }
+        Ok(())
+    }
+
+    /// Bring the registry in line with the server's tools, in a single update. Tools whose
+    /// definitions are unchanged are left as they are.
+    async fn sync_tools(&mut self, registry: &Registry) -> Result<(), GatewayError> {
+        let result = self.client.list_tools().await?;
+
+        let mut tools = HashMap::new();
+        registry.update(|update| {
+            for tool in result.tools {
+                let name = format!("{}{}", self.namespace, tool.name);
+                let proxy = || ProxyTool::new(name.clone(), tool.clone(), self.client.clone());
+                match self.tools.remove(&name) {
+                    Some(served) if served == tool => {}
+                    Some(_) => update.add_tool(proxy()),
+                    None if update.try_add_tool(proxy()) => {}
+                    None => {
+                        skip(&self.namespace, HandlerKind::Tool, &name);
+                        continue;
+                    }
+                }
+                tools.insert(name, tool);
+            }
+            for stale in self.tools.keys() {
+                update.remove_tool(stale);
+            }
+        });
+        self.tools = tools;
+        Ok(())
+    }
+
+    /// Bring the registry in line with the server's prompts, in a single update. Prompts whose
+    /// definitions are unchanged are left as they are.
+    async fn sync_prompts(&mut self, registry: &Registry) -> Result<(), GatewayError> {
+        let result = self.client.list_prompts().await?;
+
+        let mut prompts = HashMap::new();
+        registry.update(|update| {
+            for prompt in result.prompts {
+                let name = format!("{}{}", self.namespace, prompt.name);
+                let proxy = || ProxyPrompt::new(name.clone(), prompt.clone(), self.client.clone());
+                match self.prompts.remove(&name) {
+                    Some(served) if served == prompt => {}
+                    Some(_) => update.add_prompt(proxy()),
+                    None if update.try_add_prompt(proxy()) => {}
+                    None => {
+                        skip(&self.namespace, HandlerKind::Prompt, &name);
+                        continue;
+                    }
+                }
+                prompts.insert(name, prompt);
+            }
+            for stale in self.prompts.keys() {
+                update.remove_prompt(stale);
+            }
+        });
+        self.prompts = prompts;
+        Ok(())
+    }
+
+    /// Bring the registry in line with the server's resources, in a single update. Resources
+    /// whose definitions are unchanged are left as they are.
+    async fn sync_resources(&mut self, registry: &Registry) -> Result<(), GatewayError> {
+        let result = self.client.list_resources().await?;
+
+        let mut resources = HashMap::new();
+        registry.update(|update| {
+            for resource in result.resources {
+                let uri = resource.uri.clone();
+                let proxy = || ProxyResource::new(resource.clone(), self.client.clone());
+                match self.resources.remove(&uri) {
+                    Some(served) if served == resource => {}
+                    Some(_) => update.add_resource(proxy()),
+                    None if update.try_add_resource(proxy()) => {}
+                    None => {
+                        skip(&self.namespace, HandlerKind::Resource, &uri);
+                        continue;
+                    }
+                }
+                resources.insert(uri, resource);
+            }
+            for stale in self.resources.keys() {
+                update.remove_resource(stale);
+            }
+        });
+        self.resources = resources;
+        Ok(())
+    }
+
+    /// Remove the server's handlers from the registry.
+    fn unregister(&mut self, registry: &Registry) {
+        registry.update(|update| {
+            for name in self.tools.keys() {
+                update.remove_tool(name);
+            }
+            for name in self.prompts.keys() {
+                update.remove_prompt(name);
+            }
+            for uri in self.resources.keys() {
+                update.remove_resource(uri);
+            }
+        });
+        self.tools.clear();
+        self.prompts.clear();
+        self.resources.clear();
+    }
+}
+
+/// Log a downstream handler that isn't served, as its name (or URI) is taken by another server's,
+/// or one of the gateway's own.
+fn skip(namespace: &str, kind: HandlerKind, name: &str) {
+    tracing::warn!(
+        namespace,
+        %kind,
+        name,
+        "Not serving a downstream handler, as its name is already taken"
+    );
+}
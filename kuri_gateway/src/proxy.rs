@@ -0,0 +1,228 @@
+//! Handlers which forward requests to a downstream server.
+
+use async_trait::async_trait;
+use kuri::{context::RequestContext, PromptHandler, ResourceHandler, ToolHandler};
+use kuri_client::{Client, ClientError};
+use kuri_mcp_protocol::{
+    jsonrpc::{ErrorCode, ErrorData, Notification, Params},
+    messages::{CallToolResult, GetPromptResult, ReadResourceResult},
+    prompt::{Prompt, PromptArgument, PromptError},
+    resource::{Resource, ResourceContents, ResourceError},
+    tool::{Tool, ToolAnnotations, ToolError},
+};
+use serde_json::{Map, Value};
+use std::{collections::HashMap, sync::Arc};
+
+/// Forward a request to the downstream server, carrying over the request's `_meta`.
+///
+/// Progress tokens are chosen by each client, so two of the gateway's clients may use the same
+/// one. If the client asked for progress, the downstream server is given a token of the
+/// connection's own instead, and its progress notifications are sent on to the requesting client
+/// alone, with the client's token.
+async fn forward(
+    client: &Client,
+    context: &RequestContext,
+    method: &str,
+    mut params: Map<String, Value>,
+) -> Result<Value, ClientError> {
+    let mut meta = context.meta().cloned().unwrap_or_default();
+    let progress = meta
+        .remove("progressToken")
+        .and_then(|token| Some((token, context.peer()?.clone())));
+    if !meta.is_empty() {
+        params.insert("_meta".to_string(), Value::Object(meta));
+    }
+
+    let Some((token, peer)) = progress else {
+        return client.request(method, Value::Object(params)).await;
+    };
+    let on_progress = move |mut notification: Notification| {
+        if let Some(Params::Map(params)) = &mut notification.params {
+            params.insert("progressToken".to_string(), token.clone());
+        }
+        // The client may have disconnected, and no longer be interested
+        let _ = peer.notify(notification);
+    };
+    client
+        .request_with_progress(method, Value::Object(params), on_progress)
+        .await
+}
+
+/// Whether the downstream server rejected the request's parameters.
+fn is_invalid_params(err: &ClientError) -> bool {
+    matches!(err, ClientError::Response(error) if error.code == ErrorCode::InvalidParams)
+}
+
+/// A downstream error with an implementation-defined code, which is forwarded to the client as is
+/// so it can still act on the code and data.
+fn custom_error(err: &ClientError) -> Option<ErrorData> {
+    match err {
+        ClientError::Response(error) if matches!(error.code, ErrorCode::Custom(_)) => {
+            Some(error.clone())
+        }
+        _ => None,
+    }
+}
+
+/// A downstream server's tool, served under a namespaced name.
+pub(crate) struct ProxyTool {
+    name: String,
+    tool: Tool,
+    client: Arc<Client>,
+}
+
+impl ProxyTool {
+    pub(crate) fn new(name: String, tool: Tool, client: Arc<Client>) -> Self {
+        Self { name, tool, client }
+    }
+}
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl ToolHandler for ProxyTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.tool.description
+    }
+
+    fn schema(&self) -> Value {
+        self.tool.input_schema.clone()
+    }
+
+    fn output_schema(&self) -> Option<Value> {
+        self.tool.output_schema.clone()
+    }
+
+    fn annotations(&self) -> Option<ToolAnnotations> {
+        self.tool.annotations.clone()
+    }
+
+    async fn call(
+        &self,
+        context: &RequestContext,
+        params: Value,
+    ) -> Result<CallToolResult, ToolError> {
+        let mut request = Map::new();
+        request.insert("name".to_string(), Value::String(self.tool.name.clone()));
+        request.insert("arguments".to_string(), params);
+
+        let result = forward(&self.client, context, "tools/call", request)
+            .await
+            .and_then(|result| Ok(serde_json::from_value(result)?));
+        result.map_err(|e| {
+            if let Some(error) = custom_error(&e) {
+                ToolError::Rpc(error)
+            } else if is_invalid_params(&e) {
+                ToolError::InvalidParameters(e.to_string())
+            } else {
+                ToolError::InternalError(e.to_string())
+            }
+        })
+    }
+}
+
+/// A downstream server's prompt, served under a namespaced name.
+pub(crate) struct ProxyPrompt {
+    name: String,
+    prompt: Prompt,
+    client: Arc<Client>,
+}
+
+impl ProxyPrompt {
+    pub(crate) fn new(name: String, prompt: Prompt, client: Arc<Client>) -> Self {
+        Self {
+            name,
+            prompt,
+            client,
+        }
+    }
+}
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl PromptHandler for ProxyPrompt {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.prompt.description.as_deref()
+    }
+
+    fn arguments(&self) -> Option<Vec<PromptArgument>> {
+        self.prompt.arguments.clone()
+    }
+
+    async fn call(
+        &self,
+        context: &RequestContext,
+        params: HashMap<String, Value>,
+    ) -> Result<GetPromptResult, PromptError> {
+        let mut request = Map::new();
+        request.insert("name".to_string(), Value::String(self.prompt.name.clone()));
+        request.insert(
+            "arguments".to_string(),
+            Value::Object(params.into_iter().collect()),
+        );
+
+        let result = forward(&self.client, context, "prompts/get", request)
+            .await
+            .and_then(|result| Ok(serde_json::from_value(result)?));
+        result.map_err(|e| {
+            if let Some(error) = custom_error(&e) {
+                PromptError::Rpc(error)
+            } else if is_invalid_params(&e) {
+                PromptError::InvalidParameters(e.to_string())
+            } else {
+                PromptError::InternalError(e.to_string())
+            }
+        })
+    }
+}
+
+/// A downstream server's resource. Resources are identified by URI, so aren't namespaced.
+pub(crate) struct ProxyResource {
+    resource: Resource,
+    client: Arc<Client>,
+}
+
+impl ProxyResource {
+    pub(crate) fn new(resource: Resource, client: Arc<Client>) -> Self {
+        Self { resource, client }
+    }
+}
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl ResourceHandler for ProxyResource {
+    fn uri(&self) -> &str {
+        &self.resource.uri
+    }
+
+    fn name(&self) -> &str {
+        &self.resource.name
+    }
+
+    fn description(&self) -> Option<&str> {
+        self.resource.description.as_deref()
+    }
+
+    fn mime_type(&self) -> Option<&str> {
+        self.resource.mime_type.as_deref()
+    }
+
+    async fn read(&self, context: &RequestContext) -> Result<Vec<ResourceContents>, ResourceError> {
+        let mut request = Map::new();
+        request.insert("uri".to_string(), Value::String(self.resource.uri.clone()));
+
+        let result = forward(&self.client, context, "resources/read", request)
+            .await
+            .and_then(|result| Ok(serde_json::from_value::<ReadResourceResult>(result)?));
+        result
+            .map(|result| result.contents)
+            .map_err(|e| ResourceError::ExecutionError(e.to_string()))
+    }
+}
@@ -0,0 +1,606 @@
+use async_trait::async_trait;
+use kuri::{
+    context::RequestContext,
+    extract::{Meta, Peer},
+    prompt, serve, tool, MCPService, MCPServiceBuilder, ResourceHandler, ServiceExt,
+};
+use kuri_gateway::{Gateway, GatewayError};
+use kuri_mcp_protocol::{
+    jsonrpc::{ErrorCode, ErrorData},
+    resource::{ResourceContents, ResourceError},
+};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tracing_subscriber::EnvFilter;
+
+// Gateway tests, with in-process kuri servers standing in for the downstream servers
+
+#[tokio::test]
+async fn test_gateway_forwards_requests() {
+    init_tracing();
+    let service = MCPServiceBuilder::new("Gateway".to_string()).build();
+    let mut gateway = Gateway::new(service.registry());
+
+    let (weather, weather_transport) = tokio::io::duplex(4096);
+    let (notes, notes_transport) = tokio::io::duplex(4096);
+    let (client, transport) = tokio::io::duplex(4096);
+
+    let test = async move {
+        gateway.connect("weather_", weather).await.unwrap();
+        gateway.connect("notes_", notes).await.unwrap();
+
+        let mut client = Client::new(client);
+        client.initialize().await;
+
+        // Tools and prompts are namespaced; resources aren't
+        let response = client.call("tools/list", json!({})).await;
+        let mut tools: Vec<_> = response["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap().to_string())
+            .collect();
+        tools.sort();
+        assert_eq!(
+            tools,
+            ["notes_add_note", "weather_forecast", "weather_radar"]
+        );
+        assert_eq!(
+            response["result"]["tools"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|tool| tool["name"] == "weather_forecast")
+                .unwrap()["inputSchema"]["required"],
+            json!(["city"])
+        );
+
+        let response = client.call("prompts/list", json!({})).await;
+        assert_eq!(
+            response["result"]["prompts"][0]["name"],
+            "weather_plan_trip"
+        );
+
+        let response = client.call("resources/list", json!({})).await;
+        assert_eq!(
+            response["result"]["resources"][0]["uri"],
+            "notes:///index.txt"
+        );
+
+        // Requests are forwarded to the downstream server
+        let response = client
+            .call(
+                "tools/call",
+                json!({ "name": "weather_forecast", "arguments": { "city": "London" } }),
+            )
+            .await;
+        assert_eq!(response["result"]["content"][0]["text"], "Rain in London");
+
+        let response = client
+            .call(
+                "prompts/get",
+                json!({ "name": "weather_plan_trip", "arguments": { "city": "Paris" } }),
+            )
+            .await;
+        assert_eq!(
+            response["result"]["messages"][0]["content"]["text"],
+            "Plan a trip to Paris"
+        );
+
+        let response = client
+            .call("resources/read", json!({ "uri": "notes:///index.txt" }))
+            .await;
+        assert_eq!(response["result"]["contents"][0]["text"], "No notes yet");
+
+        // Errors from the downstream server are passed on
+        let response = client
+            .call(
+                "tools/call",
+                json!({ "name": "weather_forecast", "arguments": {} }),
+            )
+            .await;
+        assert_eq!(response["error"]["code"], -32602);
+
+        // Errors with custom codes are passed on as is
+        let response = client
+            .call(
+                "tools/call",
+                json!({ "name": "weather_radar", "arguments": {} }),
+            )
+            .await;
+        assert_eq!(
+            response["error"],
+            json!({ "code": -32029, "message": "Rate limited", "data": { "retryAfter": 30 } })
+        );
+
+        client.close().await;
+    };
+
+    let weather_server = weather_server();
+    weather_server.registry().add_tool(Radar);
+
+    let (result, weather, notes, _) = tokio::join!(
+        serve(service.into_request_service(), transport),
+        serve(weather_server.into_request_service(), weather_transport),
+        serve(notes_server().into_request_service(), notes_transport),
+        test,
+    );
+    result.unwrap();
+    weather.unwrap();
+    notes.unwrap();
+}
+
+#[tokio::test]
+async fn test_gateway_relays_notifications() {
+    init_tracing();
+    let service = MCPServiceBuilder::new("Gateway".to_string()).build();
+    let mut gateway = Gateway::new(service.registry());
+
+    let weather_server = weather_server();
+    let weather_registry = weather_server.registry();
+    let (weather, weather_transport) = tokio::io::duplex(4096);
+    let (client, transport) = tokio::io::duplex(4096);
+
+    let test = async move {
+        gateway.connect("weather_", weather).await.unwrap();
+
+        let client = async {
+            let mut client = Client::new(client);
+            client.initialize().await;
+
+            // Notifications sent by downstream handlers reach the client
+            let response = client
+                .call(
+                    "tools/call",
+                    json!({ "name": "weather_forecast", "arguments": { "city": "Oslo" } }),
+                )
+                .await;
+            assert_eq!(response["result"]["content"][0]["text"], "Rain in Oslo");
+            assert_eq!(
+                client.notification().await,
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/message",
+                    "params": { "level": "info", "data": "Forecasting for Oslo" }
+                })
+            );
+
+            // Changes to the downstream server's tools are reflected by the gateway, with a single
+            // notification however many tools changed
+            weather_registry.update(|update| {
+                update.add_tool(Alerts);
+                update.add_tool(Radar);
+            });
+            assert_eq!(
+                client.notification().await,
+                json!({ "jsonrpc": "2.0", "method": "notifications/tools/list_changed" })
+            );
+            let response = client.call("tools/list", json!({})).await;
+            assert!(
+                client.notifications.is_empty(),
+                "{:?}",
+                client.notifications
+            );
+            let mut tools: Vec<_> = response["result"]["tools"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .map(|tool| tool["name"].as_str().unwrap().to_string())
+                .collect();
+            tools.sort();
+            assert_eq!(
+                tools,
+                ["weather_alerts", "weather_forecast", "weather_radar"]
+            );
+
+            client.close().await;
+        };
+
+        tokio::select! {
+            _ = gateway.run() => panic!("Downstream server disconnected unexpectedly"),
+            _ = client => {}
+        }
+    };
+
+    let (result, weather, _) = tokio::join!(
+        serve(service.into_request_service(), transport),
+        serve(weather_server.into_request_service(), weather_transport),
+        test,
+    );
+    result.unwrap();
+    weather.unwrap();
+}
+
+#[tokio::test]
+async fn test_gateway_routes_progress() {
+    init_tracing();
+    let service = MCPServiceBuilder::new("Gateway".to_string()).build();
+    let mut gateway = Gateway::new(service.registry());
+
+    let weather_server = weather_server();
+    weather_server.registry().add_tool(Download);
+    let (weather, weather_transport) = tokio::io::duplex(4096);
+    let (first, first_transport) = tokio::io::duplex(4096);
+    let (second, second_transport) = tokio::io::duplex(4096);
+
+    let test = async move {
+        gateway.connect("weather_", weather).await.unwrap();
+
+        let clients = async {
+            let mut first = Client::new(first);
+            first.initialize().await;
+            let mut second = Client::new(second);
+            second.initialize().await;
+
+            // Both clients use the same progress token
+            for client in [&mut first, &mut second] {
+                let response = client
+                    .call(
+                        "tools/call",
+                        json!({ "name": "weather_download", "arguments": {}, "_meta": { "progressToken": 1 } }),
+                    )
+                    .await;
+                assert_eq!(response["result"]["content"][0]["text"], "Downloaded");
+            }
+
+            // Each client gets the progress of its own request, with its own token, before the
+            // response
+            for client in [&mut first, &mut second] {
+                assert_eq!(
+                    client.notifications,
+                    [json!({
+                        "jsonrpc": "2.0",
+                        "method": "notifications/progress",
+                        "params": { "progressToken": 1, "progress": 1, "total": 1 }
+                    })]
+                );
+            }
+
+            first.close().await;
+            second.close().await;
+        };
+
+        tokio::select! {
+            _ = gateway.run() => panic!("Downstream server disconnected unexpectedly"),
+            _ = clients => {}
+        }
+    };
+
+    let (first, second, weather, _) = tokio::join!(
+        serve(service.clone().into_request_service(), first_transport),
+        serve(service.into_request_service(), second_transport),
+        serve(weather_server.into_request_service(), weather_transport),
+        test,
+    );
+    first.unwrap();
+    second.unwrap();
+    weather.unwrap();
+}
+
+#[tokio::test]
+async fn test_gateway_downstream_disconnects() {
+    init_tracing();
+    let service = MCPServiceBuilder::new("Gateway".to_string()).build();
+    let registry = service.registry();
+    let mut gateway = Gateway::new(service.registry());
+
+    let (weather, weather_transport) = tokio::io::duplex(4096);
+    let (notes, notes_transport) = tokio::io::duplex(4096);
+
+    let weather_server = serve(weather_server().into_request_service(), weather_transport);
+    let test = async move {
+        gateway.connect("weather_", weather).await.unwrap();
+
+        // The notes server stops (dropping its transport) once connected
+        let notes_server = serve(notes_server().into_request_service(), notes_transport);
+        tokio::select! {
+            result = gateway.connect("notes_", notes) => result.unwrap(),
+            _ = notes_server => panic!("Notes server stopped before the gateway connected"),
+        }
+
+        let mut service = service;
+        assert_eq!(
+            tool_names(&mut service).await,
+            ["notes_add_note", "weather_forecast"]
+        );
+
+        // The gateway runs until the last server disconnects. Only the weather server remains
+        // connected, so time out.
+        let run = tokio::time::timeout(std::time::Duration::from_millis(100), gateway.run());
+        assert!(run.await.is_err());
+        assert_eq!(tool_names(&mut service).await, ["weather_forecast"]);
+        assert!(!registry.remove_resource("notes:///index.txt"));
+    };
+
+    tokio::select! {
+        _ = weather_server => panic!("Weather server stopped unexpectedly"),
+        _ = test => {}
+    }
+}
+
+#[tokio::test]
+async fn test_gateway_collisions() {
+    init_tracing();
+    let mut service = MCPServiceBuilder::new("Gateway".to_string())
+        .with_tool(LocalForecast)
+        .build();
+    let registry = service.registry();
+    let mut gateway = Gateway::new(service.registry());
+
+    let (weather, weather_transport) = tokio::io::duplex(4096);
+    let (notes, notes_transport) = tokio::io::duplex(4096);
+    let (more_notes, more_notes_transport) = tokio::io::duplex(4096);
+    let (stop_more_notes, stopped) = tokio::sync::oneshot::channel::<()>();
+
+    let test = async move {
+        // The gateway's own tool isn't shadowed by the downstream server's
+        gateway.connect("weather_", weather).await.unwrap();
+        let response = tool_text(&mut service, "weather_forecast").await;
+        assert_eq!(response, "Sunny everywhere");
+
+        // Nor is one downstream server's resource replaced by another's
+        gateway.connect("notes_", notes).await.unwrap();
+        gateway.connect("more_notes_", more_notes).await.unwrap();
+        assert_eq!(
+            tool_names(&mut service).await,
+            ["more_notes_add_note", "notes_add_note", "weather_forecast"]
+        );
+
+        // The resource belongs to the first server, so stays when the second disconnects
+        stop_more_notes.send(()).unwrap();
+        let run = tokio::time::timeout(std::time::Duration::from_millis(100), gateway.run());
+        assert!(run.await.is_err());
+        assert_eq!(
+            tool_names(&mut service).await,
+            ["notes_add_note", "weather_forecast"]
+        );
+        assert!(registry.remove_resource("notes:///index.txt"));
+    };
+
+    let more_notes_server = async {
+        tokio::select! {
+            _ = serve(notes_server().into_request_service(), more_notes_transport) => {
+                panic!("Notes server stopped unexpectedly")
+            }
+            _ = stopped => {}
+        }
+    };
+    tokio::select! {
+        _ = serve(weather_server().into_request_service(), weather_transport) => {
+            panic!("Weather server stopped unexpectedly")
+        }
+        _ = serve(notes_server().into_request_service(), notes_transport) => {
+            panic!("Notes server stopped unexpectedly")
+        }
+        _ = async { tokio::join!(more_notes_server, test) } => {}
+    }
+}
+
+#[tokio::test]
+async fn test_gateway_duplicate_namespace() {
+    init_tracing();
+    let service = MCPServiceBuilder::new("Gateway".to_string()).build();
+    let mut gateway = Gateway::new(service.registry());
+
+    let (weather, weather_transport) = tokio::io::duplex(4096);
+    let (notes, _notes_transport) = tokio::io::duplex(4096);
+
+    let test = async move {
+        gateway.connect("tools_", weather).await.unwrap();
+        match gateway.connect("tools_", notes).await {
+            Err(e @ GatewayError::DuplicateNamespace(_)) => assert_eq!(
+                e.to_string(),
+                "A downstream server is already connected with namespace `tools_`"
+            ),
+            other => panic!("Expected a duplicate namespace error, got {other:?}"),
+        }
+    };
+
+    tokio::select! {
+        _ = serve(weather_server().into_request_service(), weather_transport) => {
+            panic!("Weather server stopped unexpectedly")
+        }
+        _ = test => {}
+    }
+}
+
+async fn tool_text(service: &mut MCPService, name: &str) -> String {
+    use kuri_mcp_protocol::jsonrpc::{
+        MethodCall, Params, RequestId, ResponseItem, SendableMessage,
+    };
+    use tower::Service;
+
+    let params = Params::try_from(json!({ "name": name, "arguments": {} })).unwrap();
+    let request = MethodCall::new(RequestId::Num(1), "tools/call".to_string(), Some(params));
+    let response = service.call(SendableMessage::from(request)).await.unwrap();
+    let Some(ResponseItem::Success { result, .. }) = response else {
+        panic!("Expected success response");
+    };
+    result["content"][0]["text"].as_str().unwrap().to_string()
+}
+
+async fn tool_names(service: &mut MCPService) -> Vec<String> {
+    use kuri_mcp_protocol::jsonrpc::{MethodCall, RequestId, ResponseItem, SendableMessage};
+    use tower::Service;
+
+    let request = MethodCall::new(RequestId::Num(1), "tools/list".to_string(), None);
+    let response = service.call(SendableMessage::from(request)).await.unwrap();
+    let Some(ResponseItem::Success { result, .. }) = response else {
+        panic!("Expected success response");
+    };
+    let mut names: Vec<_> = result["tools"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|tool| tool["name"].as_str().unwrap().to_string())
+        .collect();
+    names.sort();
+    names
+}
+
+#[tool(description = "Get the weather forecast for a city")]
+async fn forecast(peer: Peer, city: String) -> String {
+    peer.notify_with(
+        "notifications/message",
+        Some(json!({ "level": "info", "data": format!("Forecasting for {city}") })),
+    )
+    .unwrap();
+    format!("Rain in {city}")
+}
+
+#[tool(description = "Download the latest weather data")]
+async fn download(peer: Peer, meta: Meta) -> String {
+    if let Some(token) = meta.get("progressToken") {
+        peer.notify_with(
+            "notifications/progress",
+            Some(json!({ "progressToken": token, "progress": 1, "total": 1 })),
+        )
+        .unwrap();
+    }
+    "Downloaded".to_string()
+}
+
+#[tool(
+    name = "weather_forecast",
+    description = "Get the gateway's own forecast"
+)]
+async fn local_forecast() -> String {
+    "Sunny everywhere".to_string()
+}
+
+#[tool(description = "Get weather alerts")]
+async fn alerts() -> String {
+    "No alerts".to_string()
+}
+
+#[tool(description = "Get the weather radar")]
+async fn radar() -> Result<String, ErrorData> {
+    Err(
+        ErrorData::new(ErrorCode::Custom(-32029), "Rate limited".to_string())
+            .with_data(json!({ "retryAfter": 30 })),
+    )
+}
+
+#[prompt(description = "Plan a trip", params(city = "Where to go"))]
+async fn plan_trip(city: String) -> String {
+    format!("Plan a trip to {city}")
+}
+
+#[tool(description = "Add a note")]
+async fn add_note(text: String) -> String {
+    format!("Added {text}")
+}
+
+struct NotesIndex;
+
+#[cfg_attr(feature = "send", async_trait)]
+#[cfg_attr(not(feature = "send"), async_trait(?Send))]
+impl ResourceHandler for NotesIndex {
+    fn uri(&self) -> &str {
+        "notes:///index.txt"
+    }
+
+    fn name(&self) -> &str {
+        "index.txt"
+    }
+
+    async fn read(
+        &self,
+        _context: &RequestContext,
+    ) -> Result<Vec<ResourceContents>, ResourceError> {
+        Ok(vec![ResourceContents::TextResourceContents {
+            uri: self.uri().to_string(),
+            mime_type: Some("text/plain".to_string()),
+            text: "No notes yet".to_string(),
+        }])
+    }
+}
+
+fn weather_server() -> MCPService {
+    MCPServiceBuilder::new("Weather".to_string())
+        .with_tool(Forecast)
+        .with_prompt(PlanTrip)
+        .build()
+}
+
+fn notes_server() -> MCPService {
+    MCPServiceBuilder::new("Notes".to_string())
+        .with_tool(AddNote)
+        .with_resource(NotesIndex)
+        .build()
+}
+
+fn init_tracing() {
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::from_default_env())
+        .with_test_writer()
+        .try_init();
+}
+
+/// A minimal client for the gateway, sending one request at a time.
+struct Client {
+    lines: tokio::io::Lines<BufReader<tokio::io::ReadHalf<DuplexStream>>>,
+    write: tokio::io::WriteHalf<DuplexStream>,
+    next_id: u64,
+    /// Notifications received while awaiting a response.
+    notifications: Vec<Value>,
+}
+
+impl Client {
+    fn new(stream: DuplexStream) -> Self {
+        let (read, write) = tokio::io::split(stream);
+        Self {
+            lines: BufReader::new(read).lines(),
+            write,
+            next_id: 1,
+            notifications: Vec::new(),
+        }
+    }
+
+    async fn next_message(&mut self) -> Value {
+        let line = self.lines.next_line().await.unwrap().unwrap();
+        serde_json::from_str(&line).unwrap()
+    }
+
+    async fn call(&mut self, method: &str, params: Value) -> Value {
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        self.write
+            .write_all(format!("{request}\n").as_bytes())
+            .await
+            .unwrap();
+
+        loop {
+            let message = self.next_message().await;
+            if message["id"] == id {
+                return message;
+            }
+            self.notifications.push(message);
+        }
+    }
+
+    async fn notification(&mut self) -> Value {
+        if self.notifications.is_empty() {
+            self.next_message().await
+        } else {
+            self.notifications.remove(0)
+        }
+    }
+
+    async fn initialize(&mut self) {
+        self.call(
+            "initialize",
+            json!({
+                "protocolVersion": "2024-11-05",
+                "capabilities": {},
+                "clientInfo": { "name": "TestClient", "version": "1.0.0" }
+            }),
+        )
+        .await;
+    }
+
+    async fn close(mut self) {
+        self.write.shutdown().await.unwrap();
+    }
+}
@@ -1,28 +1,470 @@
 use proc_macro::TokenStream;
 
-fn is_injected_type(ty: &syn::Type) -> bool {
-    match ty {
-        syn::Type::Path(ty) => {
-            let path = &ty.path;
-            if let Some(segment) = path.segments.last() {
-                segment.ident == "Inject"
+/// Extractors provided by kuri, which are recognised by name.
+const EXTRACTORS: &[&str] = &[
+    "Inject",
+    "RequestContext",
+    "RequestId",
+    "Meta",
+    "ClientInfo",
+    "Peer",
+    "Session",
+];
+
+/// Whether a type is named like one of kuri's extractors. A type named through a path outside kuri
+/// (eg `my_crate::Meta`) is not.
+fn is_extractor_type(ty: &syn::Type) -> bool {
+    let syn::Type::Path(syn::TypePath { qself: None, path }) = ty else {
+        return false;
+    };
+    let (Some(first), Some(last)) = (path.segments.first(), path.segments.last()) else {
+        return false;
+    };
+    let in_kuri = path.segments.len() == 1 || first.ident == "kuri";
+    in_kuri && EXTRACTORS.iter().any(|name| last.ident == name)
+}
+
+/// Remove a marker attribute (eg `#[extract]`) from a handler parameter, returning whether it
+/// was present.
+fn take_marker(arg: &mut syn::PatType, marker: &str) -> bool {
+    let attrs = arg.attrs.len();
+    arg.attrs.retain(|attr| !attr.path().is_ident(marker));
+    arg.attrs.len() != attrs
+}
+
+/// How a handler parameter is taken.
+#[derive(Clone, Copy, PartialEq)]
+enum ParamKind {
+    /// An extractor, marked with `#[extract]` or recognised by name.
+    Extractor,
+    /// An argument given by the client, marked with `#[arg]`.
+    Argument,
+    /// An argument given by the client, by default. The generated code checks that its type isn't
+    /// an extractor (see [`argument_checks`]).
+    Unmarked,
+}
+
+/// Whether a handler parameter is an extractor (rather than an argument given by the client).
+/// Removes the `#[extract]` or `#[arg]` marker attribute from the parameter, if present.
+///
+/// A macro can't see which traits a type implements, so kuri's own extractors are recognised by
+/// name, and any others must be marked with `#[extract]`. An argument whose type happens to share
+/// a name with one of kuri's extractors can be marked with `#[arg]`. Extractors are then obtained
+/// through the `FromRequestContext` trait, and unmarked arguments are checked not to implement
+/// it, so a misclassified parameter fails to compile rather than misbehaving.
+fn take_extractor(arg: &mut syn::PatType) -> syn::Result<ParamKind> {
+    let is_extractor = take_marker(arg, "extract");
+    let is_argument = take_marker(arg, "arg");
+    match (is_extractor, is_argument) {
+        (true, true) => Err(syn::Error::new_spanned(
+            &arg.pat,
+            "a parameter can't be both `#[extract]` and `#[arg]`",
+        )),
+        (true, false) => Ok(ParamKind::Extractor),
+        (false, true) => Ok(ParamKind::Argument),
+        (false, false) if is_extractor_type(&arg.ty) => Ok(ParamKind::Extractor),
+        (false, false) => Ok(ParamKind::Unmarked),
+    }
+}
+
+/// Check that the types of a handler's unmarked arguments aren't extractors, which would
+/// otherwise be taken from the client's arguments (see `kuri::__private::ArgumentCheck`). Each
+/// check is spanned by the argument's type, so that the error points at the parameter.
+fn argument_checks(types: &[syn::Type]) -> proc_macro2::TokenStream {
+    if types.is_empty() {
+        return quote::quote! {};
+    }
+    let checks = types.iter().map(|ty| {
+        quote::quote_spanned! {syn::spanned::Spanned::span(ty)=>
+            kuri::__private::assert_argument((&kuri::__private::ArgumentCheck::<#ty>::new()).kind());
+        }
+    });
+    quote::quote! {
+        const _: () = {
+            #[allow(unused_imports)]
+            use kuri::__private::{ArgumentKind as _, ExtractorKind as _};
+
+            #[allow(dead_code)]
+            fn check_arguments() {
+                #(#checks)*
+            }
+        };
+    }
+}
+
+/// The `async_trait` attribute for generated handler impls. Handlers (and their futures) are only
+/// required to be `Send` when kuri's `send` feature is enabled.
+fn async_trait_attr() -> proc_macro2::TokenStream {
+    if cfg!(feature = "send") {
+        quote::quote! { #[async_trait::async_trait] }
+    } else {
+        quote::quote! { #[async_trait::async_trait(?Send)] }
+    }
+}
+
+/// The text of the doc comments (`///`, or `#[doc = "..."]`) among `attrs`, if there are any.
+fn doc_comment(attrs: &[syn::Attribute]) -> Option<String> {
+    let lines: Vec<String> = attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("doc"))
+        .filter_map(|attr| match &attr.meta {
+            syn::Meta::NameValue(syn::MetaNameValue {
+                value:
+                    syn::Expr::Lit(syn::ExprLit {
+                        lit: syn::Lit::Str(text),
+                        ..
+                    }),
+                ..
+            }) => Some(text.value()),
+            _ => None,
+        })
+        .collect();
+
+    // `///` comments keep the space after the slashes
+    let text = lines
+        .iter()
+        .map(|line| line.strip_prefix(' ').unwrap_or(line))
+        .collect::<Vec<_>>()
+        .join("\n");
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// Remove the doc comments from a handler parameter, returning their text. rustc doesn't allow doc
+/// comments on parameters, so they mustn't remain in the expanded function.
+fn take_doc_comment(arg: &mut syn::PatType) -> Option<String> {
+    let doc = doc_comment(&arg.attrs);
+    arg.attrs.retain(|attr| !attr.path().is_ident("doc"));
+    doc
+}
+
+/// Check that a function can be made into a handler by the `#[tool]` or `#[prompt]` attribute
+/// (named by `attr`), reporting every problem with its signature at once. Methods (in a
+/// `#[tool_box]`) may take `&self`.
+fn check_signature(sig: &syn::Signature, attr: &str, in_tool_box: bool) -> syn::Result<()> {
+    let mut errors = Vec::new();
+    for arg in &sig.inputs {
+        match arg {
+            syn::FnArg::Receiver(receiver) if !in_tool_box => errors.push(syn::Error::new_spanned(
+                receiver,
+                format!("`#[{attr}]` methods taking `self` must be in a `#[tool_box]` impl block"),
+            )),
+            // The tool box is shared between handlers, so can only be borrowed immutably
+            syn::FnArg::Receiver(receiver) => {
+                if receiver.reference.is_none()
+                    || receiver.mutability.is_some()
+                    || receiver.colon_token.is_some()
+                {
+                    errors.push(syn::Error::new_spanned(
+                        receiver,
+                        format!("`#[{attr}]` methods must take `&self`"),
+                    ));
+                }
+            }
+            syn::FnArg::Typed(pat_type) => match &*pat_type.pat {
+                syn::Pat::Ident(syn::PatIdent {
+                    by_ref: None,
+                    subpat: None,
+                    ..
+                }) => {}
+                pat => errors.push(syn::Error::new_spanned(
+                    pat,
+                    format!(
+                        "`#[{attr}]` parameters must be plain identifiers, eg `x: i32`, as each \
+                         is named in the generated schema; patterns aren't supported"
+                    ),
+                )),
+            },
+        }
+    }
+    combine_errors(errors)
+}
+
+/// Define a handler's struct, returning its definition and the expression calling the handler's
+/// function with `call_args`. The handler of a free function is a unit struct, while that of a
+/// method of `self_ty` (in a `#[tool_box]`) holds the shared tool box, which methods taking `&self`
+/// are called on through `receiver` (usually `self.0`). The call is awaited if the function is
+/// `async`.
+fn handler_struct(
+    struct_name: &syn::Ident,
+    sig: &syn::Signature,
+    self_ty: Option<&syn::Type>,
+    receiver: proc_macro2::TokenStream,
+    call_args: &[proc_macro2::TokenStream],
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let fn_name = &sig.ident;
+    let await_call = sig.asyncness.map(|_| quote::quote! { .await });
+    match self_ty {
+        None => (
+            quote::quote! {
+                #[derive(Default)]
+                struct #struct_name;
+            },
+            quote::quote! { #fn_name(#(#call_args),*)#await_call },
+        ),
+        Some(self_ty) => {
+            let call = if sig.receiver().is_some() {
+                quote::quote! { #receiver.#fn_name(#(#call_args),*)#await_call }
             } else {
-                false
+                quote::quote! { <#self_ty>::#fn_name(#(#call_args),*)#await_call }
+            };
+            (
+                quote::quote! {
+                    struct #struct_name(std::sync::Arc<#self_ty>);
+                },
+                call,
+            )
+        }
+    }
+}
+
+/// Combine errors into one, so that they're all reported.
+fn combine_errors(errors: impl IntoIterator<Item = syn::Error>) -> syn::Result<()> {
+    let mut errors = errors.into_iter();
+    match errors.next() {
+        Some(mut error) => {
+            errors.for_each(|e| error.combine(e));
+            Err(error)
+        }
+        None => Ok(()),
+    }
+}
+
+/// The value of a `key = "..."` macro argument.
+fn string_value(nv: &syn::MetaNameValue) -> syn::Result<String> {
+    match &nv.value {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: syn::Lit::Str(lit_str),
+            ..
+        }) => Ok(lit_str.value()),
+        value => Err(syn::Error::new_spanned(
+            value,
+            format!(
+                "expected a string, eg `{} = \"...\"`",
+                nv.path.require_ident()?
+            ),
+        )),
+    }
+}
+
+/// Set a macro argument, which may only be given once.
+fn set_once<T>(slot: &mut Option<T>, value: T, key: &syn::Ident) -> syn::Result<()> {
+    if slot.is_some() {
+        return Err(syn::Error::new_spanned(
+            key,
+            format!("duplicate `{key}` argument"),
+        ));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+/// The error for an argument that the `#[tool]` or `#[prompt]` attribute (named by `attr`) doesn't
+/// recognise, listing those it does.
+fn unknown_argument(path: &syn::Path, attr: &str, expected: &[&str]) -> syn::Error {
+    let expected = expected
+        .iter()
+        .map(|name| format!("`{name}`"))
+        .collect::<Vec<_>>()
+        .join(", ");
+    syn::Error::new_spanned(
+        path,
+        format!("unknown `#[{attr}]` argument; expected one of {expected}"),
+    )
+}
+
+/// Options for a handler's parameters, given in the macro's `params(...)` argument. Each entry is
+/// either a description, eg `params(x = "The first number")`, or a list of options, eg
+/// `params(max_results(description = "...", rename = "maxResults", default = 10))`.
+#[derive(Default)]
+struct Params(Vec<Param>);
+
+/// The options for one of a handler's parameters
+struct Param {
+    ident: syn::Ident,
+    description: Option<String>,
+    /// The argument's name in the schema, if not the parameter's
+    rename: Option<String>,
+    default: Option<ParamDefault>,
+}
+
+/// The value of a parameter whose argument the client omits
+enum ParamDefault {
+    /// `default`: the type's `Default`
+    Default,
+    /// `default = <expr>`
+    Value(syn::Expr),
+}
+
+impl Param {
+    /// A parameter without any options
+    fn new(ident: syn::Ident) -> Self {
+        Self {
+            ident,
+            description: None,
+            rename: None,
+            default: None,
+        }
+    }
+}
+
+impl ParamDefault {
+    /// An expression evaluating to the default. String literals are converted, so that
+    /// `default = "..."` works for `String`s.
+    fn value(&self) -> proc_macro2::TokenStream {
+        match self {
+            ParamDefault::Default => quote::quote! { Default::default() },
+            ParamDefault::Value(
+                expr @ syn::Expr::Lit(syn::ExprLit {
+                    lit: syn::Lit::Str(_),
+                    ..
+                }),
+            ) => quote::quote! { #expr.into() },
+            ParamDefault::Value(expr) => quote::quote! { #expr },
+        }
+    }
+}
+
+impl Params {
+    /// Parse the entries of a `params(...)` list.
+    fn parse(list: &syn::MetaList) -> syn::Result<Self> {
+        use syn::{punctuated::Punctuated, Meta, Token};
+
+        let nested: Punctuated<Meta, Token![,]> =
+            list.parse_args_with(Punctuated::parse_terminated)?;
+        let mut params: Vec<Param> = Vec::new();
+        for meta in nested {
+            let ident = meta.path().require_ident()?.clone();
+            if params.iter().any(|param| param.ident == ident) {
+                return Err(syn::Error::new_spanned(
+                    &ident,
+                    format!("`{ident}` is given more than once"),
+                ));
+            }
+            let mut param = Param::new(ident);
+
+            match &meta {
+                Meta::NameValue(nv) => param.description = Some(string_value(nv)?),
+                Meta::List(list) => {
+                    let options: Punctuated<Meta, Token![,]> =
+                        list.parse_args_with(Punctuated::parse_terminated)?;
+                    for option in options {
+                        let key = option.path().require_ident()?;
+                        match (&option, key.to_string().as_str()) {
+                            (Meta::NameValue(nv), "description") => {
+                                set_once(&mut param.description, string_value(nv)?, key)?
+                            }
+                            (Meta::NameValue(nv), "rename") => {
+                                set_once(&mut param.rename, string_value(nv)?, key)?
+                            }
+                            (Meta::NameValue(nv), "default") => set_once(
+                                &mut param.default,
+                                ParamDefault::Value(nv.value.clone()),
+                                key,
+                            )?,
+                            (Meta::Path(_), "default") => {
+                                set_once(&mut param.default, ParamDefault::Default, key)?
+                            }
+                            _ => {
+                                return Err(syn::Error::new_spanned(
+                                    option,
+                                    "expected `description = \"...\"`, `rename = \"...\"`, \
+                                     `default`, or `default = <value>`",
+                                ))
+                            }
+                        }
+                    }
+                }
+                Meta::Path(_) => {
+                    return Err(syn::Error::new_spanned(
+                        meta,
+                        "expected a parameter's description, eg `x = \"The first number\"`, \
+                         or its options, eg `x(rename = \"...\", default = 0)`",
+                    ))
+                }
             }
+            params.push(param);
         }
-        _ => false,
+        Ok(Self(params))
+    }
+
+    /// Take the options of the named parameter, if it has any.
+    fn take(&mut self, name: &str) -> Option<Param> {
+        let index = self.0.iter().position(|param| param.ident == name)?;
+        Some(self.0.remove(index))
+    }
+
+    /// Check that every parameter's options were taken, ie that each names one of the handler's
+    /// arguments, so that misspelt names aren't silently ignored.
+    fn finish(self, fn_name: &syn::Ident, extractors: &[String]) -> syn::Result<()> {
+        combine_errors(self.0.into_iter().map(|Param { ident, .. }| {
+            let message = if extractors.contains(&ident.to_string()) {
+                format!("`{ident}` is an extractor, not an argument given by the client, so can't be described")
+            } else {
+                format!("`{fn_name}` has no parameter named `{ident}`")
+            };
+            syn::Error::new(ident.span(), message)
+        }))
     }
 }
 
 mod prompt;
 mod tool;
+mod tool_box;
 
+/// Make a function into a prompt handler, named after the function, which is added to a service
+/// with `MCPServiceBuilder::with_prompt`. The handler's struct is named in PascalCase (eg
+/// `fn summarise` becomes `Summarise`).
+///
+/// Takes a `name`, a `description` (defaulting to the function's doc comment), and `params(...)`
+/// options. Arguments are parsed from strings, with their type's `FromStr`.
+///
+/// # Parameters
+///
+/// Each parameter is either an argument given by the client, or an extractor, which is obtained
+/// from the request instead (see `kuri::extract`). A macro can't see which traits a type
+/// implements, so only kuri's own extractors are recognised, by name: `Inject`, `Session`,
+/// `RequestId`, `Meta`, `ClientInfo`, `Peer` and `RequestContext`. Any other extractor (including
+/// `Registry`, and your own types implementing `FromRequestContext`) must be marked with
+/// `#[extract]`. An unmarked parameter whose type implements `FromRequestContext` fails to
+/// compile, rather than being taken from the client's arguments. Conversely, mark an argument
+/// with `#[arg]` if its type shares a name with one of kuri's extractors.
 #[proc_macro_attribute]
 pub fn prompt(args: TokenStream, input: TokenStream) -> TokenStream {
     prompt::prompt(args, input)
 }
 
+/// Make a function into a tool handler, named after the function, which is added to a service
+/// with `MCPServiceBuilder::with_tool`. The handler's struct is named in PascalCase (eg
+/// `fn get_weather` becomes `GetWeather`).
+///
+/// Takes a `name`, a `description` (defaulting to the function's doc comment), `params(...)`
+/// options, annotations (`title`, `read_only`, `destructive`, `idempotent` and `open_world`), and
+/// `blocking`. See the kuri crate documentation for examples.
+///
+/// # Parameters
+///
+/// Each parameter is either an argument given by the client, or an extractor, which is obtained
+/// from the request instead (see `kuri::extract`). A macro can't see which traits a type
+/// implements, so only kuri's own extractors are recognised, by name: `Inject`, `Session`,
+/// `RequestId`, `Meta`, `ClientInfo`, `Peer` and `RequestContext`. Any other extractor (including
+/// `Registry`, and your own types implementing `FromRequestContext`) must be marked with
+/// `#[extract]`. An unmarked parameter whose type implements `FromRequestContext` fails to
+/// compile, rather than being taken from the client's arguments. Conversely, mark an argument
+/// with `#[arg]` if its type shares a name with one of kuri's extractors.
+///
+/// ```rust,ignore
+/// #[tool(description = "Look up a word, on behalf of the client")]
+/// async fn define(client: ClientInfo, #[extract] dictionary: Dictionary, word: String) -> String {
+///     dictionary.define(&word, &client.name)
+/// }
+/// ```
 #[proc_macro_attribute]
 pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
     tool::tool(args, input)
 }
+
+#[proc_macro_attribute]
+pub fn tool_box(args: TokenStream, input: TokenStream) -> TokenStream {
+    tool_box::tool_box(args, input)
+}
@@ -1,79 +1,110 @@
 use convert_case::{Case, Casing};
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
-use std::collections::HashMap;
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
-    parse::Parse, parse::ParseStream, parse_macro_input, punctuated::Punctuated, Expr, ExprLit,
-    FnArg, ItemFn, Lit, Meta, Pat, PatType, Token,
+    parse::Parse, parse::ParseStream, parse_macro_input, punctuated::Punctuated, spanned::Spanned,
+    Attribute, FnArg, Ident, ItemFn, Meta, Pat, PatType, ReturnType, Signature, Token, Type,
 };
 
-use crate::is_injected_type;
+use crate::{
+    argument_checks, async_trait_attr, check_signature, doc_comment, handler_struct, set_once,
+    string_value, take_doc_comment, take_extractor, take_marker, unknown_argument, Param,
+    ParamKind, Params,
+};
 
-struct MacroArgs {
-    name: Option<String>,
+pub(crate) struct MacroArgs {
+    pub(crate) name: Option<String>,
     description: Option<String>,
-    param_descriptions: HashMap<String, String>,
+    params: Params,
 }
 
+/// The arguments `#[prompt]` accepts
+const ARGUMENTS: &[&str] = &["name", "description", "params"];
+
 impl Parse for MacroArgs {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut name = None;
         let mut description = None;
-        let mut param_descriptions = HashMap::new();
+        let mut params = None;
 
         let meta_list: Punctuated<Meta, Token![,]> = Punctuated::parse_terminated(input)?;
 
         for meta in meta_list {
-            match meta {
-                Meta::NameValue(nv) => {
-                    let ident = nv.path.get_ident().unwrap().to_string();
-                    if let Expr::Lit(ExprLit {
-                        lit: Lit::Str(lit_str),
-                        ..
-                    }) = nv.value
-                    {
-                        match ident.as_str() {
-                            "name" => name = Some(lit_str.value()),
-                            "description" => description = Some(lit_str.value()),
-                            _ => {}
-                        }
-                    }
+            let key = meta.path().require_ident()?;
+            match (&meta, key.to_string().as_str()) {
+                (Meta::NameValue(nv), "name") => set_once(&mut name, string_value(nv)?, key)?,
+                (Meta::NameValue(nv), "description") => {
+                    set_once(&mut description, string_value(nv)?, key)?
                 }
-                Meta::List(list) if list.path.is_ident("params") => {
-                    let nested: Punctuated<Meta, Token![,]> =
-                        list.parse_args_with(Punctuated::parse_terminated)?;
-
-                    for meta in nested {
-                        if let Meta::NameValue(nv) = meta {
-                            if let Expr::Lit(ExprLit {
-                                lit: Lit::Str(lit_str),
-                                ..
-                            }) = nv.value
-                            {
-                                let param_name = nv.path.get_ident().unwrap().to_string();
-                                param_descriptions.insert(param_name, lit_str.value());
-                            }
-                        }
-                    }
+                (Meta::List(list), "params") => set_once(&mut params, Params::parse(list)?, key)?,
+                (_, "name" | "description") => {
+                    return Err(syn::Error::new_spanned(
+                        &meta,
+                        format!("expected `{key} = \"...\"`"),
+                    ))
+                }
+                (_, "params") => {
+                    return Err(syn::Error::new_spanned(
+                        &meta,
+                        "expected `params(name = \"description\", ...)`",
+                    ))
                 }
-                _ => {}
+                _ => return Err(unknown_argument(meta.path(), "prompt", ARGUMENTS)),
             }
         }
 
         Ok(MacroArgs {
             name,
             description,
-            param_descriptions,
+            params: params.unwrap_or_default(),
         })
     }
 }
 
 pub fn prompt(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as MacroArgs);
-    let input_fn = parse_macro_input!(input as ItemFn);
+    let mut input_fn = parse_macro_input!(input as ItemFn);
+
+    match expand(args, &input_fn.attrs, &mut input_fn.sig, None) {
+        Ok((_, handler)) => quote! {
+            #input_fn
+            #handler
+        }
+        .into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Generate a prompt handler for a function, or for a method of `self_ty` in a `#[tool_box]`,
+/// returning the name of the handler struct along with its definition. The attributes the macro
+/// consumes are removed from the function's parameters.
+pub(crate) fn expand(
+    mut args: MacroArgs,
+    attrs: &[Attribute],
+    sig: &mut Signature,
+    self_ty: Option<&Type>,
+) -> syn::Result<(Ident, proc_macro2::TokenStream)> {
+    check_signature(sig, "prompt", self_ty.is_some())?;
+
+    // A prompt must return its messages. The conversion is spanned by the return type, so that an
+    // unsupported type is reported there.
+    let into_result = match &sig.output {
+        ReturnType::Default => {
+            return Err(syn::Error::new(
+                sig.paren_token.span.close(),
+                "`#[prompt]` functions must return the prompt, eg as a `String` or `Vec<PromptMessage>`",
+            ));
+        }
+        ReturnType::Type(_, ty) if matches!(**ty, Type::ImplTrait(_)) => quote! {
+            <_ as kuri::response::IntoGetPromptResult>::into_get_prompt_result(result)
+        },
+        ReturnType::Type(_, ty) => quote_spanned! {ty.span()=>
+            <#ty as kuri::response::IntoGetPromptResult>::into_get_prompt_result(result)
+        },
+    };
 
     // Extract function details
-    let fn_name = &input_fn.sig.ident;
+    let fn_name = sig.ident.clone();
     let fn_name_str = fn_name.to_string();
 
     // Generate PascalCase struct name from the function name
@@ -81,103 +112,148 @@ pub fn prompt(args: TokenStream, input: TokenStream) -> TokenStream {
 
     // Use provided name or function name as default
     let tool_name = args.name.unwrap_or_else(|| fn_name_str.clone());
-    let tool_description = args.description.unwrap_or_default();
+    // The description falls back to the function's doc comment
+    let tool_description = match args.description.or_else(|| doc_comment(attrs)) {
+        Some(description) => quote! { Some(#description) },
+        None => quote! { None },
+    };
 
     // Extract parameter names, types, and descriptions
-    let mut ctx_params = Vec::new();
     let mut param_defs = Vec::new();
-    let mut param_names = Vec::new();
+    let mut call_args = Vec::new();
+    let mut extractor_types = Vec::new();
+    let mut extractor_names = Vec::new();
+    let mut unmarked_types = Vec::new();
     let mut param_extracts = Vec::new();
     let mut prompt_args = Vec::new();
 
-    for arg in input_fn.sig.inputs.iter() {
-        if let FnArg::Typed(PatType { pat, ty, .. }) = arg {
+    for arg in sig.inputs.iter_mut() {
+        if let FnArg::Typed(pat_type) = arg {
+            let kind = take_extractor(pat_type)?;
+            let is_flattened = take_marker(pat_type, "flatten");
+            let doc = take_doc_comment(pat_type);
+            let PatType { pat, ty, .. } = pat_type;
             if let Pat::Ident(param_ident) = &**pat {
-                if is_injected_type(ty) {
-                    ctx_params.push(param_ident);
-                    continue;
-                }
-
                 let param_name = &param_ident.ident;
                 let param_name_str = param_name.to_string();
-                let description = args
-                    .param_descriptions
-                    .get(&param_name_str)
-                    .cloned()
-                    .unwrap_or_default();
-
-                // Determine if the parameter is optional
-                let (is_optional, _) = get_type_info(ty);
-
-                // Generate parameter extraction logic based on type
-                // TODO (param injection): may need some mapping by name, if fn param name differs
-                //   from that exposed in the schema.
-                if is_optional {
-                    param_extracts.push(quote! {
-                        let #param_name = if let Some(value) = args.get(#param_name_str) {
-                            Some(serde_json::from_value(value.clone())
-                                .map_err(|e| kuri::PromptError::InvalidParameters(
-                                    format!("Failed to deserialize parameter '{}': {}", #param_name_str, e)
-                                ))?)
-                        } else {
-                            None
-                        };
-                    });
-                } else {
-                    param_extracts.push(quote! {
-                        let #param_name = match args.get(#param_name_str) {
-                            Some(value) => {
-                                serde_json::from_value(value.clone())
-                                    .map_err(|e| kuri::PromptError::InvalidParameters(
-                                        format!("Failed to deserialize parameter '{}': {}", #param_name_str, e)
-                                    ))?
-                            },
-                            None => return Err(kuri::PromptError::InvalidParameters(
-                                format!("Missing required parameter: {}", #param_name_str)
-                            )),
-                        };
+                if kind == ParamKind::Extractor {
+                    call_args.push(quote! {
+                        <#ty as kuri::extract::FromRequestContext>::from_request_context(context)?
                     });
+                    extractor_types.push(ty.clone());
+                    extractor_names.push(param_name_str);
+                    continue;
+                }
+                if is_flattened {
+                    return Err(syn::Error::new_spanned(
+                        param_name,
+                        "`#[prompt]` arguments can't be flattened",
+                    ));
                 }
 
+                if kind == ParamKind::Unmarked {
+                    unmarked_types.push((**ty).clone());
+                }
+                // A description in `params(...)` takes precedence over the parameter's doc comment
+                let Param {
+                    description,
+                    rename,
+                    default,
+                    ..
+                } = args
+                    .params
+                    .take(&param_name_str)
+                    .unwrap_or_else(|| Param::new(param_name.clone()));
+                let description = description.or(doc);
+                // The argument's name, as the client gives it
+                let argument_name = rename.unwrap_or_else(|| param_name_str.clone());
+
+                // Optional parameters are parsed as their inner type
+                let inner_ty = option_inner_type(ty);
+                let is_required = inner_ty.is_none() && default.is_none();
+
+                // Arguments are parsed from strings. The parse is spanned by the parameter's type,
+                // so that a type without `FromStr` is reported there.
+                let value_ty = inner_ty.unwrap_or(ty);
+                let parse = quote_spanned! {value_ty.span()=>
+                    kuri::__private::parse_prompt_argument::<#value_ty>(#argument_name, value)?
+                };
+                let parsed = match inner_ty {
+                    Some(_) => quote! { Some(#parse) },
+                    None => parse,
+                };
+                let missing = match default {
+                    Some(default) => default.value(),
+                    None if inner_ty.is_some() => quote! { None },
+                    None => quote! {
+                        return Err(kuri::PromptError::InvalidParameters(
+                            format!("Missing required parameter: {}", #argument_name)
+                        ))
+                    },
+                };
+                param_extracts.push(quote! {
+                    let #param_name: #ty = match args.get(#argument_name) {
+                        Some(value) if !value.is_null() => #parsed,
+                        _ => #missing,
+                    };
+                });
+
                 // Build prompt argument definitions
+                let argument_description = match &description {
+                    Some(description) => quote! { Some(#description.to_string()) },
+                    None => quote! { None },
+                };
                 prompt_args.push(quote! {
                     kuri::PromptArgument {
-                        name: #param_name_str.to_string(),
-                        description: Some(#description.to_string()),
-                        required: Some(!#is_optional),
+                        name: #argument_name.to_string(),
+                        description: #argument_description,
+                        required: Some(#is_required),
                     }
                 });
 
-                param_names.push(param_name);
+                call_args.push(quote! { #param_name });
+                let description = description.map(|description| {
+                    quote! { #[schemars(description = #description)] }
+                });
                 param_defs.push(quote! {
-                    #[schemars(description = #description)]
+                    #description
                     #param_name: #ty
                 });
             }
         }
     }
+    args.params.finish(&fn_name, &extractor_names)?;
+    let (struct_def, call) =
+        handler_struct(&struct_name, sig, self_ty, quote! { self.0 }, &call_args);
 
     // Generate the implementation
-    let ctx_params = (0..ctx_params.len()).map(|_| {
+    // Declare the handler's dependencies on the service's state, if it has any extractors
+    let verify_state_impl = if extractor_types.is_empty() {
+        quote! {}
+    } else {
         quote! {
-            <kuri::context::Inject<_> as kuri::context::FromContext>::from_context(&context),
+            fn verify_state(&self, state: &kuri::context::Context) -> Result<(), kuri::extract::ExtractError> {
+                #(<#extractor_types as kuri::extract::FromRequestContext>::verify_state(state)?;)*
+                Ok(())
+            }
         }
-    });
+    };
 
+    let argument_checks = argument_checks(&unmarked_types);
+    let async_trait_attr = async_trait_attr();
     let expanded = quote! {
-        #input_fn
+        #argument_checks
 
-        #[derive(Default)]
-        struct #struct_name;
+        #struct_def
 
-        #[async_trait::async_trait(?Send)]
+        #async_trait_attr
         impl kuri::PromptHandler for #struct_name {
             fn name(&self) -> &'static str {
                 #tool_name
             }
 
             fn description(&self) -> Option<&'static str> {
-                Some(#tool_description)
+                #tool_description
             }
 
             fn arguments(&self) -> Option<Vec<kuri::PromptArgument>> {
@@ -186,44 +262,37 @@ pub fn prompt(args: TokenStream, input: TokenStream) -> TokenStream {
                 ])
             }
 
-            async fn call(&self, context: &kuri::context::Context, args: std::collections::HashMap<String, serde_json::Value>) -> Result<String, kuri::PromptError> {
+            #verify_state_impl
+
+            async fn call(&self, context: &kuri::context::RequestContext, args: std::collections::HashMap<String, serde_json::Value>) -> Result<kuri::GetPromptResult, kuri::PromptError> {
                 // Extract parameters from the HashMap
                 #(#param_extracts)*
 
                 // Call the function with extracted parameters
-                let result = #fn_name(#(#ctx_params)* #(#param_names,)*).await;
+                let result = #call;
 
-                // Return the result directly, as it's already a String
-                Ok(result)
+                #into_result
             }
         }
     };
 
-    TokenStream::from(expanded)
+    Ok((struct_name, expanded))
 }
 
-/// Determine if a type is optional (Option<T>) and what the base type is
-fn get_type_info(ty: &syn::Type) -> (bool, String) {
-    if let syn::Type::Path(type_path) = ty {
-        let path = &type_path.path;
-        if let Some(segment) = path.segments.last() {
-            if segment.ident == "Option" {
-                // Extract the inner type from Option<T>
-                #[allow(clippy::collapsible_match)]
-                if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
-                    if let Some(syn::GenericArgument::Type(inner_ty)) = args.args.first() {
-                        if let syn::Type::Path(inner_path) = inner_ty {
-                            if let Some(inner_segment) = inner_path.path.segments.last() {
-                                return (true, inner_segment.ident.to_string());
-                            }
-                        }
-                    }
-                }
-                return (true, "unknown".to_string());
-            } else {
-                return (false, segment.ident.to_string());
-            }
-        }
+/// The inner type of an `Option<T>`, or `None` if the type isn't optional
+fn option_inner_type(ty: &Type) -> Option<&Type> {
+    let Type::Path(type_path) = ty else {
+        return None;
+    };
+    let segment = type_path.path.segments.last()?;
+    if segment.ident != "Option" {
+        return None;
+    }
+    match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first()? {
+            syn::GenericArgument::Type(inner_ty) => Some(inner_ty),
+            _ => None,
+        },
+        _ => None,
     }
-    (false, "unknown".to_string())
 }
@@ -1,79 +1,185 @@
 use convert_case::{Case, Casing};
 use proc_macro::TokenStream;
-use quote::{format_ident, quote};
-use std::collections::HashMap;
+use quote::{format_ident, quote, quote_spanned};
 use syn::{
-    parse::Parse, parse::ParseStream, parse_macro_input, punctuated::Punctuated, Expr, ExprLit,
-    FnArg, ItemFn, Lit, Meta, Pat, PatType, Token,
+    parse::Parse, parse::ParseStream, parse_macro_input, punctuated::Punctuated, spanned::Spanned,
+    Attribute, Expr, ExprLit, FnArg, Ident, ItemFn, Lit, Meta, MetaNameValue, Pat, PatType,
+    ReturnType, Signature, Token, Type,
 };
 
-use crate::is_injected_type;
+use crate::{
+    argument_checks, async_trait_attr, check_signature, doc_comment, handler_struct, set_once,
+    string_value, take_doc_comment, take_extractor, take_marker, unknown_argument, Param,
+    ParamDefault, ParamKind, Params,
+};
 
-struct MacroArgs {
-    name: Option<String>,
+pub(crate) struct MacroArgs {
+    pub(crate) name: Option<String>,
     description: Option<String>,
-    param_descriptions: HashMap<String, String>,
+    params: Params,
+    annotations: Annotations,
+    /// Whether to run the function on a blocking thread, with `#[tool(blocking)]`
+    blocking: bool,
+}
+
+/// Tool annotations (hints about the tool's behaviour), eg `#[tool(read_only, title = "...")]`
+#[derive(Default)]
+struct Annotations {
+    title: Option<String>,
+    read_only: Option<bool>,
+    destructive: Option<bool>,
+    idempotent: Option<bool>,
+    open_world: Option<bool>,
+}
+
+impl Annotations {
+    /// Get the hint with the given name, as used in the macro's arguments
+    fn hint_mut(&mut self, name: &str) -> Option<&mut Option<bool>> {
+        match name {
+            "read_only" => Some(&mut self.read_only),
+            "destructive" => Some(&mut self.destructive),
+            "idempotent" => Some(&mut self.idempotent),
+            "open_world" => Some(&mut self.open_world),
+            _ => None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.read_only.is_none()
+            && self.destructive.is_none()
+            && self.idempotent.is_none()
+            && self.open_world.is_none()
+    }
 }
 
+/// Generate tokens for an `Option<T>`
+fn quote_option<T: quote::ToTokens>(value: &Option<T>) -> proc_macro2::TokenStream {
+    match value {
+        Some(value) => quote! { Some(#value.into()) },
+        None => quote! { None },
+    }
+}
+
+/// The arguments `#[tool]` accepts
+const ARGUMENTS: &[&str] = &[
+    "name",
+    "description",
+    "params",
+    "blocking",
+    "title",
+    "read_only",
+    "destructive",
+    "idempotent",
+    "open_world",
+];
+
 impl Parse for MacroArgs {
     fn parse(input: ParseStream) -> syn::Result<Self> {
         let mut name = None;
         let mut description = None;
-        let mut param_descriptions = HashMap::new();
+        let mut params = None;
+        let mut blocking = None;
+        let mut annotations = Annotations::default();
 
         let meta_list: Punctuated<Meta, Token![,]> = Punctuated::parse_terminated(input)?;
 
         for meta in meta_list {
-            match meta {
-                Meta::NameValue(nv) => {
-                    let ident = nv.path.get_ident().unwrap().to_string();
-                    if let Expr::Lit(ExprLit {
-                        lit: Lit::Str(lit_str),
-                        ..
-                    }) = nv.value
-                    {
-                        match ident.as_str() {
-                            "name" => name = Some(lit_str.value()),
-                            "description" => description = Some(lit_str.value()),
-                            _ => {}
-                        }
-                    }
+            let key = meta.path().require_ident()?;
+            match (&meta, key.to_string().as_str()) {
+                (Meta::NameValue(nv), "name") => set_once(&mut name, string_value(nv)?, key)?,
+                (Meta::NameValue(nv), "description") => {
+                    set_once(&mut description, string_value(nv)?, key)?
                 }
-                Meta::List(list) if list.path.is_ident("params") => {
-                    let nested: Punctuated<Meta, Token![,]> =
-                        list.parse_args_with(Punctuated::parse_terminated)?;
-
-                    for meta in nested {
-                        if let Meta::NameValue(nv) = meta {
-                            if let Expr::Lit(ExprLit {
-                                lit: Lit::Str(lit_str),
-                                ..
-                            }) = nv.value
-                            {
-                                let param_name = nv.path.get_ident().unwrap().to_string();
-                                param_descriptions.insert(param_name, lit_str.value());
-                            }
+                (Meta::NameValue(nv), "title") => {
+                    set_once(&mut annotations.title, string_value(nv)?, key)?
+                }
+                (Meta::List(list), "params") => set_once(&mut params, Params::parse(list)?, key)?,
+                (Meta::Path(_), "blocking") => set_once(&mut blocking, true, key)?,
+                (meta, hint_name) if annotations.hint_mut(hint_name).is_some() => {
+                    let value = match meta {
+                        // Hints given as flags are set, eg `read_only`
+                        Meta::Path(_) => true,
+                        // Hints may be set explicitly, eg `destructive = false`
+                        Meta::NameValue(MetaNameValue {
+                            value:
+                                Expr::Lit(ExprLit {
+                                    lit: Lit::Bool(lit_bool),
+                                    ..
+                                }),
+                            ..
+                        }) => lit_bool.value,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                meta,
+                                format!("expected `{hint_name}`, or `{hint_name} = true/false`"),
+                            ))
                         }
-                    }
+                    };
+                    set_once(annotations.hint_mut(hint_name).unwrap(), value, key)?;
+                }
+                (_, "name" | "description" | "title") => {
+                    return Err(syn::Error::new_spanned(
+                        &meta,
+                        format!("expected `{key} = \"...\"`"),
+                    ))
+                }
+                (_, "blocking") => {
+                    return Err(syn::Error::new_spanned(&meta, "expected `blocking`"))
+                }
+                (_, "params") => {
+                    return Err(syn::Error::new_spanned(
+                        &meta,
+                        "expected `params(name = \"description\", ...)`",
+                    ))
                 }
-                _ => {}
+                _ => return Err(unknown_argument(meta.path(), "tool", ARGUMENTS)),
             }
         }
 
         Ok(MacroArgs {
             name,
             description,
-            param_descriptions,
+            params: params.unwrap_or_default(),
+            annotations,
+            blocking: blocking.unwrap_or_default(),
         })
     }
 }
 
 pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
     let args = parse_macro_input!(args as MacroArgs);
-    let input_fn = parse_macro_input!(input as ItemFn);
+    let mut input_fn = parse_macro_input!(input as ItemFn);
+
+    match expand(args, &input_fn.attrs, &mut input_fn.sig, None) {
+        Ok((_, handler)) => quote! {
+            #input_fn
+            #handler
+        }
+        .into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Generate a tool handler for a function, or for a method of `self_ty` in a `#[tool_box]`,
+/// returning the name of the handler struct along with its definition. The attributes the macro
+/// consumes are removed from the function's parameters.
+pub(crate) fn expand(
+    mut args: MacroArgs,
+    attrs: &[Attribute],
+    sig: &mut Signature,
+    self_ty: Option<&Type>,
+) -> syn::Result<(Ident, proc_macro2::TokenStream)> {
+    check_signature(sig, "tool", self_ty.is_some())?;
+    if let (true, Some(asyncness)) = (args.blocking, sig.asyncness) {
+        return Err(syn::Error::new_spanned(
+            asyncness,
+            "`#[tool(blocking)]` functions run on a blocking thread, so can't be `async`",
+        ));
+    }
 
     // Extract function details
-    let fn_name = &input_fn.sig.ident;
+    let fn_name = sig.ident.clone();
     let fn_name_str = fn_name.to_string();
 
     // Generate PascalCase struct name from the function name
@@ -81,81 +187,229 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
 
     // Use provided name or function name as default
     let tool_name = args.name.unwrap_or(fn_name_str);
-    let tool_description = args.description.unwrap_or_default();
+    // The description falls back to the function's doc comment
+    let tool_description = args
+        .description
+        .or_else(|| doc_comment(attrs))
+        .unwrap_or_default();
 
-    // Extract parameter names, types, and descriptions
-    let mut ctx_params = Vec::new();
+    // Extract parameter names, types, and descriptions. `call_args` are the expressions passed to
+    // the function, in order: extractors are obtained from the request context, while arguments
+    // are taken from the deserialised parameters.
+    let params_struct_name = format_ident!("{}Parameters", struct_name);
     let mut param_defs = Vec::new();
-    let mut param_names = Vec::new();
+    let mut default_fns = Vec::new();
+    let mut call_args = Vec::new();
+    let mut arg_names = Vec::new();
+    let mut extractor_types = Vec::new();
+    let mut extractor_names = Vec::new();
+    let mut unmarked_types = Vec::new();
 
-    for arg in input_fn.sig.inputs.iter() {
-        if let FnArg::Typed(PatType { pat, ty, .. }) = arg {
+    for arg in sig.inputs.iter_mut() {
+        if let FnArg::Typed(pat_type) = arg {
+            let kind = take_extractor(pat_type)?;
+            let is_flattened = take_marker(pat_type, "flatten");
+            let doc = take_doc_comment(pat_type);
+            let PatType { pat, ty, .. } = pat_type;
             if let Pat::Ident(param_ident) = &**pat {
-                if is_injected_type(ty) {
-                    ctx_params.push(param_ident);
+                let param_name = &param_ident.ident;
+                let param_name_str = param_name.to_string();
+                arg_names.push(format_ident!("arg_{}", param_name));
+                if kind == ParamKind::Extractor {
+                    if is_flattened {
+                        return Err(syn::Error::new_spanned(
+                            param_name,
+                            format!("`{param_name}` is an extractor, so can't be flattened"),
+                        ));
+                    }
+                    call_args.push(quote! {
+                        <#ty as kuri::extract::FromRequestContext>::from_request_context(context)?
+                    });
+                    extractor_types.push(ty.clone());
+                    extractor_names.push(param_name_str);
                     continue;
                 }
+                if kind == ParamKind::Unmarked {
+                    unmarked_types.push((**ty).clone());
+                }
+                call_args.push(quote! { params.#param_name });
 
-                let param_name = &param_ident.ident;
-                let param_name_str = param_name.to_string();
-                let description = args
-                    .param_descriptions
-                    .get(&param_name_str)
-                    .map(|s| s.as_str())
-                    .unwrap_or("");
+                // A flattened parameter's fields are arguments in their own right, described by
+                // its type
+                let options = args.params.take(&param_name_str);
+                if is_flattened {
+                    if let Some(options) = options {
+                        return Err(syn::Error::new_spanned(
+                            options.ident,
+                            format!("`{param_name}` is flattened, so its fields are the arguments; give their options on its type instead"),
+                        ));
+                    }
+                    param_defs.push(quote! {
+                        #[serde(flatten)]
+                        #param_name: #ty
+                    });
+                    continue;
+                }
+
+                // A description in `params(...)` takes precedence over the parameter's doc comment
+                let Param {
+                    description,
+                    rename,
+                    default,
+                    ..
+                } = options.unwrap_or_else(|| Param::new(param_name.clone()));
+                let description = description.or(doc).map(|description| {
+                    quote! { #[schemars(description = #description)] }
+                });
+                let rename = rename.map(|rename| quote! { #[serde(rename = #rename)] });
+                let default = default.map(|default| match default {
+                    ParamDefault::Default => quote! { #[serde(default)] },
+                    default => {
+                        let default_fn = format_ident!("default_{}", param_name);
+                        let path = format!("{params_struct_name}::{default_fn}");
+                        let value = default.value();
+                        default_fns.push(quote! {
+                            fn #default_fn() -> #ty {
+                                #value
+                            }
+                        });
+                        quote! { #[serde(default = #path)] }
+                    }
+                });
 
-                param_names.push(param_name);
                 param_defs.push(quote! {
-                    #[schemars(description = #description)]
+                    #description
+                    #rename
+                    #default
                     #param_name: #ty
                 });
             }
         }
     }
+    args.params.finish(&fn_name, &extractor_names)?;
+    let (struct_def, call) = if args.blocking {
+        // The arguments and extractors are moved to the blocking thread, along with the tool box
+        // (if any), so they're obtained beforehand
+        let receiver = sig
+            .receiver()
+            .map(|_| quote! { let this = self.0.clone(); });
+        let moved_args: Vec<_> = arg_names.iter().map(|name| quote! { #name }).collect();
+        let (struct_def, call) =
+            handler_struct(&struct_name, sig, self_ty, quote! { this }, &moved_args);
+        let call = quote! {{
+            #(let #arg_names = #call_args;)*
+            #receiver
+            kuri::__private::spawn_blocking(move || #call).await?
+        }};
+        (struct_def, call)
+    } else {
+        handler_struct(&struct_name, sig, self_ty, quote! { self.0 }, &call_args)
+    };
 
     // Generate the implementation
-    let params_struct_name = format_ident!("{}Parameters", struct_name);
-    let ctx_param_tokens: Vec<_> = (0..ctx_params.len())
-        .map(|_| {
-            quote! {
-                <kuri::context::Inject<_> as kuri::context::FromContext>::from_context(&context),
+    // Only override the default (no annotations) if any were given
+    let annotations_impl = if args.annotations.is_empty() {
+        quote! {}
+    } else {
+        let Annotations {
+            title,
+            read_only,
+            destructive,
+            idempotent,
+            open_world,
+        } = &args.annotations;
+        let (title, read_only, destructive, idempotent, open_world) = (
+            quote_option(title),
+            quote_option(read_only),
+            quote_option(destructive),
+            quote_option(idempotent),
+            quote_option(open_world),
+        );
+        quote! {
+            fn annotations(&self) -> Option<kuri::ToolAnnotations> {
+                Some(kuri::ToolAnnotations {
+                    title: #title,
+                    read_only_hint: #read_only,
+                    destructive_hint: #destructive,
+                    idempotent_hint: #idempotent,
+                    open_world_hint: #open_world,
+                })
             }
-        })
-        .collect();
+        }
+    };
+
+    // Advertise the return type's output schema (eg for `Json<T>`). `impl Trait` can't be named
+    // here, so such handlers fall back to the default (no output schema).
+    // The conversion is spanned by the return type, so that an unsupported type is reported there.
+    let (return_type, return_span) = match &sig.output {
+        ReturnType::Default => (Some(quote! { () }), sig.span()),
+        ReturnType::Type(_, ty) if matches!(**ty, Type::ImplTrait(_)) => (None, ty.span()),
+        ReturnType::Type(_, ty) => (Some(quote! { #ty }), ty.span()),
+    };
+    let output_schema_impl = match &return_type {
+        Some(return_type) => quote_spanned! {return_span=>
+            fn output_schema(&self) -> Option<serde_json::Value> {
+                <#return_type as kuri::response::IntoCallToolResult>::output_schema()
+            }
+        },
+        None => quote! {},
+    };
+    let return_type = return_type.unwrap_or_else(|| quote! { _ });
+    let into_result = quote_spanned! {return_span=>
+        <#return_type as kuri::response::IntoCallToolResult>::into_call_tool_result(result)
+    };
 
     // Generate different implementations based on whether there are any parameters
     let call_impl = if param_defs.is_empty() {
         // No parameters case
         quote! {
-            // No parameters to deserialize - call function with just context parameters (if any)
-            let result = #fn_name(#(#ctx_param_tokens)*).await;
-            <_ as kuri::response::IntoCallToolResult>::into_call_tool_result(result)
+            // No parameters to deserialize - call function with just extractors (if any)
+            let result = #call;
+            #into_result
         }
     } else {
         // With parameters case
         quote! {
-            // Deserialize parameters
+            // Deserialize parameters. Arguments may be omitted entirely, if none are required.
+            let params = if params.is_null() { serde_json::json!({}) } else { params };
             let params: #params_struct_name = serde_json::from_value(params)
-                .map_err(|e| kuri::ToolError::InvalidParameters("Missing or incorrect tool arguments".into()))?;
+                .map_err(|e| kuri::ToolError::InvalidParameters(format!("Missing or incorrect tool arguments: {e}")))?;
 
             // Call function with parameters
-            let result = #fn_name(#(#ctx_param_tokens)* #(params.#param_names,)*).await;
-            <_ as kuri::response::IntoCallToolResult>::into_call_tool_result(result)
+            let result = #call;
+            #into_result
+        }
+    };
+
+    // Declare the handler's dependencies on the service's state, if it has any extractors
+    let verify_state_impl = if extractor_types.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn verify_state(&self, state: &kuri::context::Context) -> Result<(), kuri::extract::ExtractError> {
+                #(<#extractor_types as kuri::extract::FromRequestContext>::verify_state(state)?;)*
+                Ok(())
+            }
         }
     };
 
+    let argument_checks = argument_checks(&unmarked_types);
+    let async_trait_attr = async_trait_attr();
     let expanded = quote! {
+        #argument_checks
+
         #[derive(serde::Deserialize, schemars::JsonSchema)]
         struct #params_struct_name {
             #(#param_defs,)*
         }
 
-        #input_fn
+        impl #params_struct_name {
+            #(#default_fns)*
+        }
 
-        #[derive(Default)]
-        struct #struct_name;
+        #struct_def
 
-        #[async_trait::async_trait(?Send)]
+        #async_trait_attr
         impl kuri::ToolHandler for #struct_name {
             fn name(&self) -> &'static str {
                 #tool_name
@@ -170,12 +424,18 @@ pub fn tool(args: TokenStream, input: TokenStream) -> TokenStream {
                     .expect("Failed to generate schema")
             }
 
+            #output_schema_impl
+
+            #annotations_impl
+
+            #verify_state_impl
+
             #[allow(unused_variables)]
-            async fn call(&self, context: &kuri::context::Context, params: serde_json::Value) -> Result<kuri::CallToolResult, kuri::ToolError> {
+            async fn call(&self, context: &kuri::context::RequestContext, params: serde_json::Value) -> Result<kuri::CallToolResult, kuri::ToolError> {
                 { #call_impl }
             }
         }
     };
 
-    TokenStream::from(expanded)
+    Ok((struct_name, expanded))
 }
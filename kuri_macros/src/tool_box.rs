@@ -0,0 +1,159 @@
+use proc_macro::TokenStream;
+use quote::quote;
+use std::collections::HashMap;
+use syn::{parse_macro_input, Attribute, Ident, ImplItem, ItemImpl, Meta};
+
+use crate::{combine_errors, prompt, tool};
+
+/// The kinds of handler that may be defined in a tool box
+enum Kind {
+    Tool,
+    Prompt,
+}
+
+pub fn tool_box(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return syn::Error::new_spanned(
+            proc_macro2::TokenStream::from(args),
+            "`#[tool_box]` takes no arguments",
+        )
+        .to_compile_error()
+        .into();
+    }
+    let mut item_impl = parse_macro_input!(input as ItemImpl);
+
+    match expand(&mut item_impl) {
+        Ok(expanded) => expanded.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+fn expand(item_impl: &mut ItemImpl) -> syn::Result<proc_macro2::TokenStream> {
+    if let Some((_, path, _)) = &item_impl.trait_ {
+        return Err(syn::Error::new_spanned(
+            path,
+            "`#[tool_box]` must be used on an inherent impl block, eg `impl MyTools { ... }`",
+        ));
+    }
+    if !item_impl.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item_impl.generics,
+            "`#[tool_box]` can't be used on a generic impl block",
+        ));
+    }
+    let self_ty = item_impl.self_ty.clone();
+
+    let mut handlers = Vec::new();
+    let mut tools = Vec::new();
+    let mut prompts = Vec::new();
+    let mut errors = Vec::new();
+    // The methods defining each tool and prompt, by name, to catch two with the same name
+    let mut tool_names = HashMap::new();
+    let mut prompt_names = HashMap::new();
+
+    for item in item_impl.items.iter_mut() {
+        let ImplItem::Fn(method) = item else {
+            continue;
+        };
+        let (kind, attr) = match take_handler_attr(&mut method.attrs) {
+            Ok(Some(handler)) => handler,
+            Ok(None) => continue,
+            Err(e) => {
+                errors.push(e);
+                continue;
+            }
+        };
+
+        // The handler's own macro arguments, which are empty for a bare `#[tool]`
+        let args = match &attr.meta {
+            Meta::Path(_) => proc_macro2::TokenStream::new(),
+            meta => meta.require_list()?.tokens.clone(),
+        };
+        let expanded = match kind {
+            Kind::Tool => syn::parse2::<tool::MacroArgs>(args).and_then(|args| {
+                let name = args.name.clone();
+                check_unique(&mut tool_names, "tool", name, &attr, &method.sig.ident)?;
+                tool::expand(args, &method.attrs, &mut method.sig, Some(&self_ty))
+            }),
+            Kind::Prompt => syn::parse2::<prompt::MacroArgs>(args).and_then(|args| {
+                let name = args.name.clone();
+                check_unique(&mut prompt_names, "prompt", name, &attr, &method.sig.ident)?;
+                prompt::expand(args, &method.attrs, &mut method.sig, Some(&self_ty))
+            }),
+        };
+        match expanded {
+            Ok((struct_name, handler)) => {
+                handlers.push(handler);
+                match kind {
+                    Kind::Tool => tools.push(struct_name),
+                    Kind::Prompt => prompts.push(struct_name),
+                }
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+    combine_errors(errors)?;
+
+    // The handlers are defined in an anonymous scope, so that their names don't collide with
+    // those of other tool boxes (or free functions) in the module
+    Ok(quote! {
+        #item_impl
+
+        const _: () = {
+            #(#handlers)*
+
+            impl kuri::ToolBox for #self_ty {
+                fn tools(this: &std::sync::Arc<Self>) -> Vec<Box<dyn kuri::ToolHandler>> {
+                    vec![#(Box::new(#tools(this.clone())) as Box<dyn kuri::ToolHandler>),*]
+                }
+
+                fn prompts(this: &std::sync::Arc<Self>) -> Vec<Box<dyn kuri::PromptHandler>> {
+                    vec![#(Box::new(#prompts(this.clone())) as Box<dyn kuri::PromptHandler>),*]
+                }
+            }
+        };
+    })
+}
+
+/// Record the name a method's handler is served under (its `name` argument, or else the method's
+/// own name), failing if another method in the tool box already uses it.
+fn check_unique(
+    names: &mut HashMap<String, Ident>,
+    kind: &str,
+    name: Option<String>,
+    attr: &Attribute,
+    method: &Ident,
+) -> syn::Result<()> {
+    let name = name.unwrap_or_else(|| method.to_string());
+    match names.get(&name) {
+        Some(first) => Err(syn::Error::new_spanned(
+            attr,
+            format!("method `{first}` already defines a {kind} named `{name}` in this tool box"),
+        )),
+        None => {
+            names.insert(name, method.clone());
+            Ok(())
+        }
+    }
+}
+
+/// Remove the `#[tool]` or `#[prompt]` attribute from a method, if it has one.
+fn take_handler_attr(attrs: &mut Vec<Attribute>) -> syn::Result<Option<(Kind, Attribute)>> {
+    let kind = |attr: &Attribute| match attr.path().segments.last() {
+        Some(segment) if segment.ident == "tool" => Some(Kind::Tool),
+        Some(segment) if segment.ident == "prompt" => Some(Kind::Prompt),
+        _ => None,
+    };
+
+    let Some(index) = attrs.iter().position(|attr| kind(attr).is_some()) else {
+        return Ok(None);
+    };
+    let attr = attrs.remove(index);
+    if let Some(other) = attrs.iter().find(|attr| kind(attr).is_some()) {
+        return Err(syn::Error::new_spanned(
+            other,
+            "a method can only be one tool or prompt",
+        ));
+    }
+    Ok(Some((kind(&attr).unwrap(), attr)))
+}
@@ -0,0 +1,250 @@
+//! Helpers for binary content (images, audio and blobs), which MCP sends base64-encoded.
+//!
+//! Constructors such as [`Content::image_bytes`] and [`ResourceContents::blob_bytes`] take raw
+//! bytes, encode them, and identify their MIME type from the data's magic number. So that a
+//! handler can't accidentally send a huge payload, data larger than [`DEFAULT_MAX_SIZE`] is
+//! rejected. Each constructor has a `_with_limit` variant (eg [`Content::image_bytes_with_limit`])
+//! taking a different limit.
+//!
+//! [`Content::image_bytes`]: crate::Content::image_bytes
+//! [`Content::image_bytes_with_limit`]: crate::Content::image_bytes_with_limit
+//! [`ResourceContents::blob_bytes`]: crate::resource::ResourceContents::blob_bytes
+
+use base64::{prelude::BASE64_STANDARD, Engine};
+use std::path::Path;
+use thiserror::Error;
+
+/// The largest binary payload, in bytes (before encoding), that may be encoded unless another
+/// limit is given: 10 MiB.
+pub const DEFAULT_MAX_SIZE: usize = 10 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum BinaryError {
+    #[error("Binary data is {size} bytes, over the limit of {limit} bytes")]
+    TooLarge { size: usize, limit: usize },
+    #[error("Couldn't identify the type of the data")]
+    UnknownMimeType,
+    #[error("Expected {expected} data, but the data is {mime_type}")]
+    UnexpectedMimeType {
+        expected: &'static str,
+        mime_type: &'static str,
+    },
+    #[error("Failed to read {0}: {1}")]
+    Io(String, std::io::Error),
+}
+
+/// Magic numbers, as the offset and bytes they're found at, and the MIME type they identify.
+const SIGNATURES: &[(usize, &[u8], &str)] = &[
+    (0, b"\x89PNG\r\n\x1a\n", "image/png"),
+    (0, b"\xff\xd8\xff", "image/jpeg"),
+    (0, b"GIF87a", "image/gif"),
+    (0, b"GIF89a", "image/gif"),
+    (8, b"WEBP", "image/webp"),
+    (0, b"BM", "image/bmp"),
+    (0, b"II*\0", "image/tiff"),
+    (0, b"MM\0*", "image/tiff"),
+    (0, b"\0\0\x01\0", "image/x-icon"),
+    (8, b"WAVE", "audio/wav"),
+    (0, b"ID3", "audio/mpeg"),
+    (0, b"\xff\xfb", "audio/mpeg"),
+    (0, b"\xff\xf3", "audio/mpeg"),
+    (0, b"\xff\xf2", "audio/mpeg"),
+    (0, b"OggS", "audio/ogg"),
+    (0, b"fLaC", "audio/flac"),
+    (4, b"ftypM4A", "audio/mp4"),
+    (0, b"%PDF-", "application/pdf"),
+    (0, b"PK\x03\x04", "application/zip"),
+    (0, b"\x1f\x8b", "application/gzip"),
+];
+
+/// Identify the MIME type of some data from its magic number, eg `image/png`. Returns `None` if
+/// the type isn't recognised.
+pub fn sniff_mime_type(data: &[u8]) -> Option<&'static str> {
+    // WebP and WAV files are both RIFF containers, identified by the form type at offset 8
+    let is_riff = data.starts_with(b"RIFF");
+    SIGNATURES
+        .iter()
+        .find(|(offset, magic, _)| {
+            (*offset != 8 || is_riff) && data.get(*offset..*offset + magic.len()) == Some(magic)
+        })
+        .map(|(_, _, mime_type)| *mime_type)
+}
+
+/// Base64-encode some data, if it isn't over the [`DEFAULT_MAX_SIZE`].
+pub fn encode(data: &[u8]) -> Result<String, BinaryError> {
+    encode_with_limit(data, DEFAULT_MAX_SIZE)
+}
+
+/// Base64-encode some data, if it isn't over `limit` bytes.
+pub fn encode_with_limit(data: &[u8], limit: usize) -> Result<String, BinaryError> {
+    check_size(data.len(), limit)?;
+    Ok(BASE64_STANDARD.encode(data))
+}
+
+/// Encode some data of a kind of MIME type (eg `image`), returning the encoded data and the type.
+pub(crate) fn encode_as(
+    data: &[u8],
+    expected: &'static str,
+    limit: usize,
+) -> Result<(String, &'static str), BinaryError> {
+    let mime_type = sniff_mime_type(data).ok_or(BinaryError::UnknownMimeType)?;
+    if mime_type.split('/').next() != Some(expected) {
+        return Err(BinaryError::UnexpectedMimeType {
+            expected,
+            mime_type,
+        });
+    }
+    Ok((encode_with_limit(data, limit)?, mime_type))
+}
+
+/// Read a file, checking its size before reading it.
+pub(crate) fn read_file(path: &Path, limit: usize) -> Result<Vec<u8>, BinaryError> {
+    let io_error = |e| BinaryError::Io(path.display().to_string(), e);
+    let size = std::fs::metadata(path).map_err(io_error)?.len();
+    check_size(usize::try_from(size).unwrap_or(usize::MAX), limit)?;
+    std::fs::read(path).map_err(io_error)
+}
+
+fn check_size(size: usize, limit: usize) -> Result<(), BinaryError> {
+    if size > limit {
+        return Err(BinaryError::TooLarge { size, limit });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prompt::{PromptMessage, PromptMessageRole},
+        resource::ResourceContents,
+        Content,
+    };
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+    const WAV: &[u8] = b"RIFF\x24\0\0\0WAVEfmt ";
+
+    #[test]
+    fn test_sniff_mime_type() {
+        assert_eq!(sniff_mime_type(PNG), Some("image/png"));
+        assert_eq!(
+            sniff_mime_type(b"\xff\xd8\xff\xe0\0\x10JFIF"),
+            Some("image/jpeg")
+        );
+        assert_eq!(sniff_mime_type(b"RIFF\0\0\0\0WEBPVP8 "), Some("image/webp"));
+        assert_eq!(sniff_mime_type(WAV), Some("audio/wav"));
+        assert_eq!(sniff_mime_type(b"ID3\x04\0"), Some("audio/mpeg"));
+        assert_eq!(sniff_mime_type(b"\0\0\0\x20ftypM4A "), Some("audio/mp4"));
+        assert_eq!(sniff_mime_type(b"%PDF-1.7"), Some("application/pdf"));
+
+        // Form types only count in RIFF containers
+        assert_eq!(sniff_mime_type(b"NOT RIFFWAVE"), None);
+        assert_eq!(sniff_mime_type(b"plain text"), None);
+        assert_eq!(sniff_mime_type(b""), None);
+    }
+
+    #[test]
+    fn test_image_and_audio_bytes() {
+        let image = Content::image_bytes(PNG).unwrap();
+        assert_eq!(
+            image,
+            Content::image(BASE64_STANDARD.encode(PNG), "image/png")
+        );
+        let audio = Content::audio_bytes(WAV).unwrap();
+        assert_eq!(
+            audio,
+            Content::audio(BASE64_STANDARD.encode(WAV), "audio/wav")
+        );
+
+        assert!(matches!(
+            Content::image_bytes(WAV),
+            Err(BinaryError::UnexpectedMimeType {
+                expected: "image",
+                mime_type: "audio/wav"
+            })
+        ));
+        assert!(matches!(
+            Content::audio_bytes(b"plain text"),
+            Err(BinaryError::UnknownMimeType)
+        ));
+    }
+
+    #[test]
+    fn test_prompt_image_bytes() {
+        let message = PromptMessage::new_image_bytes(PromptMessageRole::User, PNG, None).unwrap();
+        let expected = PromptMessage::new_image(
+            PromptMessageRole::User,
+            BASE64_STANDARD.encode(PNG),
+            "image/png".to_string(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(message, expected);
+    }
+
+    #[test]
+    fn test_audio_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(WAV).unwrap();
+        let audio = Content::audio_file(file.path()).unwrap();
+        assert_eq!(
+            audio,
+            Content::audio(BASE64_STANDARD.encode(WAV), "audio/wav")
+        );
+
+        let missing = Content::audio_file("/no/such/file.wav");
+        assert!(matches!(missing, Err(BinaryError::Io(..))));
+    }
+
+    #[test]
+    fn test_blob_bytes() {
+        let blob = ResourceContents::blob_bytes("file:///logo.png", PNG).unwrap();
+        assert_eq!(
+            blob,
+            ResourceContents::BlobResourceContents {
+                uri: "file:///logo.png".to_string(),
+                mime_type: Some("image/png".to_string()),
+                blob: BASE64_STANDARD.encode(PNG),
+            }
+        );
+
+        // Blobs of an unknown type have no MIME type
+        let blob = ResourceContents::blob_bytes("file:///data.bin", b"\x01\x02").unwrap();
+        assert!(matches!(
+            blob,
+            ResourceContents::BlobResourceContents {
+                mime_type: None,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_size_limit() {
+        assert!(matches!(
+            check_size(DEFAULT_MAX_SIZE + 1, DEFAULT_MAX_SIZE),
+            Err(BinaryError::TooLarge {
+                size,
+                limit: DEFAULT_MAX_SIZE
+            }) if size == DEFAULT_MAX_SIZE + 1
+        ));
+        assert!(check_size(DEFAULT_MAX_SIZE, DEFAULT_MAX_SIZE).is_ok());
+
+        // Each constructor may be given its own limit
+        assert!(matches!(
+            Content::image_bytes_with_limit(PNG, 4),
+            Err(BinaryError::TooLarge { size: 16, limit: 4 })
+        ));
+        assert!(Content::image_bytes_with_limit(PNG, 16).is_ok());
+        assert!(matches!(
+            ResourceContents::blob_bytes_with_limit("file:///logo.png", PNG, 4),
+            Err(BinaryError::TooLarge { size: 16, limit: 4 })
+        ));
+        assert!(matches!(
+            encode_with_limit(WAV, 4),
+            Err(BinaryError::TooLarge { size: 16, limit: 4 })
+        ));
+    }
+}
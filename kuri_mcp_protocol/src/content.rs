@@ -1,5 +1,9 @@
-use crate::resource::ResourceContents;
+use crate::{
+    binary::{self, BinaryError},
+    resource::{Resource, ResourceContents},
+};
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -70,6 +74,10 @@ pub enum Content {
     Image(ImageContent),
     Resource(EmbeddedResource),
     Audio(AudioContent),
+    /// A link to a resource the server can read, given instead of the resource's contents. Added
+    /// in the 2025-06-18 version of the protocol; see [`Content::for_protocol_version`].
+    #[serde(rename = "resource_link")]
+    ResourceLink(Resource),
 }
 
 impl Content {
@@ -114,6 +122,82 @@ impl Content {
         })
     }
 
+    /// A link to a resource, so that the client can read it if needed, rather than embedding its
+    /// contents. The link describes the resource as `resources/list` does.
+    pub fn resource_link(resource: Resource) -> Self {
+        Content::ResourceLink(resource)
+    }
+
+    /// Image content from raw image data, which is base64-encoded, and whose MIME type is
+    /// identified from the data. Fails if the data isn't a recognised image, or is over the
+    /// [`binary::DEFAULT_MAX_SIZE`].
+    pub fn image_bytes(data: &[u8]) -> Result<Self, BinaryError> {
+        Content::image_bytes_with_limit(data, binary::DEFAULT_MAX_SIZE)
+    }
+
+    /// Like [`Content::image_bytes`], but failing if the data is over `limit` bytes.
+    pub fn image_bytes_with_limit(data: &[u8], limit: usize) -> Result<Self, BinaryError> {
+        let (data, mime_type) = binary::encode_as(data, "image", limit)?;
+        Ok(Content::image(data, mime_type))
+    }
+
+    /// Image content from an image file. See [`Content::image_bytes`].
+    pub fn image_file<P: AsRef<Path>>(path: P) -> Result<Self, BinaryError> {
+        Content::image_file_with_limit(path, binary::DEFAULT_MAX_SIZE)
+    }
+
+    /// Like [`Content::image_file`], but failing if the file is over `limit` bytes.
+    pub fn image_file_with_limit<P: AsRef<Path>>(
+        path: P,
+        limit: usize,
+    ) -> Result<Self, BinaryError> {
+        Content::image_bytes_with_limit(&binary::read_file(path.as_ref(), limit)?, limit)
+    }
+
+    /// Audio content from raw audio data, which is base64-encoded, and whose MIME type is
+    /// identified from the data. Fails if the data isn't recognised audio, or is over the
+    /// [`binary::DEFAULT_MAX_SIZE`].
+    pub fn audio_bytes(data: &[u8]) -> Result<Self, BinaryError> {
+        Content::audio_bytes_with_limit(data, binary::DEFAULT_MAX_SIZE)
+    }
+
+    /// Like [`Content::audio_bytes`], but failing if the data is over `limit` bytes.
+    pub fn audio_bytes_with_limit(data: &[u8], limit: usize) -> Result<Self, BinaryError> {
+        let (data, mime_type) = binary::encode_as(data, "audio", limit)?;
+        Ok(Content::audio(data, mime_type))
+    }
+
+    /// Audio content from an audio file. See [`Content::audio_bytes`].
+    pub fn audio_file<P: AsRef<Path>>(path: P) -> Result<Self, BinaryError> {
+        Content::audio_file_with_limit(path, binary::DEFAULT_MAX_SIZE)
+    }
+
+    /// Like [`Content::audio_file`], but failing if the file is over `limit` bytes.
+    pub fn audio_file_with_limit<P: AsRef<Path>>(
+        path: P,
+        limit: usize,
+    ) -> Result<Self, BinaryError> {
+        Content::audio_bytes_with_limit(&binary::read_file(path.as_ref(), limit)?, limit)
+    }
+
+    /// Replace anything that the given version of the protocol doesn't support. Resource links
+    /// were added in 2025-06-18, so older clients are given a text description of the resource.
+    pub fn for_protocol_version(self, version: &str) -> Self {
+        match self {
+            Content::ResourceLink(resource) if version < "2025-06-18" => {
+                let mut text = format!("Resource: {} ({})", resource.name, resource.uri);
+                if let Some(description) = &resource.description {
+                    text.push_str(&format!("\n{description}"));
+                }
+                Content::Text(TextContent {
+                    text,
+                    annotations: resource.annotations,
+                })
+            }
+            content => content,
+        }
+    }
+
     /// Set the audience for the content
     pub fn with_audience(mut self, audience: Vec<Role>) -> Self {
         let annotations = match &mut self {
@@ -121,6 +205,7 @@ impl Content {
             Content::Image(image) => &mut image.annotations,
             Content::Resource(resource) => &mut resource.annotations,
             Content::Audio(audio) => &mut audio.annotations,
+            Content::ResourceLink(resource) => &mut resource.annotations,
         };
         *annotations = Some(match annotations.take() {
             Some(mut a) => {
@@ -145,6 +230,10 @@ impl Content {
                 .as_ref()
                 .and_then(|a| a.audience.as_ref()),
             Content::Audio(audio) => audio.annotations.as_ref().and_then(|a| a.audience.as_ref()),
+            Content::ResourceLink(resource) => resource
+                .annotations
+                .as_ref()
+                .and_then(|a| a.audience.as_ref()),
         }
     }
 
@@ -155,6 +244,10 @@ impl Content {
             Content::Image(image) => Content::image(image.data.clone(), image.mime_type.clone()),
             Content::Resource(resource) => Content::resource(resource.resource.clone()),
             Content::Audio(audio) => Content::audio(audio.data.clone(), audio.mime_type.clone()),
+            Content::ResourceLink(resource) => Content::resource_link(Resource {
+                annotations: None,
+                ..resource.clone()
+            }),
         }
     }
 }
@@ -198,6 +291,39 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_resource_link() {
+        let resource = Resource::new(
+            "file:///src/main.rs",
+            Some("text/x-rust".to_string()),
+            None,
+            None,
+        )
+        .unwrap();
+        let link = Content::resource_link(resource).with_audience(vec![Role::Assistant]);
+        let json = serde_json::to_value(&link).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "type": "resource_link",
+                "uri": "file:///src/main.rs",
+                "name": "main.rs",
+                "mimeType": "text/x-rust",
+                "annotations": { "audience": ["assistant"] }
+            })
+        );
+        assert_eq!(serde_json::from_value::<Content>(json).unwrap(), link);
+        assert_eq!(link.unannotated().audience(), None);
+
+        // Older clients get a description of the resource instead
+        assert_eq!(link.clone().for_protocol_version("2025-06-18"), link);
+        assert_eq!(
+            link.for_protocol_version("2025-03-26"),
+            Content::text("Resource: main.rs (file:///src/main.rs)")
+                .with_audience(vec![Role::Assistant])
+        );
+    }
+
     #[test]
     fn test_unannotated() {
         let content = Content::text("hello").with_audience(vec![Role::User]);
@@ -356,8 +356,23 @@ impl ErrorData {
             data: None,
         }
     }
+
+    /// Attach additional information about the error, such as structured details a client can act
+    /// on.
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.data = Some(data);
+        self
+    }
 }
 
+impl std::fmt::Display for ErrorData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl std::error::Error for ErrorData {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -569,6 +584,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn error_with_custom_code_and_data() {
+        let error = ErrorData::new(ErrorCode::Custom(-32029), "Rate limited".to_string())
+            .with_data(json!({ "retryAfter": 30 }));
+        let serialized = serde_json::to_value(&error).unwrap();
+        assert_eq!(
+            serialized,
+            json!({ "code": -32029, "message": "Rate limited", "data": { "retryAfter": 30 } })
+        );
+        let deserialized: ErrorData = serde_json::from_value(serialized).unwrap();
+        assert_eq!(deserialized, error);
+    }
+
     #[test]
     fn error_deserialization() {
         let error = r#"{"jsonrpc":"2.0","id":42,"error":{"code":-32700,"message":"Parse error"}}"#;
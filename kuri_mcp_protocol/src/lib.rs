@@ -9,6 +9,7 @@ may use `kuri_mcp_protocol` by itself in your project, if you only want the prot
 
 The crate is organised into several modules:
 
+- [`binary`](binary/index.html) - Helpers for binary content (images, audio, etc.)
 - [`content`](content/index.html) - Content types for communication (text, images, etc.)
 - [`jsonrpc`](jsonrpc/index.html) - JSON-RPC protocol implementation
 - [`messages`](messages/index.html) - MCP message types
@@ -81,6 +82,7 @@ let tool = Tool::new(
 ```
 */
 
+pub mod binary;
 pub mod content;
 pub use content::{Annotations, Content, ImageContent, TextContent};
 pub mod jsonrpc;
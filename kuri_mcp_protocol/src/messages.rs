@@ -4,7 +4,7 @@
 use std::collections::HashMap;
 
 use crate::{
-    content::Content,
+    content::{Content, Role},
     prompt::{Prompt, PromptMessage},
     resource::{Resource, ResourceContents},
     tool::Tool,
@@ -12,8 +12,24 @@ use crate::{
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+/// The latest version of the protocol supported by these types.
+pub const LATEST_PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// The versions of the protocol supported by these types, oldest first.
+pub const SUPPORTED_PROTOCOL_VERSIONS: [&str; 3] =
+    ["2024-11-05", "2025-03-26", LATEST_PROTOCOL_VERSION];
+
+/// The version of the protocol to use, given the version requested by the client: the requested
+/// version if it's supported, otherwise the latest.
+pub fn negotiate_protocol_version(requested: &str) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS
+        .into_iter()
+        .find(|version| *version == requested)
+        .unwrap_or(LATEST_PROTOCOL_VERSION)
+}
+
 // https://spec.modelcontextprotocol.io/specification/2025-03-26/basic/lifecycle/#initialization
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InitializeParams {
     pub protocol_version: String,
@@ -22,7 +38,7 @@ pub struct InitializeParams {
 }
 
 // https://spec.modelcontextprotocol.io/specification/2025-03-26/basic/lifecycle/#initialization
-#[derive(Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ClientCapabilities {
     /// Experimental, non-standard capabilities that the client supports.
@@ -38,7 +54,7 @@ pub struct ClientCapabilities {
     pub sampling: Option<Value>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct ClientInfo {
     pub name: String,
     pub version: String,
@@ -83,19 +99,23 @@ pub struct ServerCapabilities {
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct PromptsCapability {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub list_changed: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ResourcesCapability {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub subscribe: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub list_changed: Option<bool>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct ToolsCapability {
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub list_changed: Option<bool>,
 }
 
@@ -124,10 +144,32 @@ pub struct ListToolsResult {
 #[serde(rename_all = "camelCase")]
 pub struct CallToolResult {
     pub content: Vec<Content>,
+    /// The tool's output as a JSON object, conforming to the tool's `output_schema` (if any).
+    /// Tools returning structured content should also include a text serialisation of it in
+    /// `content`, for clients that don't support structured output.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
     #[serde(default, skip_serializing_if = "std::ops::Not::not")]
     pub is_error: bool,
 }
 
+impl CallToolResult {
+    /// Remove anything that the given version of the protocol doesn't support. Structured content
+    /// was added in 2025-06-18; older clients rely on its text serialisation in `content`. See
+    /// also [`Content::for_protocol_version`].
+    pub fn for_protocol_version(mut self, version: &str) -> Self {
+        if version < "2025-06-18" {
+            self.structured_content = None;
+        }
+        self.content = self
+            .content
+            .into_iter()
+            .map(|content| content.for_protocol_version(version))
+            .collect();
+        self
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ListPromptsResult {
     pub prompts: Vec<Prompt>,
@@ -148,6 +190,62 @@ pub struct GetPromptResult {
     pub messages: Vec<PromptMessage>,
 }
 
+// https://spec.modelcontextprotocol.io/specification/2025-03-26/client/sampling/
+/// Params of a `sampling/createMessage` request, sent by a server to ask the client to sample
+/// from an LLM.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageParams {
+    pub messages: Vec<SamplingMessage>,
+    /// The server's preferences for which model to select. The client may ignore them.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    /// Which servers' context to include in the prompt: `none`, `thisServer` or `allServers`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_context: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    pub max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<Value>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SamplingMessage {
+    pub role: Role,
+    pub content: Content,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMessageResult {
+    pub role: Role,
+    pub content: Content,
+    /// The name of the model that generated the message.
+    pub model: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+// https://spec.modelcontextprotocol.io/specification/2025-03-26/client/roots/
+/// A directory or file that the server may operate on.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Root {
+    /// The root's URI. Currently, this must be a `file://` URI.
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ListRootsResult {
+    pub roots: Vec<Root>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,4 +1,6 @@
+use crate::binary::{self, BinaryError};
 use crate::content::{Annotations, AudioContent, EmbeddedResource, ImageContent};
+use crate::jsonrpc::ErrorData;
 use crate::resource::ResourceContents;
 use base64::engine::{general_purpose::STANDARD as BASE64_STANDARD, Engine};
 use serde::{Deserialize, Serialize};
@@ -65,11 +67,20 @@ pub enum PromptMessageContent {
     /// Plain text content
     Text { text: String },
     /// Image content with base64-encoded data
-    Image { image: ImageContent },
+    Image {
+        #[serde(flatten)]
+        image: ImageContent,
+    },
     /// Embedded server-side resource
-    Resource { resource: EmbeddedResource },
+    Resource {
+        #[serde(flatten)]
+        resource: EmbeddedResource,
+    },
     /// Audio content with base64-encoded data
-    Audio { audio: AudioContent },
+    Audio {
+        #[serde(flatten)]
+        audio: AudioContent,
+    },
 }
 
 /// A message in a prompt conversation
@@ -123,6 +134,37 @@ impl PromptMessage {
         })
     }
 
+    /// Create a new image message from raw image data, which is base64-encoded, and whose MIME
+    /// type is identified from the data. Fails if the data isn't a recognised image, or is over
+    /// the [`binary::DEFAULT_MAX_SIZE`].
+    pub fn new_image_bytes(
+        role: PromptMessageRole,
+        data: &[u8],
+        annotations: Option<Annotations>,
+    ) -> Result<Self, BinaryError> {
+        Self::new_image_bytes_with_limit(role, data, annotations, binary::DEFAULT_MAX_SIZE)
+    }
+
+    /// Like [`PromptMessage::new_image_bytes`], but failing if the data is over `limit` bytes.
+    pub fn new_image_bytes_with_limit(
+        role: PromptMessageRole,
+        data: &[u8],
+        annotations: Option<Annotations>,
+        limit: usize,
+    ) -> Result<Self, BinaryError> {
+        let (data, mime_type) = binary::encode_as(data, "image", limit)?;
+        Ok(Self {
+            role,
+            content: PromptMessageContent::Image {
+                image: ImageContent {
+                    data,
+                    mime_type: mime_type.to_string(),
+                    annotations,
+                },
+            },
+        })
+    }
+
     /// Create a new resource message
     pub fn new_resource(
         role: PromptMessageRole,
@@ -173,4 +215,13 @@ pub enum PromptError {
     InternalError(String),
     #[error("Prompt not found: {0}")]
     NotFound(String),
+    /// An error to send to the client as is, with its own code and data.
+    #[error("{0}")]
+    Rpc(ErrorData),
+}
+
+impl From<ErrorData> for PromptError {
+    fn from(err: ErrorData) -> Self {
+        PromptError::Rpc(err)
+    }
 }
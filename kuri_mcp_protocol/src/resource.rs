@@ -2,7 +2,10 @@ use serde::{Deserialize, Serialize};
 use thiserror::Error;
 use url::Url;
 
-use crate::content::Annotations;
+use crate::{
+    binary::{self, BinaryError},
+    content::Annotations,
+};
 
 /// A known resource that the server is capable of reading. This struct provides metadata about
 /// resources in list calls. Contents are provided by `ResourceContents`.
@@ -36,10 +39,11 @@ pub struct Resource {
 
 /// The contents of a resource, identified by the `uri` field in `Resource`.
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-#[serde(rename_all = "camelCase", untagged)]
+#[serde(untagged)]
 pub enum ResourceContents {
     /// Text resources contain UTF-8 encoded text data. They're most suitable for things like
     /// source code, config or log files, JSON/XML data or plain text.
+    #[serde(rename_all = "camelCase")]
     TextResourceContents {
         uri: String,
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -48,6 +52,7 @@ pub enum ResourceContents {
     },
     /// Binary resources contain raw binary data encoded in base64. They're most suitable for
     /// things like images, audio, video, or other non-text, binary data.
+    #[serde(rename_all = "camelCase")]
     BlobResourceContents {
         uri: String,
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -56,6 +61,27 @@ pub enum ResourceContents {
     },
 }
 
+impl ResourceContents {
+    /// Binary contents from raw data, which is base64-encoded. The MIME type is identified from
+    /// the data, if it's recognised. Fails if the data is over the [`binary::DEFAULT_MAX_SIZE`].
+    pub fn blob_bytes<S: Into<String>>(uri: S, data: &[u8]) -> Result<Self, BinaryError> {
+        Self::blob_bytes_with_limit(uri, data, binary::DEFAULT_MAX_SIZE)
+    }
+
+    /// Like [`ResourceContents::blob_bytes`], but failing if the data is over `limit` bytes.
+    pub fn blob_bytes_with_limit<S: Into<String>>(
+        uri: S,
+        data: &[u8],
+        limit: usize,
+    ) -> Result<Self, BinaryError> {
+        Ok(ResourceContents::BlobResourceContents {
+            uri: uri.into(),
+            mime_type: binary::sniff_mime_type(data).map(str::to_string),
+            blob: binary::encode_with_limit(data, limit)?,
+        })
+    }
+}
+
 // TODO: Consider a ResourceBuilder
 impl Resource {
     /// Creates a new Resource from a URI.
@@ -1,3 +1,4 @@
+use crate::jsonrpc::ErrorData;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
@@ -15,6 +16,13 @@ pub struct Tool {
     pub description: String,
     /// A JSON Schema object defining the expected parameters and the return format
     pub input_schema: Value,
+    /// An optional JSON Schema object describing the tool's structured output (returned in
+    /// `CallToolResult::structured_content`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_schema: Option<Value>,
+    /// Optional hints describing the tool's behaviour to the client
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
 }
 
 impl Tool {
@@ -28,8 +36,67 @@ impl Tool {
             name: name.into(),
             description: description.into(),
             input_schema,
+            output_schema: None,
+            annotations: None,
         }
     }
+
+    /// Set the schema describing the tool's structured output
+    pub fn with_output_schema(mut self, output_schema: Value) -> Self {
+        self.output_schema = Some(output_schema);
+        self
+    }
+
+    /// Set the annotations describing the tool's behaviour
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+
+    /// Remove anything that the given version of the protocol doesn't support: annotations were
+    /// added in 2025-03-26, and output schemas in 2025-06-18.
+    pub fn for_protocol_version(mut self, version: &str) -> Self {
+        // Versions are dates, so compare lexicographically
+        if version < "2025-03-26" {
+            self.annotations = None;
+        }
+        if version < "2025-06-18" {
+            self.output_schema = None;
+        }
+        self
+    }
+}
+
+/// Additional properties describing a tool's behaviour to clients. Clients may use these, for
+/// example, to decide whether to ask the user before calling a tool.
+///
+/// All properties are *hints*. They're not guaranteed to faithfully describe the tool's behaviour,
+/// and clients shouldn't rely on them when making decisions about tools from untrusted servers.
+///
+/// Spec: <https://modelcontextprotocol.io/specification/2025-03-26/server/tools#tool>
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolAnnotations {
+    /// A human-readable title for the tool
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// If true, the tool does not modify its environment. Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    /// If true, the tool may perform destructive updates to its environment. If false, the tool
+    /// performs only additive updates. Only meaningful when `read_only_hint` is false.
+    /// Default: true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    /// If true, calling the tool repeatedly with the same arguments has no additional effect on
+    /// its environment. Only meaningful when `read_only_hint` is false. Default: false
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    /// If true, the tool may interact with an "open world" of external entities (eg a web search
+    /// tool). If false, the tool's domain of interaction is closed (eg a memory tool).
+    /// Default: true
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
 }
 
 pub type ToolResult<T> = Result<T, ToolError>;
@@ -45,6 +112,17 @@ pub enum ToolError {
     SchemaError(String),
     #[error("Tool not found: {0}")]
     NotFound(String),
+    #[error("Internal error: {0}")]
+    InternalError(String),
+    /// An error to send to the client as is, with its own code and data.
+    #[error("{0}")]
+    Rpc(ErrorData),
+}
+
+impl From<ErrorData> for ToolError {
+    fn from(err: ErrorData) -> Self {
+        ToolError::Rpc(err)
+    }
 }
 
 /// Helper function to generate JSON schema for a type
@@ -87,4 +165,32 @@ mod tests {
         });
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_tool_annotations_serialisation() {
+        let tool = Tool::new("delete_file", "Delete a file", serde_json::json!({}))
+            .with_annotations(ToolAnnotations {
+                title: Some("Delete file".to_string()),
+                destructive_hint: Some(true),
+                idempotent_hint: Some(true),
+                ..Default::default()
+            });
+        let expected = serde_json::json!({
+            "name": "delete_file",
+            "description": "Delete a file",
+            "inputSchema": {},
+            "annotations": {
+                "title": "Delete file",
+                "destructiveHint": true,
+                "idempotentHint": true,
+            },
+        });
+        assert_eq!(serde_json::to_value(&tool).unwrap(), expected);
+
+        // Annotations are omitted when absent
+        let tool = Tool::new("echo", "Echo the input", serde_json::json!({}));
+        let actual = serde_json::to_value(&tool).unwrap();
+        assert!(actual.get("annotations").is_none());
+        assert_eq!(serde_json::from_value::<Tool>(actual).unwrap(), tool);
+    }
 }
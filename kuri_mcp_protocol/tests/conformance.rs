@@ -0,0 +1,434 @@
+mod schema;
+
+use kuri_mcp_protocol::{
+    content::{Annotations, AudioContent, Content, Role},
+    jsonrpc::{ErrorCode, ErrorData, MethodCall, Notification, Params, RequestId, ResponseItem},
+    messages::{
+        CallToolResult, ClientCapabilities, ClientInfo, CreateMessageParams, CreateMessageResult,
+        GetPromptRequest, GetPromptResult, Implementation, InitializeParams, InitializeResult,
+        ListPromptsResult, ListResourcesResult, ListRootsResult, ListToolsResult,
+        PromptsCapability, ReadResourceResult, ResourcesCapability, Root, RootsCapability,
+        SamplingMessage, ServerCapabilities, ToolsCapability,
+    },
+    prompt::{Prompt, PromptArgument, PromptMessage, PromptMessageContent, PromptMessageRole},
+    resource::{Resource, ResourceContents},
+    tool::{generate_tool_schema, Tool, ToolAnnotations},
+};
+use schema::Schema;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+// Conformance tests: serialised protocol types are checked against the specification's schema,
+// for each supported version of the protocol
+
+fn to_value<T: Serialize>(value: &T) -> Value {
+    serde_json::to_value(value).unwrap()
+}
+
+/// A request or notification, as the schema defines it: without its JSON-RPC envelope.
+fn message<T: Serialize>(method: &str, params: &T) -> Value {
+    json!({ "method": method, "params": to_value(params) })
+}
+
+fn text_resource(mime_type: Option<&str>) -> ResourceContents {
+    ResourceContents::TextResourceContents {
+        uri: "file:///notes.txt".to_string(),
+        mime_type: mime_type.map(str::to_string),
+        text: "Remember the milk".to_string(),
+    }
+}
+
+fn blob_resource(mime_type: Option<&str>) -> ResourceContents {
+    ResourceContents::BlobResourceContents {
+        uri: "file:///logo.png".to_string(),
+        mime_type: mime_type.map(str::to_string),
+        blob: "iVBORw0KGgo=".to_string(),
+    }
+}
+
+/// Content of every kind that `schema`'s version supports.
+fn contents(schema: &Schema) -> Vec<Content> {
+    let mut contents = vec![
+        Content::text("Hello"),
+        Content::text("Annotated").with_audience(vec![Role::User, Role::Assistant]),
+        Content::image("iVBORw0KGgo=", "image/png"),
+        Content::embedded_text("file:///notes.txt", "Remember the milk"),
+        Content::resource(text_resource(None)),
+        Content::resource(blob_resource(Some("image/png"))),
+    ];
+    if let Content::Text(text) = &mut contents[1] {
+        text.annotations.as_mut().unwrap().priority = Some(0.5);
+    }
+    if schema.since("2025-03-26") {
+        contents.push(Content::audio("UklGRg==", "audio/wav"));
+    }
+    contents
+}
+
+#[test]
+fn test_initialize() {
+    for schema in Schema::all() {
+        let params = InitializeParams {
+            protocol_version: schema.version().to_string(),
+            capabilities: ClientCapabilities {
+                experimental: Some(HashMap::from([("custom".to_string(), json!({}))])),
+                roots: Some(RootsCapability {
+                    list_changed: Some(true),
+                }),
+                sampling: Some(json!({})),
+            },
+            client_info: ClientInfo {
+                name: "Client".to_string(),
+                version: "1.0.0".to_string(),
+            },
+        };
+        schema.assert_valid("InitializeRequest", &message("initialize", &params));
+
+        let mut result = InitializeResult {
+            protocol_version: schema.version().to_string(),
+            capabilities: ServerCapabilities {
+                prompts: Some(PromptsCapability {
+                    list_changed: Some(true),
+                }),
+                resources: Some(ResourcesCapability {
+                    subscribe: Some(false),
+                    list_changed: Some(true),
+                }),
+                tools: Some(ToolsCapability {
+                    list_changed: Some(false),
+                }),
+            },
+            server_info: Implementation {
+                name: "Server".to_string(),
+                version: "1.0.0".to_string(),
+            },
+            instructions: Some("Be nice".to_string()),
+        };
+        schema.assert_valid("InitializeResult", &to_value(&result));
+
+        // Unset capability flags are omitted, rather than null
+        result.capabilities = ServerCapabilities {
+            prompts: Some(PromptsCapability { list_changed: None }),
+            resources: Some(ResourcesCapability {
+                subscribe: None,
+                list_changed: None,
+            }),
+            tools: Some(ToolsCapability { list_changed: None }),
+        };
+        result.instructions = None;
+        schema.assert_valid("InitializeResult", &to_value(&result));
+    }
+}
+
+#[test]
+fn test_tools() {
+    #[allow(dead_code)]
+    #[derive(serde::Deserialize, schemars::JsonSchema)]
+    struct Parameters {
+        /// The city
+        city: String,
+        days: Option<u8>,
+    }
+
+    for schema in Schema::all() {
+        // Fields from later versions are removed for earlier ones
+        let tools = vec![
+            Tool::new(
+                "forecast",
+                "Get a forecast",
+                generate_tool_schema::<Parameters>().unwrap(),
+            )
+            .with_output_schema(json!({
+                "type": "object",
+                "properties": { "summary": { "type": "string" } },
+                "required": ["summary"],
+            })),
+            Tool::new("now", "Get the time", json!({ "type": "object" })),
+            Tool::new("delete", "Delete a file", json!({ "type": "object" })).with_annotations(
+                ToolAnnotations {
+                    title: Some("Delete".to_string()),
+                    read_only_hint: Some(false),
+                    destructive_hint: Some(true),
+                    idempotent_hint: Some(true),
+                    open_world_hint: Some(false),
+                },
+            ),
+        ];
+        let tools = tools
+            .into_iter()
+            .map(|tool| tool.for_protocol_version(schema.version()))
+            .collect();
+        schema.assert_valid("ListToolsResult", &to_value(&ListToolsResult { tools }));
+
+        let params = json!({ "name": "forecast", "arguments": { "city": "Paris" } });
+        schema.assert_valid("CallToolRequest", &message("tools/call", &params));
+
+        for is_error in [false, true] {
+            let result = CallToolResult {
+                content: contents(&schema),
+                structured_content: Some(json!({ "summary": "Sunny" })),
+                is_error,
+            }
+            .for_protocol_version(schema.version());
+            assert_eq!(
+                result.structured_content.is_some(),
+                schema.since("2025-06-18")
+            );
+            schema.assert_valid("CallToolResult", &to_value(&result));
+        }
+    }
+}
+
+#[test]
+fn test_prompts() {
+    for schema in Schema::all() {
+        let result = ListPromptsResult {
+            prompts: vec![
+                Prompt::new(
+                    "review",
+                    Some("Review some code"),
+                    Some(vec![
+                        PromptArgument {
+                            name: "code".to_string(),
+                            description: Some("The code".to_string()),
+                            required: Some(true),
+                        },
+                        PromptArgument {
+                            name: "style".to_string(),
+                            description: None,
+                            required: None,
+                        },
+                    ]),
+                ),
+                Prompt::new("greet", None::<String>, None),
+            ],
+        };
+        schema.assert_valid("ListPromptsResult", &to_value(&result));
+
+        let params = GetPromptRequest {
+            name: "review".to_string(),
+            arguments: Some(HashMap::from([(
+                "code".to_string(),
+                "fn main() {}".to_string(),
+            )])),
+        };
+        schema.assert_valid("GetPromptRequest", &message("prompts/get", &params));
+
+        let annotations = Annotations {
+            audience: Some(vec![Role::User]),
+            priority: Some(1.0),
+        };
+        let mut result = GetPromptResult {
+            description: Some("A review".to_string()),
+            messages: vec![
+                PromptMessage::new_text(PromptMessageRole::User, "Review this"),
+                PromptMessage::new_image(
+                    PromptMessageRole::User,
+                    "iVBORw0KGgo=",
+                    "image/png",
+                    Some(annotations.clone()),
+                )
+                .unwrap(),
+                PromptMessage::new_resource(
+                    PromptMessageRole::Assistant,
+                    "file:///main.rs".to_string(),
+                    "text/x-rust".to_string(),
+                    Some("fn main() {}".to_string()),
+                    Some(annotations),
+                ),
+            ],
+        };
+        if schema.since("2025-03-26") {
+            result.messages.push(PromptMessage {
+                role: PromptMessageRole::User,
+                content: PromptMessageContent::Audio {
+                    audio: AudioContent {
+                        data: "UklGRg==".to_string(),
+                        mime_type: "audio/wav".to_string(),
+                        annotations: None,
+                    },
+                },
+            });
+        }
+        schema.assert_valid("GetPromptResult", &to_value(&result));
+    }
+}
+
+#[test]
+fn test_resources() {
+    for schema in Schema::all() {
+        let mut resource = Resource::new(
+            "file:///notes.txt",
+            Some("text/plain".to_string()),
+            None,
+            Some(Annotations {
+                audience: Some(vec![Role::Assistant]),
+                priority: None,
+            }),
+        )
+        .unwrap();
+        resource.description = Some("Some notes".to_string());
+        if schema.since("2025-03-26") {
+            resource.size = Some(17);
+        }
+        let result = ListResourcesResult {
+            resources: vec![
+                resource,
+                Resource::new("memo://insights", None, Some("Insights".to_string()), None).unwrap(),
+            ],
+        };
+        schema.assert_valid("ListResourcesResult", &to_value(&result));
+
+        let params = json!({ "uri": "file:///notes.txt" });
+        schema.assert_valid("ReadResourceRequest", &message("resources/read", &params));
+
+        let result = ReadResourceResult {
+            contents: vec![
+                text_resource(Some("text/plain")),
+                text_resource(None),
+                blob_resource(Some("image/png")),
+                blob_resource(None),
+            ],
+        };
+        schema.assert_valid("ReadResourceResult", &to_value(&result));
+    }
+}
+
+#[test]
+fn test_sampling_and_roots() {
+    for schema in Schema::all() {
+        let params = CreateMessageParams {
+            messages: vec![
+                SamplingMessage {
+                    role: Role::User,
+                    content: Content::text("What's in this image?"),
+                },
+                SamplingMessage {
+                    role: Role::User,
+                    content: Content::image("iVBORw0KGgo=", "image/png"),
+                },
+            ],
+            model_preferences: Some(json!({
+                "hints": [{ "name": "claude" }],
+                "costPriority": 0.3,
+                "intelligencePriority": 0.8,
+            })),
+            system_prompt: Some("You describe images".to_string()),
+            include_context: Some("thisServer".to_string()),
+            temperature: Some(0.7),
+            max_tokens: 100,
+            stop_sequences: Some(vec!["\n\n".to_string()]),
+            metadata: Some(json!({ "trace": "abc" })),
+        };
+        schema.assert_valid(
+            "CreateMessageRequest",
+            &message("sampling/createMessage", &params),
+        );
+
+        let result = CreateMessageResult {
+            role: Role::Assistant,
+            content: Content::text("A cat"),
+            model: "claude".to_string(),
+            stop_reason: Some("endTurn".to_string()),
+        };
+        schema.assert_valid("CreateMessageResult", &to_value(&result));
+
+        let result = ListRootsResult {
+            roots: vec![
+                Root {
+                    uri: "file:///home/user/project".to_string(),
+                    name: Some("Project".to_string()),
+                },
+                Root {
+                    uri: "file:///tmp".to_string(),
+                    name: None,
+                },
+            ],
+        };
+        schema.assert_valid("ListRootsResult", &to_value(&result));
+    }
+}
+
+#[test]
+fn test_jsonrpc() {
+    let params = |value: Value| Params::try_from(value).ok();
+
+    for schema in Schema::all() {
+        for id in [RequestId::Num(7), RequestId::Str("abc".to_string())] {
+            let request = MethodCall::new(id.clone(), "ping".to_string(), None);
+            schema.assert_valid_message(&to_value(&request), None);
+
+            let request = MethodCall::new(
+                id.clone(),
+                "tools/call".to_string(),
+                params(json!({ "name": "now" })),
+            );
+            schema.assert_valid_message(&to_value(&request), None);
+
+            let response = ResponseItem::success(id.clone(), json!({}));
+            schema.assert_valid_message(&to_value(&response), Some("ping"));
+
+            for code in [
+                ErrorCode::ParseError,
+                ErrorCode::InvalidRequest,
+                ErrorCode::MethodNotFound,
+                ErrorCode::InvalidParams,
+                ErrorCode::InternalError,
+                ErrorCode::Custom(-32002),
+            ] {
+                let mut error = ErrorData::new(code, "Something went wrong".to_string());
+                schema.assert_valid(
+                    "JSONRPCError",
+                    &to_value(&ResponseItem::error(id.clone(), error.clone())),
+                );
+
+                error.data = Some(json!({ "uri": "file:///missing.txt" }));
+                let response = ResponseItem::error(id.clone(), error);
+                schema.assert_valid_message(&to_value(&response), Some("resources/read"));
+            }
+        }
+
+        let notifications = [
+            Notification::new("notifications/initialized".to_string(), None),
+            Notification::new("notifications/tools/list_changed".to_string(), None),
+            Notification::new("notifications/prompts/list_changed".to_string(), None),
+            Notification::new("notifications/resources/list_changed".to_string(), None),
+            Notification::new("notifications/roots/list_changed".to_string(), None),
+            Notification::new(
+                "notifications/resources/updated".to_string(),
+                params(json!({ "uri": "file:///notes.txt" })),
+            ),
+            Notification::new(
+                "notifications/message".to_string(),
+                params(json!({ "level": "warning", "logger": "db", "data": { "retries": 3 } })),
+            ),
+            Notification::new(
+                "notifications/progress".to_string(),
+                params(json!({ "progressToken": "upload", "progress": 50, "total": 100 })),
+            ),
+            Notification::new(
+                "notifications/cancelled".to_string(),
+                params(json!({ "requestId": 7, "reason": "Took too long" })),
+            ),
+        ];
+        for notification in notifications {
+            schema.assert_valid_message(&to_value(&notification), None);
+        }
+    }
+}
+
+#[test]
+fn test_undeclared_properties_are_rejected() {
+    // The schema itself allows them, so a misspelt field would otherwise go unnoticed
+    for schema in Schema::all() {
+        let contents = json!({ "uri": "file:///notes.txt", "mime_type": "text/plain", "text": "" });
+        let errors = schema
+            .validate("TextResourceContents", &contents)
+            .unwrap_err();
+        assert!(errors[0].contains("mime_type"), "{errors:?}");
+
+        // Results remain open to extension
+        schema.assert_valid("EmptyResult", &json!({ "_meta": {}, "extra": true }));
+    }
+}
@@ -0,0 +1,52 @@
+#!/usr/bin/env bash
+# Vendor the specification's JSON schema for each supported protocol version, byte-for-byte, from
+# the upstream repository. The commit they were fetched from, and each file's SHA-256, are recorded
+# in SOURCE.
+#
+# Usage: fetch.sh [ref]   (a branch, tag or commit of the upstream repository)
+#
+# Without a ref, the files are fetched again from the commit recorded in SOURCE, so they only
+# change when a ref is given deliberately (eg `fetch.sh main` to update them).
+
+set -euo pipefail
+
+REPOSITORY=https://github.com/modelcontextprotocol/modelcontextprotocol
+VERSIONS=(2024-11-05 2025-03-26 2025-06-18)
+
+cd "$(dirname "$0")"
+if [[ $# -gt 0 ]]; then
+    ref=$1
+elif [[ -f SOURCE ]]; then
+    ref=$(sed -n 's/^commit: //p' SOURCE)
+else
+    echo "No schemas have been vendored yet: give a ref to fetch them from (eg main)" >&2
+    exit 1
+fi
+
+# Resolve the ref to a commit, so that every file comes from the same one
+if [[ $ref =~ ^[0-9a-f]{40}$ ]]; then
+    commit=$ref
+else
+    commit=$(git ls-remote "$REPOSITORY.git" "$ref" | head -n 1 | cut -f 1)
+    if [[ -z $commit ]]; then
+        echo "Couldn't resolve $ref in $REPOSITORY" >&2
+        exit 1
+    fi
+fi
+
+{
+    echo "# Fetched by fetch.sh. Don't edit the schema files by hand."
+    echo "repository: $REPOSITORY"
+    echo "commit: $commit"
+} > SOURCE.tmp
+
+for version in "${VERSIONS[@]}"; do
+    mkdir -p "$version"
+    curl --fail --silent --show-error --location \
+        --output "$version/schema.json" \
+        "https://raw.githubusercontent.com/modelcontextprotocol/modelcontextprotocol/$commit/schema/$version/schema.json"
+    echo "sha256 $version/schema.json: $(sha256sum "$version/schema.json" | cut -d ' ' -f 1)" >> SOURCE.tmp
+done
+
+mv SOURCE.tmp SOURCE
+cat SOURCE
@@ -0,0 +1,182 @@
+//! Validation of serialised messages against the MCP specification's JSON schema.
+//!
+//! Each supported protocol version's schema is vendored from the specification's repository as
+//! `<version>/schema.json`, byte-for-byte, by `fetch.sh`, which records the commit the files came
+//! from in `SOURCE`. The files are committed, so the suite doesn't depend on the network or on
+//! upstream changes; run `fetch.sh <ref>` to vendor (or update) them.
+//!
+//! Where a version's upstream file is missing, validation falls back to `transcribed/<version>.json`:
+//! a hand-written transcription of the definitions kuri sends and receives, in the same layout. It
+//! was written from kuri's own types, so it can't catch drift from the specification, and is only
+//! there until the upstream files are vendored. Set `KURI_REQUIRE_UPSTREAM_SCHEMA` (as CI does) to
+//! refuse the fallback.
+//!
+//! The schema allows properties it doesn't declare, so on its own it doesn't notice a field whose
+//! name has drifted from the spec's casing (eg `mime_type` rather than `mimeType`). Validation
+//! is therefore strict: every definition that declares its properties, and doesn't say otherwise,
+//! is treated as though it had `"additionalProperties": false`. Properties nested inside a
+//! definition (such as a tool's `inputSchema`) remain open.
+
+#![allow(dead_code)]
+
+use serde_json::{json, Value};
+use std::path::Path;
+
+/// The protocol versions with a vendored schema, oldest first.
+pub const VERSIONS: [&str; 3] = ["2024-11-05", "2025-03-26", "2025-06-18"];
+
+/// The schema for one version of the protocol.
+pub struct Schema {
+    version: &'static str,
+    document: Value,
+}
+
+impl Schema {
+    /// Load the vendored schema for `version`, or its transcription if it hasn't been vendored.
+    pub fn load(version: &'static str) -> Self {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../kuri_mcp_protocol/tests/schema");
+        let mut path = dir.join(version).join("schema.json");
+        if !path.exists() {
+            assert!(
+                std::env::var_os("KURI_REQUIRE_UPSTREAM_SCHEMA").is_none(),
+                "The {version} schema hasn't been vendored: run kuri_mcp_protocol/tests/schema/fetch.sh <ref>"
+            );
+            path = dir.join("transcribed").join(format!("{version}.json"));
+        }
+        let file = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Couldn't read {}: {e}", path.display()));
+        let mut document: Value = serde_json::from_str(&file).unwrap();
+
+        for definition in document["definitions"]
+            .as_object_mut()
+            .unwrap()
+            .values_mut()
+        {
+            let definition = definition.as_object_mut().unwrap();
+            if definition.contains_key("properties")
+                && !definition.contains_key("additionalProperties")
+            {
+                definition.insert("additionalProperties".to_string(), Value::Bool(false));
+            }
+        }
+
+        Self { version, document }
+    }
+
+    /// The schemas for every supported version.
+    pub fn all() -> Vec<Self> {
+        VERSIONS.into_iter().map(Self::load).collect()
+    }
+
+    pub fn version(&self) -> &'static str {
+        self.version
+    }
+
+    /// Whether this version is `version` or later.
+    pub fn since(&self, version: &str) -> bool {
+        // Versions are dates, so sort lexicographically
+        self.version >= version
+    }
+
+    /// Validate `instance` against the named definition, returning a description of each error.
+    pub fn validate(&self, definition: &str, instance: &Value) -> Result<(), Vec<String>> {
+        assert!(
+            self.document["definitions"].get(definition).is_some(),
+            "No definition `{definition}` in the {} schema",
+            self.version
+        );
+        let schema = json!({
+            "$schema": self.document["$schema"],
+            "allOf": [{ "$ref": format!("#/definitions/{definition}") }],
+            "definitions": self.document["definitions"],
+        });
+        let validator = jsonschema::draft7::options()
+            .should_validate_formats(true)
+            .build(&schema)
+            .unwrap();
+
+        let errors: Vec<_> = validator
+            .iter_errors(instance)
+            .map(|e| format!("at `{}`: {e}", e.instance_path))
+            .collect();
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Panic unless `instance` is valid against the named definition.
+    pub fn assert_valid(&self, definition: &str, instance: &Value) {
+        if let Err(errors) = self.validate(definition, instance) {
+            panic!(
+                "Not a valid `{definition}` in the {} schema:\n{}\n\n{}",
+                self.version,
+                serde_json::to_string_pretty(instance).unwrap(),
+                errors.join("\n")
+            );
+        }
+    }
+
+    /// Panic unless a JSON-RPC message from the server is valid. Responses are checked against the
+    /// result type of the method of the request they answer, and notifications and requests
+    /// against the definition for their method, where the schema has one.
+    pub fn assert_valid_message(&self, message: &Value, request_method: Option<&str>) {
+        self.assert_valid("JSONRPCMessage", message);
+
+        if let Some(result) = message.get("result") {
+            let method = request_method.expect("A response must answer a request");
+            if let Some(definition) = result_definition(method) {
+                self.assert_valid(definition, result);
+            }
+        } else if let Some(method) = message.get("method").and_then(Value::as_str) {
+            // The method's definition is of the message without its JSON-RPC envelope
+            let mut message = message.clone();
+            let inner = message.as_object_mut().unwrap();
+            inner.remove("jsonrpc");
+            inner.remove("id");
+            if let Some(definition) = method_definition(method) {
+                self.assert_valid(definition, &message);
+            }
+        }
+    }
+}
+
+/// The definition of the result of a request with the given method.
+pub fn result_definition(method: &str) -> Option<&'static str> {
+    Some(match method {
+        "initialize" => "InitializeResult",
+        "ping" => "EmptyResult",
+        "tools/list" => "ListToolsResult",
+        "tools/call" => "CallToolResult",
+        "prompts/list" => "ListPromptsResult",
+        "prompts/get" => "GetPromptResult",
+        "resources/list" => "ListResourcesResult",
+        "resources/read" => "ReadResourceResult",
+        "sampling/createMessage" => "CreateMessageResult",
+        "roots/list" => "ListRootsResult",
+        _ => return None,
+    })
+}
+
+/// The definition of a request or notification with the given method.
+pub fn method_definition(method: &str) -> Option<&'static str> {
+    Some(match method {
+        "initialize" => "InitializeRequest",
+        "ping" => "PingRequest",
+        "tools/call" => "CallToolRequest",
+        "prompts/get" => "GetPromptRequest",
+        "resources/read" => "ReadResourceRequest",
+        "sampling/createMessage" => "CreateMessageRequest",
+        "notifications/initialized" => "InitializedNotification",
+        "notifications/cancelled" => "CancelledNotification",
+        "notifications/progress" => "ProgressNotification",
+        "notifications/message" => "LoggingMessageNotification",
+        "notifications/tools/list_changed" => "ToolListChangedNotification",
+        "notifications/prompts/list_changed" => "PromptListChangedNotification",
+        "notifications/resources/list_changed" => "ResourceListChangedNotification",
+        "notifications/resources/updated" => "ResourceUpdatedNotification",
+        "notifications/roots/list_changed" => "RootsListChangedNotification",
+        _ => return None,
+    })
+}